@@ -24,15 +24,22 @@ _delta() {
         --highlight-removed
         --hunk-style
         --light
+        --line-numbers
+        --line-numbers-minus-style
+        --line-numbers-plus-style
+        --line-numbers-zero-style
         --list-languages
         --list-themes
         --list-theme-names
-        --minus-color
-        --minus-emph-color
-        --plus-color
-        --plus-emph-color
+        --minus-emph-style
+        --minus-moved-style
+        --minus-style
+        --plus-emph-style
+        --plus-moved-style
+        --plus-style
         --keep-plus-minus-markers
         --show-background-colors
+        --side-by-side
         --theme
         --version
         --width
@@ -0,0 +1,134 @@
+pub const ANSI_CSI_ERASE_IN_LINE: &str = "\x1b[K";
+pub const ANSI_SGR_RESET: &str = "\x1b[0m";
+
+const ESC: char = '\x1b';
+
+/// Split s after its first n visible characters, i.e. characters that are not part of an ANSI
+/// escape sequence. Escape sequences preceding the n-th visible character belong to the first
+/// part; escape sequences following it belong to the second.
+pub fn split_at_visible_char(s: &str, n: usize) -> (&str, &str) {
+    let mut visible = 0;
+    let mut i = 0;
+    while i < s.len() && visible < n {
+        match get_escape_sequence_length(&s[i..]) {
+            Some(length) => i += length,
+            None => {
+                i += s[i..].chars().next().map(char::len_utf8).unwrap_or(1);
+                visible += 1;
+            }
+        }
+    }
+    s.split_at(i)
+}
+
+/// Return the byte length of the ANSI escape sequence at the start of s, if there is one. CSI
+/// sequences (e.g. SGR color sequences) and OSC sequences (e.g. hyperlinks) are recognized.
+pub fn get_escape_sequence_length(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    if chars.next()?.1 != ESC {
+        return None;
+    }
+    match chars.next()? {
+        (_, '[') => {
+            // Parameter and intermediate bytes, terminated by a final byte in @-~.
+            for (i, c) in chars {
+                if ('@'..='~').contains(&c) {
+                    return Some(i + 1);
+                } else if !(' '..='?').contains(&c) {
+                    return None;
+                }
+            }
+            None
+        }
+        (_, ']') => {
+            // Terminated by BEL or by ESC \.
+            let mut previous = None;
+            for (i, c) in chars {
+                if c == '\x07' || c == '\\' && previous == Some(ESC) {
+                    return Some(i + 1);
+                }
+                previous = Some(c);
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Split s into its sections of visible text, each with a flag recording whether it is styled by
+/// the SGR sequences preceding it, e.g. as a match is colored in `git grep --color` output. Escape
+/// sequences are not included in the sections.
+pub fn get_styled_sections(s: &str) -> Vec<(bool, &str)> {
+    let mut sections = Vec::new();
+    let mut styled = false;
+    let mut section_start = 0;
+    let mut i = 0;
+    while i < s.len() {
+        match get_escape_sequence_length(&s[i..]) {
+            Some(length) => {
+                if i > section_start {
+                    sections.push((styled, &s[section_start..i]));
+                }
+                let sequence = &s[i..i + length];
+                if sequence.starts_with("\x1b[") && sequence.ends_with('m') {
+                    styled = !is_sgr_reset(&sequence[2..length - 1]);
+                }
+                i += length;
+                section_start = i;
+            }
+            None => i += s[i..].chars().next().map(char::len_utf8).unwrap_or(1),
+        }
+    }
+    if s.len() > section_start {
+        sections.push((styled, &s[section_start..]));
+    }
+    sections
+}
+
+/// Return true if the SGR parameters only reset attributes, e.g. "0", "" or "39;49".
+fn is_sgr_reset(parameters: &str) -> bool {
+    parameters
+        .split(';')
+        .all(|p| ["", "0", "22", "23", "24", "27", "39", "49"].contains(&p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_at_visible_char() {
+        assert_eq!(split_at_visible_char("| commit", 2), ("| ", "commit"));
+        assert_eq!(
+            split_at_visible_char("\x1b[31m|\x1b[m \x1b[33mcommit\x1b[m", 2),
+            ("\x1b[31m|\x1b[m ", "\x1b[33mcommit\x1b[m")
+        );
+        assert_eq!(split_at_visible_char("|", 2), ("|", ""));
+        assert_eq!(split_at_visible_char("ü|x", 2), ("ü|", "x"));
+    }
+
+    #[test]
+    fn test_get_escape_sequence_length() {
+        assert_eq!(get_escape_sequence_length("\x1b[31mx"), Some(5));
+        assert_eq!(get_escape_sequence_length("\x1b[mx"), Some(3));
+        assert_eq!(
+            get_escape_sequence_length("\x1b]8;;http://x\x1b\\x"),
+            Some(15)
+        );
+        assert_eq!(get_escape_sequence_length("x\x1b[31m"), None);
+        assert_eq!(get_escape_sequence_length("\x1b[31"), None);
+    }
+
+    #[test]
+    fn test_get_styled_sections() {
+        assert_eq!(get_styled_sections("a b"), vec![(false, "a b")]);
+        assert_eq!(
+            get_styled_sections("let \x1b[1;31mx\x1b[m = 1;"),
+            vec![(false, "let "), (true, "x"), (false, " = 1;")]
+        );
+        assert_eq!(
+            get_styled_sections("\x1b[0m\x1b[1m\x1b[31mx\x1b[0m\x1b[39m y"),
+            vec![(true, "x"), (false, " y")]
+        );
+    }
+}
@@ -19,6 +19,12 @@ pub struct HighlightingAssets {
     pub theme_set: ThemeSet,
 }
 
+impl Default for HighlightingAssets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HighlightingAssets {
     pub fn new() -> Self {
         Self::from_cache().unwrap_or_else(|_| Self::from_binary())
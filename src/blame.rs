@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use console::strip_ansi_codes;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::delta;
+use crate::line_numbers;
+use crate::paint::{self, Painter};
+use crate::parse;
+use crate::style;
+
+lazy_static! {
+    /// A line of `git blame` output, e.g.
+    /// "^1f2e3d4 src/x.rs (Dan Davison 2019-11-20 06:16:08 +0100  1) use std::io::Write;"
+    /// The file name is present only when lines come from more than one file.
+    static ref BLAME_LINE_REGEX: Regex = Regex::new(
+        r"^(\^?[0-9a-f]{4,40}) (?:(\S+) +)?\((.*?) +(\d{4}-\d{2}-\d{2}) \d{2}:\d{2}:\d{2} [+-]\d{4} +(\d+)\)(?: (.*))?$"
+    )
+    .unwrap();
+
+    /// The first line of a group in `git blame --porcelain` output: the commit, the line number
+    /// in the original file, the line number in the final file, and optionally the number of
+    /// lines in the group.
+    static ref PORCELAIN_HEADER_LINE_REGEX: Regex =
+        Regex::new(r"^([0-9a-f]{40}) (\d+) (\d+)(?: \d+)?$").unwrap();
+}
+
+/// Width of the commit hash in the blame gutter.
+const COMMIT_WIDTH: usize = 8;
+
+/// Width of the author name in the blame gutter.
+const AUTHOR_WIDTH: usize = 16;
+
+/// A line of code, together with the commit that last changed it.
+#[derive(Debug, PartialEq)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    pub date: String,
+    pub filename: Option<String>,
+    pub line_number: usize,
+    pub code: String,
+}
+
+/// The meaning of a line of blame input.
+#[derive(Debug, PartialEq)]
+pub enum BlameInput {
+    Code(BlameLine),
+    Metadata, // Commit metadata in porcelain output, which is not displayed
+    Other,
+}
+
+#[derive(Default)]
+struct CommitMetadata {
+    author: String,
+    author_time: Option<i64>,
+    author_tz: String,
+    filename: Option<String>,
+}
+
+/// Parser for `git blame` and `git blame --porcelain` output. Porcelain output gives the metadata
+/// of a commit only the first time the commit occurs, so the parser remembers it.
+#[derive(Default)]
+pub struct BlameParser {
+    commits: HashMap<String, CommitMetadata>,
+    current: Option<(String, usize)>, // Commit and final line number of the next code line
+}
+
+impl BlameParser {
+    pub fn parse(&mut self, line: &str) -> BlameInput {
+        if let Some(captures) = BLAME_LINE_REGEX.captures(line) {
+            let capture = |i| captures.get(i).map(|m| m.as_str().to_string());
+            return BlameInput::Code(BlameLine {
+                commit: capture(1).unwrap_or_default(),
+                filename: capture(2),
+                author: capture(3).unwrap_or_default(),
+                date: capture(4).unwrap_or_default(),
+                line_number: capture(5).and_then(|n| n.parse().ok()).unwrap_or(0),
+                code: capture(6).unwrap_or_default(),
+            });
+        }
+        if let Some(captures) = PORCELAIN_HEADER_LINE_REGEX.captures(line) {
+            let commit = captures[1].to_string();
+            let line_number = captures[3].parse().unwrap_or(0);
+            self.commits.entry(commit.clone()).or_default();
+            self.current = Some((commit, line_number));
+            return BlameInput::Metadata;
+        }
+        let (commit, line_number) = match &self.current {
+            Some(current) => current.clone(),
+            None => return BlameInput::Other,
+        };
+        let metadata = self.commits.entry(commit.clone()).or_default();
+        if let Some(code) = line.strip_prefix('\t') {
+            self.current = None;
+            BlameInput::Code(BlameLine {
+                date: metadata
+                    .author_time
+                    .map(|time| format_date(time, &metadata.author_tz))
+                    .unwrap_or_default(),
+                author: metadata.author.clone(),
+                filename: metadata.filename.clone(),
+                commit,
+                line_number,
+                code: code.to_string(),
+            })
+        } else {
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+            match key {
+                "author" => metadata.author = value.to_string(),
+                "author-time" => metadata.author_time = value.parse().ok(),
+                "author-tz" => metadata.author_tz = value.to_string(),
+                "filename" => metadata.filename = Some(value.to_string()),
+                _ => {}
+            }
+            BlameInput::Metadata
+        }
+    }
+}
+
+/// Return true if the line is the first line of `git blame` or `git blame --porcelain` output.
+pub fn is_blame_line(line: &str) -> bool {
+    BLAME_LINE_REGEX.is_match(line) || PORCELAIN_HEADER_LINE_REGEX.is_match(line)
+}
+
+/// Paint blame output: each line of code is syntax highlighted, and preceded by a gutter holding
+/// the commit, author and date, and the line number. Consecutive lines from the same commit form
+/// a group, and the commit metadata is shown only on the first line of the group.
+pub fn paint_blame<I>(lines: I, painter: &mut Painter, config: &Config) -> std::io::Result<()>
+where
+    I: Iterator<Item = String>,
+{
+    let mut parser = BlameParser::default();
+    let mut previous_commit: Option<String> = None;
+    for raw_line in lines {
+        let line = strip_ansi_codes(&raw_line).to_string();
+        match parser.parse(&line) {
+            BlameInput::Code(blame_line) => {
+                if previous_commit.is_none() {
+                    match &blame_line.filename {
+                        Some(filename) => painter.set_syntax(
+                            parse::get_file_extension_from_file_meta_line_file_path(filename),
+                        ),
+                        None => painter.set_syntax_by_first_line(&blame_line.code),
+                    }
+                    painter.set_highlighter();
+                }
+                let is_group_start = previous_commit.as_ref() != Some(&blame_line.commit);
+                painter
+                    .output_buffer
+                    .push_str(&format_gutter(&blame_line, is_group_start, config));
+                let code = delta::prepare(&blame_line.code, 0, true, config);
+                let syntax_style_sections = Painter::get_line_syntax_style_sections(
+                    &code,
+                    &mut painter.highlighter,
+                    painter.config,
+                    true,
+                );
+                Painter::paint_lines(
+                    vec![syntax_style_sections],
+                    vec![vec![(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER, &code)]],
+                    &mut painter.output_buffer,
+                    config,
+                    "",
+                    style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+                    Some(false),
+                );
+                previous_commit = Some(blame_line.commit);
+                painter.emit()?;
+            }
+            BlameInput::Metadata => {}
+            BlameInput::Other => writeln!(painter.writer, "{}", raw_line)?,
+        }
+    }
+    Ok(())
+}
+
+fn format_gutter(blame_line: &BlameLine, is_group_start: bool, config: &Config) -> String {
+    let metadata = if is_group_start {
+        format!(
+            "{:commit_width$} {:author_width$} {}",
+            blame_line
+                .commit
+                .chars()
+                .take(COMMIT_WIDTH)
+                .collect::<String>(),
+            blame_line
+                .author
+                .chars()
+                .take(AUTHOR_WIDTH)
+                .collect::<String>(),
+            blame_line.date,
+            commit_width = COMMIT_WIDTH,
+            author_width = AUTHOR_WIDTH,
+        )
+    } else {
+        "".to_string()
+    };
+    format!(
+        "{}{} {}",
+        paint::paint_text_foreground(
+            &format!("{:width$}", metadata, width = get_metadata_width()),
+            config.commit_color,
+            config.true_color
+        ),
+        paint::paint_text_foreground("│", config.commit_color, config.true_color),
+        line_numbers::format_line_number_panel(
+            Some(blame_line.line_number),
+            config.line_numbers_zero_style_modifier,
+            line_numbers::MIN_LINE_NUMBER_WIDTH,
+            config
+        ),
+    )
+}
+
+/// The width of the commit, author and date in the gutter.
+fn get_metadata_width() -> usize {
+    COMMIT_WIDTH + 1 + AUTHOR_WIDTH + 1 + "YYYY-MM-DD".len()
+}
+
+/// Format a Unix timestamp as a date, e.g. "2019-11-20", in the time zone given by an offset such
+/// as "+0100".
+fn format_date(time: i64, tz: &str) -> String {
+    let offset_minutes = tz
+        .get(1..3)
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .zip(tz.get(3..5).and_then(|minutes| minutes.parse::<i64>().ok()))
+        .map(|(hours, minutes)| hours * 60 + minutes)
+        .unwrap_or(0);
+    let offset_minutes = if tz.starts_with('-') {
+        -offset_minutes
+    } else {
+        offset_minutes
+    };
+    let days = (time + offset_minutes * 60).div_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert a number of days since 1970-01-01 to a (year, month, day) date in the proleptic
+/// Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blame_line() {
+        let mut parser = BlameParser::default();
+        assert_eq!(
+            parser
+                .parse("^1f2e3d4 (Dan Davison       2019-11-20 06:16:08 +0100  12)     let x = 1;"),
+            BlameInput::Code(BlameLine {
+                commit: "^1f2e3d4".to_string(),
+                author: "Dan Davison".to_string(),
+                date: "2019-11-20".to_string(),
+                filename: None,
+                line_number: 12,
+                code: "    let x = 1;".to_string(),
+            })
+        );
+        assert_eq!(
+            parser.parse("1f2e3d4a src/x.rs (A B 2019-11-20 06:16:08 -0500 3) "),
+            BlameInput::Code(BlameLine {
+                commit: "1f2e3d4a".to_string(),
+                author: "A B".to_string(),
+                date: "2019-11-20".to_string(),
+                filename: Some("src/x.rs".to_string()),
+                line_number: 3,
+                code: "".to_string(),
+            })
+        );
+        assert_eq!(parser.parse("diff --git a/x b/x"), BlameInput::Other);
+    }
+
+    #[test]
+    fn test_parse_porcelain_blame() {
+        let mut parser = BlameParser::default();
+        let mut code_lines = Vec::new();
+        for line in BLAME_PORCELAIN.lines() {
+            if let BlameInput::Code(blame_line) = parser.parse(line) {
+                code_lines.push(blame_line);
+            }
+        }
+        assert_eq!(code_lines.len(), 3);
+        assert_eq!(code_lines[0].author, "Dan Davison");
+        assert_eq!(code_lines[0].date, "2019-11-20");
+        assert_eq!(code_lines[0].filename, Some("src/main.rs".to_string()));
+        assert_eq!(code_lines[0].code, "use std::io;");
+        // The metadata of a commit is given only once.
+        assert_eq!(code_lines[2].commit, code_lines[0].commit);
+        assert_eq!(code_lines[2].author, "Dan Davison");
+        assert_eq!(code_lines[2].line_number, 3);
+        assert_eq!(code_lines[1].author, "Someone Else");
+    }
+
+    #[test]
+    fn test_is_blame_line() {
+        assert!(is_blame_line(
+            "^1f2e3d4 (Dan Davison 2019-11-20 06:16:08 +0100 1) use std::io;"
+        ));
+        assert!(is_blame_line(
+            "94907c0f136f46dc46ffae2dc92dca9af7eb7c2e 1 1 2"
+        ));
+        assert!(!is_blame_line(
+            "commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"
+        ));
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0, "+0000"), "1970-01-01");
+        assert_eq!(format_date(1574226968, "+0100"), "2019-11-20");
+        assert_eq!(format_date(1574226968, "-1000"), "2019-11-19");
+        assert_eq!(format_date(951782400, "+0000"), "2000-02-29");
+    }
+
+    const BLAME_PORCELAIN: &str = "\
+94907c0f136f46dc46ffae2dc92dca9af7eb7c2e 1 1 1
+author Dan Davison
+author-mail <dandavison7@gmail.com>
+author-time 1574226968
+author-tz +0100
+committer Dan Davison
+committer-mail <dandavison7@gmail.com>
+committer-time 1574226968
+committer-tz +0100
+summary Initial commit
+boundary
+filename src/main.rs
+\tuse std::io;
+6b2aeb0b8cb9f4cd4e1b9e4a8ed04a6eab36b9f3 2 2 1
+author Someone Else
+author-mail <someone@example.com>
+author-time 1575740102
+author-tz -0500
+committer Someone Else
+committer-mail <someone@example.com>
+committer-time 1575740102
+committer-tz -0500
+summary Second commit
+previous 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e src/main.rs
+filename src/main.rs
+\tuse std::process;
+94907c0f136f46dc46ffae2dc92dca9af7eb7c2e 3 3 1
+\tfn main() {}";
+}
@@ -8,6 +8,7 @@ use structopt::StructOpt;
 use crate::bat::assets::HighlightingAssets;
 use crate::bat::output::PagingMode;
 use crate::config;
+use crate::encoding::Encoding;
 use crate::env;
 use crate::style;
 
@@ -19,12 +20,12 @@ use crate::style;
 Colors
 ------
 
-All delta color options work the same way. There are three ways to specify a color:
+The colors of style strings (see Styles below) are specified in one of three ways:
 
 1. RGB hex code
 
    An example of using an RGB hex code is:
-   --file-color=\"#0e7c0e\"
+   --file-style=\"#0e7c0e\"
 
 2. ANSI color name
 
@@ -35,7 +36,7 @@ All delta color options work the same way. There are three ways to specify a col
    bright-black, bright-red, bright-green, bright-yellow, bright-blue, bright-magenta, bright-cyan, bright-white
 
    An example of using an ANSI color name is:
-   --file-color=\"green\"
+   --file-style=\"green\"
 
    Unlike RGB hex codes, ANSI color names are just names: you can choose the exact color that each
    name corresponds to in the settings of your terminal application (the application you use to
@@ -48,12 +49,32 @@ All delta color options work the same way. There are three ways to specify a col
 3. ANSI color number
 
    An example of using an ANSI color number is:
-   --file-color=28
+   --file-style=28
 
    There are 256 ANSI color numbers: 0-255. The first 16 are the same as the colors described in
    the \"ANSI color name\" section above. See https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit.
    Specifying colors like this is useful if your terminal only supports 256 colors (i.e. doesn\'t
    support 24-bit color).
+
+Styles
+------
+
+Every element of the output has a style, given by its --*-style option. These options take a
+style string: a list of words, in any order, such as --minus-style=\"bold italic #ffaa00 ul\". The
+words are:
+
+- up to two colors, written as described above: the first is the foreground color, and the second
+  the background color. \"normal\" means no color, and \"syntax\" (as a foreground color only)
+  means the colors of syntax highlighting.
+- the attributes \"bold\", \"italic\" and \"ul\" (underlined).
+- for --commit-style, --file-style and --hunk-style only, the decoration of the section: \"box\",
+  \"underline\", \"plain\" or \"omit\".
+
+The parts of a style that are not given keep their default. For example --plus-style=bold makes
+added lines bold, keeping their syntax highlighting and their background color.
+
+The --*-color options of earlier versions are deprecated: each sets a color of the default style of
+its element, e.g. --minus-color=red is --minus-style=\"normal red\".
 "
 )]
 pub struct Opt {
@@ -67,28 +88,75 @@ pub struct Opt {
     #[structopt(long = "dark")]
     pub dark: bool,
 
-    #[structopt(long = "minus-color")]
-    /// The background color to use for removed lines.
+    #[structopt(long = "minus-style")]
+    /// Style string for removed lines (see Styles below), e.g. "bold normal red". By default
+    /// removed lines have a red background, and are not syntax highlighted (see
+    /// --highlight-removed).
+    pub minus_style: Option<String>,
+
+    #[structopt(long = "minus-emph-style")]
+    /// Style string for emphasized sections of removed lines (see Styles below). By default they
+    /// have a brighter red background.
+    pub minus_emph_style: Option<String>,
+
+    #[structopt(long = "plus-style")]
+    /// Style string for added lines (see Styles below). By default added lines are syntax
+    /// highlighted, with a green background.
+    pub plus_style: Option<String>,
+
+    #[structopt(long = "plus-emph-style")]
+    /// Style string for emphasized sections of added lines (see Styles below). By default they
+    /// have a brighter green background.
+    pub plus_emph_style: Option<String>,
+
+    #[structopt(long = "minus-moved-style")]
+    /// Style string for removed lines of moved code (see Styles below): lines that git colors as
+    /// moved, when its output is colored with --color-moved, or lines found by --detect-moved. By
+    /// default they have a purple background.
+    pub minus_moved_style: Option<String>,
+
+    #[structopt(long = "plus-moved-style")]
+    /// Style string for added lines of moved code (see Styles below). By default they have a blue
+    /// background.
+    pub plus_moved_style: Option<String>,
+
+    #[structopt(long = "zero-style")]
+    /// Style string for unchanged lines (see Styles below), e.g. "syntax #222222". By default
+    /// unchanged lines are syntax highlighted, with no background color.
+    pub zero_style: Option<String>,
+
+    #[structopt(long = "minus-color", hidden = true)]
+    /// Deprecated: use --minus-style. The background color of removed lines.
     pub minus_color: Option<String>,
 
-    #[structopt(long = "minus-emph-color")]
-    /// The background color to use for emphasized sections of removed lines.
+    #[structopt(long = "minus-emph-color", hidden = true)]
+    /// Deprecated: use --minus-emph-style. The background color of emphasized sections of removed
+    /// lines.
     pub minus_emph_color: Option<String>,
 
-    #[structopt(long = "plus-color")]
-    /// The background color to use for added lines.
+    #[structopt(long = "plus-color", hidden = true)]
+    /// Deprecated: use --plus-style. The background color of added lines.
     pub plus_color: Option<String>,
 
-    #[structopt(long = "plus-emph-color")]
-    /// The background color to use for emphasized sections of added lines.
+    #[structopt(long = "plus-emph-color", hidden = true)]
+    /// Deprecated: use --plus-emph-style. The background color of emphasized sections of added
+    /// lines.
     pub plus_emph_color: Option<String>,
 
+    #[structopt(long = "minus-moved-color", hidden = true)]
+    /// Deprecated: use --minus-moved-style. The background color of removed lines of moved code.
+    pub minus_moved_color: Option<String>,
+
+    #[structopt(long = "plus-moved-color", hidden = true)]
+    /// Deprecated: use --plus-moved-style. The background color of added lines of moved code.
+    pub plus_moved_color: Option<String>,
+
     #[structopt(long = "theme", env = "BAT_THEME")]
     /// The code syntax highlighting theme to use. Use --theme=none to disable syntax highlighting.
     /// If the theme is not set using this option, it will be taken from the BAT_THEME environment
     /// variable, if that contains a valid theme name. Use --list-themes to view available themes.
-    /// Note that the choice of theme only affects code syntax highlighting. See --commit-color,
-    /// --file-color, --hunk-color to configure the colors of other parts of the diff output.
+    /// Note that the choice of theme only affects code syntax highlighting. See --commit-style,
+    /// --file-style, --hunk-style to configure the styles of other parts of the diff output.
     pub theme: Option<String>,
 
     #[structopt(long = "highlight-removed")]
@@ -96,43 +164,107 @@ pub struct Opt {
     /// apply syntax highlighting to unchanged and new lines only.
     pub highlight_removed: bool,
 
+    #[structopt(long = "detect-moved")]
+    /// Detect blocks of removed lines that are added elsewhere in the diff, even in another file,
+    /// and paint them as moved code. Edits within a moved block are emphasized. The whole diff is
+    /// read before any of it is displayed.
+    pub detect_moved: bool,
+
     #[structopt(long = "color-only")]
-    /// Do not alter the input in any way other than applying colors. Equivalent to
-    /// `--keep-plus-minus-markers --width variable --tabs 0 --commit-style plain
-    ///  --file-style plain --hunk-style plain`.
+    /// Do not alter the input in any way other than applying colors: each input line is
+    /// written as exactly one output line, as required by git's interactive.diffFilter.
+    /// Equivalent to `--keep-plus-minus-markers --width variable --tabs 0 --commit-style plain
+    ///  --file-style plain --hunk-style plain`, and disables --side-by-side, --line-numbers,
+    /// and the display of blame and grep output.
     pub color_only: bool,
 
+    #[structopt(long = "side-by-side")]
+    /// Display a side-by-side diff view instead of the traditional view. Removed lines are shown
+    /// in the left column and added lines in the right column, each taking half of the terminal
+    /// width. Lines that delta infers to be homologous are shown on the same row. Combined diffs
+    /// of merge commits are shown in the traditional view.
+    pub side_by_side: bool,
+
+    #[structopt(long = "line-numbers")]
+    /// Display line numbers in a gutter to the left of each line: the line number in the old file
+    /// and the line number in the new file. In side-by-side mode each panel shows the line number
+    /// of its own file.
+    pub line_numbers: bool,
+
+    #[structopt(long = "line-numbers-minus-style")]
+    /// Style string for the line number of a removed line (see Styles below). The default is
+    /// "red".
+    pub line_numbers_minus_style: Option<String>,
+
+    #[structopt(long = "line-numbers-plus-style")]
+    /// Style string for the line number of an added line (see Styles below). The default is
+    /// "green".
+    pub line_numbers_plus_style: Option<String>,
+
+    #[structopt(long = "line-numbers-zero-style")]
+    /// Style string for the line numbers of an unchanged line, and for the gutter separators (see
+    /// Styles below). The default is "bright-black".
+    pub line_numbers_zero_style: Option<String>,
+
+    #[structopt(long = "line-numbers-minus-color", hidden = true)]
+    /// Deprecated: use --line-numbers-minus-style. The color of the line number of a removed line.
+    pub line_numbers_minus_color: Option<String>,
+
+    #[structopt(long = "line-numbers-plus-color", hidden = true)]
+    /// Deprecated: use --line-numbers-plus-style. The color of the line number of an added line.
+    pub line_numbers_plus_color: Option<String>,
+
+    #[structopt(long = "line-numbers-zero-color", hidden = true)]
+    /// Deprecated: use --line-numbers-zero-style. The color of the line numbers of an unchanged
+    /// line, and of the gutter separators.
+    pub line_numbers_zero_color: Option<String>,
+
     #[structopt(long = "keep-plus-minus-markers")]
     /// Prefix added/removed lines with a +/- character, respectively, exactly as git does. The
     /// default behavior is to output a space character in place of these markers.
     pub keep_plus_minus_markers: bool,
 
-    #[structopt(long = "commit-style", default_value = "plain")]
-    /// Formatting style for the commit section of git output. Options
-    /// are: plain, box.
-    pub commit_style: SectionStyle,
+    #[structopt(long = "commit-style")]
+    /// Style string for the commit section of git output (see Styles below). Its decoration is
+    /// one of: plain, box, omit. The default is "plain yellow".
+    pub commit_style: Option<String>,
+
+    #[structopt(long = "commit-color", hidden = true)]
+    /// Deprecated: use --commit-style. The color of the commit section of git output.
+    pub commit_color: Option<String>,
 
-    #[structopt(long = "commit-color", default_value = "yellow")]
-    /// Color for the commit section of git output.
-    pub commit_color: String,
+    #[structopt(long = "commit-hash-length")]
+    /// Abbreviate the hashes of the commit section of git output to this number of characters.
+    pub commit_hash_length: Option<usize>,
 
-    #[structopt(long = "file-style", default_value = "underline")]
-    /// Formatting style for the file section of git output. Options
-    /// are: plain, box, underline.
-    pub file_style: SectionStyle,
+    #[structopt(long = "relative-dates")]
+    /// Show the dates of the commit section of git output relative to the current time, e.g. "3
+    /// days ago", as git does with --relative-date.
+    pub relative_dates: bool,
 
-    #[structopt(long = "file-color", default_value = "blue")]
-    /// Color for the file section of git output.
-    pub file_color: String,
+    #[structopt(long = "commit-message-markdown")]
+    /// Highlight the body of commit messages as Markdown, using the syntax highlighting theme.
+    pub commit_message_markdown: bool,
 
-    #[structopt(long = "hunk-style", default_value = "box")]
-    /// Formatting style for the hunk-marker section of git output. Options
-    /// are: plain, box.
-    pub hunk_style: SectionStyle,
+    #[structopt(long = "file-style")]
+    /// Style string for the file section of git output (see Styles below). Its decoration is one
+    /// of: plain, box, underline, omit. The default is "underline blue".
+    pub file_style: Option<String>,
 
-    #[structopt(long = "hunk-color", default_value = "blue")]
-    /// Color for the hunk-marker section of git output.
-    pub hunk_color: String,
+    #[structopt(long = "file-color", hidden = true)]
+    /// Deprecated: use --file-style. The color of the file section of git output.
+    pub file_color: Option<String>,
+
+    #[structopt(long = "hunk-style")]
+    /// Style string for the hunk-marker section of git output (see Styles below). Its decoration
+    /// is one of: plain, box, omit. The decoration and the line number are drawn in its
+    /// foreground color, and the code fragment of the hunk header is syntax highlighted. The
+    /// default is "box blue".
+    pub hunk_style: Option<String>,
+
+    #[structopt(long = "hunk-color", hidden = true)]
+    /// Deprecated: use --hunk-style. The color of the hunk-marker section of git output.
+    pub hunk_color: Option<String>,
 
     /// Use --width=variable to extend background colors to the end of each line only. Otherwise
     /// background colors extend to the full terminal width.
@@ -146,11 +278,17 @@ pub struct Opt {
     #[structopt(long = "tabs", default_value = "4")]
     pub tab_width: usize,
 
+    /// The encoding used to display input lines that are not valid UTF-8. Options are utf-8,
+    /// latin1 (ISO-8859-1), and cp1252 (Windows-1252). With utf-8, the bytes of such lines that
+    /// are not valid UTF-8 are written unchanged.
+    #[structopt(long = "encoding", default_value = "utf-8")]
+    pub encoding: String,
+
     /// Show the command-line arguments (RGB hex codes) for the background colors that are in
     /// effect. The hex codes are displayed with their associated background color. This option can
     /// be combined with --light and --dark to view the background colors for those modes. It can
     /// also be used to experiment with different RGB hex codes by combining this option with
-    /// --minus-color, --minus-emph-color, --plus-color, --plus-emph-color.
+    /// --minus-style, --minus-emph-style, --plus-style, --plus-emph-style.
     #[structopt(long = "show-background-colors")]
     pub show_background_colors: bool,
 
@@ -211,6 +349,7 @@ impl FromStr for SectionStyle {
             "box" => Ok(SectionStyle::Box),
             "plain" => Ok(SectionStyle::Plain),
             "underline" => Ok(SectionStyle::Underline),
+            "omit" => Ok(SectionStyle::Omit),
             _ => Err(Error::SectionStyleParseError),
         }
     }
@@ -289,6 +428,19 @@ pub fn process_command_line_arguments<'a>(
         }
     };
 
+    let encoding = match opt.encoding.to_lowercase().as_ref() {
+        "utf-8" | "utf8" => Encoding::Utf8,
+        "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+        "cp1252" | "windows-1252" => Encoding::Windows1252,
+        _ => {
+            eprintln!(
+                "Invalid value for --encoding option: {} (valid values are \"utf-8\", \"latin1\", and \"cp1252\")",
+                opt.encoding
+            );
+            process::exit(1);
+        }
+    };
+
     config::get_config(
         opt,
         &assets.syntax_set,
@@ -296,6 +448,7 @@ pub fn process_command_line_arguments<'a>(
         true_color,
         available_terminal_width,
         paging_mode,
+        encoding,
     )
 }
 
@@ -0,0 +1,128 @@
+use crate::ansi;
+use crate::config::Config;
+use crate::edits;
+use crate::paint::DiffStyleSections;
+
+/// Return true if a removed or added line is colored as moved code by `git diff --color-moved`.
+/// The color of a line is that of its marker column. With git's default colors, removed lines are
+/// red and added lines green; moved lines are magenta (or blue, for alternate blocks of the
+/// "zebra" modes) when removed, and cyan (or yellow) when added. The "dimmed-zebra" mode dims the
+/// moved lines that are not at the edge of a block.
+pub fn is_moved_line(raw_line: &str, marker: char) -> bool {
+    let (is_dim, foreground) = get_marker_sgr_attributes(raw_line);
+    let moved_colors: &[u8] = match marker {
+        '-' => &[35, 95, 34, 94],
+        '+' => &[36, 96, 33, 93],
+        _ => return false,
+    };
+    is_dim || matches!(foreground, Some(color) if moved_colors.contains(&color))
+}
+
+/// Return the diff style sections of moved lines: a moved line is painted as a whole in the
+/// style of moved code, since it is unchanged from the line it was moved from or to. If it was
+/// changed by the move, the given counterpart is the line at the other end of the move, and the
+/// edits between them are emphasized. Lines moved from and to elsewhere are not homologous, so
+/// each is given a row of its own.
+pub fn get_diff_style_sections<'a>(
+    minus_lines: &'a [String],
+    plus_lines: &'a [String],
+    minus_counterparts: &'a [Option<String>],
+    plus_counterparts: &'a [Option<String>],
+    config: &Config,
+) -> DiffStyleSections<'a> {
+    let infer_edits = |minus_line: &'a String, plus_line: &'a String| {
+        edits::infer_edits(
+            std::slice::from_ref(minus_line),
+            std::slice::from_ref(plus_line),
+            config.minus_moved_style_modifier,
+            config.minus_emph_style_modifier,
+            config.plus_moved_style_modifier,
+            config.plus_emph_style_modifier,
+            config.max_line_distance,
+            config.max_line_distance_for_naively_paired_lines,
+        )
+    };
+    let minus_sections = minus_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match minus_counterparts.get(i) {
+            Some(Some(counterpart)) => infer_edits(line, counterpart).0.remove(0),
+            _ => vec![(config.minus_moved_style_modifier, line.as_str())],
+        })
+        .collect();
+    let plus_sections = plus_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match plus_counterparts.get(i) {
+            Some(Some(counterpart)) => infer_edits(counterpart, line).1.remove(0),
+            _ => vec![(config.plus_moved_style_modifier, line.as_str())],
+        })
+        .collect();
+    let line_alignment = (0..minus_lines.len())
+        .map(|i| (Some(i), None))
+        .chain((0..plus_lines.len()).map(|i| (None, Some(i))))
+        .collect();
+    (minus_sections, plus_sections, line_alignment)
+}
+
+/// Return whether the marker column of a line is dimmed, and its basic foreground color code
+/// (e.g. 31 for red, or 91 for bright red), according to the SGR sequences preceding it.
+fn get_marker_sgr_attributes(raw_line: &str) -> (bool, Option<u8>) {
+    let (mut is_dim, mut foreground) = (false, None);
+    let mut i = 0;
+    while let Some(length) = ansi::get_escape_sequence_length(&raw_line[i..]) {
+        let sequence = &raw_line[i..i + length];
+        i += length;
+        if !(sequence.starts_with("\x1b[") && sequence.ends_with('m')) {
+            continue;
+        }
+        let mut parameters = sequence[2..length - 1].split(';');
+        while let Some(parameter) = parameters.next() {
+            match parameter.parse::<u8>() {
+                Err(_) | Ok(0) => {
+                    is_dim = false;
+                    foreground = None;
+                }
+                Ok(2) => is_dim = true,
+                Ok(22) => is_dim = false,
+                Ok(39) => foreground = None,
+                Ok(color @ 30..=37) | Ok(color @ 90..=97) => foreground = Some(color),
+                // An extended color: skip its "5;n" or "2;r;g;b" parameters.
+                Ok(38) | Ok(48) => match parameters.next() {
+                    Some("5") => {
+                        parameters.next();
+                    }
+                    Some("2") => {
+                        parameters.nth(2);
+                    }
+                    _ => {}
+                },
+                Ok(_) => {}
+            }
+        }
+    }
+    (is_dim, foreground)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_moved_line() {
+        assert!(!is_moved_line("\x1b[31m-fn b() {\x1b[m", '-'));
+        assert!(!is_moved_line("\x1b[32m+\x1b[m\x1b[32mfn b() {\x1b[m", '+'));
+        assert!(!is_moved_line("-fn b() {", '-'));
+        assert!(is_moved_line("\x1b[1;35m-fn b() {\x1b[m", '-'));
+        assert!(is_moved_line("\x1b[1;34m-fn b() {\x1b[m", '-'));
+        assert!(is_moved_line(
+            "\x1b[1;36m+\x1b[m\x1b[1;36mfn b() {\x1b[m",
+            '+'
+        ));
+        assert!(is_moved_line("\x1b[1;33m+fn b() {\x1b[m", '+'));
+        assert!(is_moved_line("\x1b[2m-fn b() {\x1b[m", '-'));
+        // Cyan is the color of added moved lines only.
+        assert!(!is_moved_line("\x1b[1;36m+fn b() {\x1b[m", '-'));
+        assert!(!is_moved_line("\x1b[38;5;35m-fn b() {\x1b[m", '-'));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::config::Config;
+use crate::paint::to_ansi_style;
+
+/// Return the marker that classifies a hunk line of a diff with n_parents parents. A line of a
+/// combined diff starts with one marker column per parent: the line is a removed line if any
+/// column holds '-', an added line if any column holds '+', and an unchanged line if all columns
+/// are spaces. Any other line (e.g. "\ No newline at end of file") is classified by its first
+/// character.
+pub fn get_hunk_line_marker(line: &str, n_parents: usize) -> Option<char> {
+    let markers: Vec<char> = line.chars().take(n_parents).collect();
+    if n_parents == 1
+        || markers.len() < n_parents
+        || !markers.iter().all(|c| *c == '-' || *c == '+' || *c == ' ')
+    {
+        markers.first().copied()
+    } else if markers.contains(&'-') {
+        Some('-')
+    } else if markers.contains(&'+') {
+        Some('+')
+    } else {
+        Some(' ')
+    }
+}
+
+/// Split a hunk line of a diff with n_parents parents into its marker columns and the rest of the
+/// line. The columns are characters, not bytes: a malformed line may have a multibyte character
+/// in place of a marker. A line shorter than n_parents characters is all marker columns.
+pub fn split_markers(line: &str, n_parents: usize) -> (&str, &str) {
+    let i = line
+        .char_indices()
+        .nth(n_parents)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len());
+    line.split_at(i)
+}
+
+/// Partition the minus and plus lines of a combined diff hunk according to the parent against
+/// which their edits should be inferred. A minus line belongs to the first parent from which it
+/// was removed. A plus line belongs to the first parent to which it was added that also has
+/// removed lines, or else to the first parent to which it was added. Return, for each parent, the
+/// indices of its minus lines and plus lines.
+pub fn group_lines_by_parent(
+    minus_markers: &[String],
+    plus_markers: &[String],
+    n_parents: usize,
+) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let mut groups = vec![(Vec::new(), Vec::new()); n_parents];
+    for (i, markers) in minus_markers.iter().enumerate() {
+        let parent = markers.chars().position(|c| c == '-').unwrap_or(0);
+        groups[parent].0.push(i);
+    }
+    for (i, markers) in plus_markers.iter().enumerate() {
+        let parents: Vec<usize> = markers
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '+')
+            .map(|(parent, _)| parent)
+            .collect();
+        let parent = parents
+            .iter()
+            .find(|parent| !groups[**parent].0.is_empty())
+            .or_else(|| parents.first())
+            .copied()
+            .unwrap_or(0);
+        groups[parent].1.push(i);
+    }
+    groups
+}
+
+/// Return the marker columns of a combined diff line, each painted with the background color of
+/// a removed or added line according to whether the line was removed from or added to that
+/// parent.
+pub fn paint_markers(markers: &str, config: &Config) -> String {
+    markers
+        .chars()
+        .map(|c| match c {
+            '-' => to_ansi_style(
+                config.no_style.apply(config.minus_style_modifier),
+                config.true_color,
+            )
+            .paint(config.minus_line_marker)
+            .to_string(),
+            '+' => to_ansi_style(
+                config.no_style.apply(config.plus_style_modifier),
+                config.true_color,
+            )
+            .paint(config.plus_line_marker)
+            .to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_hunk_line_marker() {
+        assert_eq!(get_hunk_line_marker("-a", 1), Some('-'));
+        assert_eq!(get_hunk_line_marker("", 1), None);
+        assert_eq!(get_hunk_line_marker(" -a", 2), Some('-'));
+        assert_eq!(get_hunk_line_marker("++a", 2), Some('+'));
+        assert_eq!(get_hunk_line_marker("+ a", 2), Some('+'));
+        assert_eq!(get_hunk_line_marker("  a", 2), Some(' '));
+        assert_eq!(get_hunk_line_marker("\\ No newline", 2), Some('\\'));
+    }
+
+    #[test]
+    fn test_split_markers() {
+        assert_eq!(split_markers("+-a", 2), ("+-", "a"));
+        assert_eq!(split_markers("+é", 2), ("+é", ""));
+        assert_eq!(split_markers("+éa", 2), ("+é", "a"));
+        assert_eq!(split_markers("-", 2), ("-", ""));
+    }
+
+    #[test]
+    fn test_group_lines_by_parent() {
+        let minus_markers = vec!["- ".to_string(), " -".to_string()];
+        let plus_markers = vec!["++".to_string(), " +".to_string(), "+ ".to_string()];
+        assert_eq!(
+            group_lines_by_parent(&minus_markers, &plus_markers, 2),
+            vec![(vec![0], vec![0, 2]), (vec![1], vec![1])]
+        );
+        let minus_markers = vec![" -".to_string()];
+        assert_eq!(
+            group_lines_by_parent(&minus_markers, &plus_markers, 2),
+            vec![(vec![], vec![2]), (vec![0], vec![0, 1])]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ansi_term::{Colour, Style};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::paint::{self, Painter};
+
+lazy_static! {
+    /// A metadata line of a commit of `git log` or `git show` output, following the "commit"
+    /// line, e.g. "Author: someone <someone@example.com>" or "Date:   Thu Nov 21 16:10:55 2019
+    /// +0100". The labels are those of git's medium, full and fuller formats, and of reflogs.
+    static ref METADATA_LINE_REGEX: Regex = Regex::new(
+        r"^(Merge|Author|AuthorDate|Commit|CommitDate|Date|Reflog|Reflog message):( +)(.*)$"
+    )
+    .unwrap();
+
+    /// The author or committer of a commit, e.g. "someone <someone@example.com>".
+    static ref PERSON_REGEX: Regex = Regex::new(r"^(.*?) *<([^>]*)>$").unwrap();
+
+    /// A trailer line of a commit message, e.g. "Signed-off-by: someone <someone@example.com>".
+    static ref TRAILER_LINE_REGEX: Regex = Regex::new(
+        r"(?i)^((?:Signed-off-by|Co-authored-by|Fixes|Closes|Resolves|Refs|Reviewed-by|Acked-by|Tested-by|Reported-by|Suggested-by|Helped-by|Cc):)(.*)$"
+    )
+    .unwrap();
+
+    /// A reference to an issue, e.g. "#123" (GitHub, GitLab) or "PROJ-123" (Jira).
+    static ref ISSUE_REFERENCE_REGEX: Regex =
+        Regex::new(r"(^|[^\w&])(#\d+|\b[A-Z][A-Z0-9]+-\d+)\b").unwrap();
+}
+
+/// The indentation of the lines of a commit message in `git log` output.
+const MESSAGE_INDENT: &str = "    ";
+
+/// Uppercase words followed by a number that are not the keys of issue trackers, e.g. "UTF-8".
+const NON_ISSUE_KEYS: [&str; 3] = ["ISO", "SHA", "UTF"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The kind of a ref decorating a commit, e.g. "HEAD", "master", "origin/master" or "tag: v1.0".
+#[derive(Debug, PartialEq)]
+enum DecorationKind {
+    Head,
+    Branch,
+    RemoteBranch,
+    Tag,
+    Stash,
+    Grafted,
+}
+
+impl DecorationKind {
+    /// The style of a ref of this kind: the default of git's color.decorate.* settings.
+    fn get_style(&self) -> Style {
+        match self {
+            DecorationKind::Head => Colour::Cyan.bold(),
+            DecorationKind::Branch => Colour::Green.bold(),
+            DecorationKind::RemoteBranch => Colour::Red.bold(),
+            DecorationKind::Tag => Colour::Yellow.bold(),
+            DecorationKind::Stash => Colour::Purple.bold(),
+            DecorationKind::Grafted => Colour::Blue.bold(),
+        }
+    }
+}
+
+/// Return the text of the header shown for a "commit" line, e.g. "commit 94907c0f136f46dc46ffae2d
+/// (HEAD -> master, origin/master)": the hash, abbreviated to --commit-hash-length, is painted in
+/// the commit color, and each of the refs decorating the commit is painted according to its kind.
+pub fn get_commit_header(line: &str, config: &Config) -> String {
+    let rest = line.strip_prefix("commit ").unwrap_or(line);
+    let (hash, rest) = rest.split_at(rest.find(' ').unwrap_or(rest.len()));
+    let paint = |text: &str| {
+        paint::paint_text_with_style_modifier(text, config.commit_style_modifier, config)
+    };
+    let mut header = format!("{}{}", paint("commit "), paint_hash(hash, config));
+    match rest
+        .strip_prefix(" (")
+        .and_then(|decorations| decorations.strip_suffix(')'))
+    {
+        Some(decorations) => {
+            let decorations: Vec<String> = decorations
+                .split(", ")
+                .map(|decoration| paint_decoration(decoration, config))
+                .collect();
+            header.push_str(&paint(" ("));
+            header.push_str(&decorations.join(&paint(", ")));
+            header.push_str(&paint(")"));
+        }
+        None => header.push_str(&paint(rest)),
+    }
+    header
+}
+
+/// Return the painted form of a metadata line of a commit, or None if the line is not a metadata
+/// line, such as the blank line preceding the commit message. The hashes of merge parents are
+/// painted as the commit's hash is, the names of people are emphasized, and dates are shown
+/// relative to the current time under --relative-dates. The lines written by `git log
+/// --show-signature` are painted according to the verdict on the signature.
+pub fn get_painted_metadata_line(line: &str, config: &Config) -> Option<String> {
+    if line.starts_with("gpg: ") || line.starts_with("Primary key fingerprint: ") {
+        return Some(get_signature_line_style(line).paint(line).to_string());
+    }
+    let captures = METADATA_LINE_REGEX.captures(line)?;
+    let value = &captures[3];
+    let painted_value = match &captures[1] {
+        "Merge" => value
+            .split(' ')
+            .map(|hash| paint_hash(hash, config))
+            .collect::<Vec<String>>()
+            .join(" "),
+        "Author" | "Commit" => match PERSON_REGEX.captures(value) {
+            Some(person) => format!(
+                "{} {}",
+                Style::new().bold().paint(&person[1]),
+                Style::new().dimmed().paint(format!("<{}>", &person[2]))
+            ),
+            None => value.to_string(),
+        },
+        "Date" | "AuthorDate" | "CommitDate" if config.relative_dates => match parse_date(value) {
+            Some(time) => format_relative_date(time, get_current_time()),
+            None => value.to_string(),
+        },
+        _ => value.to_string(),
+    };
+    Some(format!(
+        "{}:{}{}",
+        &captures[1], &captures[2], painted_value
+    ))
+}
+
+/// The message of a commit of `git log` output: the indented lines following its metadata.
+#[derive(Debug, Default)]
+pub struct CommitMessage {
+    has_subject: bool,
+}
+
+impl CommitMessage {
+    /// Start the message of a commit. Under --commit-message-markdown, the body of the message is
+    /// highlighted as Markdown.
+    pub fn new(painter: &mut Painter) -> Self {
+        if painter.config.commit_message_markdown {
+            painter.set_syntax(Some("md"));
+            painter.set_highlighter();
+        }
+        Self::default()
+    }
+
+    /// Return the painted form of a line of the message, or None if the line is not part of the
+    /// message. The subject line is bold, the keys of trailers such as "Signed-off-by:" are
+    /// highlighted, and so are references to issues, such as "#123" or "PROJ-123".
+    pub fn paint_line(&mut self, line: &str, painter: &mut Painter) -> Option<String> {
+        let text = line.strip_prefix(MESSAGE_INDENT)?;
+        let painted_text = if text.trim().is_empty() {
+            text.to_string()
+        } else if !self.has_subject {
+            self.has_subject = true;
+            paint_issue_references(&[(Style::new().bold(), text)])
+        } else if let Some(captures) = TRAILER_LINE_REGEX.captures(text) {
+            format!(
+                "{}{}",
+                Colour::Cyan.bold().paint(&captures[1]),
+                paint_issue_references(&[(Style::new(), &captures[2])])
+            )
+        } else if painter.config.commit_message_markdown {
+            let config = painter.config;
+            let line = format!("{}\n", text);
+            let sections: Vec<(Style, &str)> = Painter::get_line_syntax_style_sections(
+                &line,
+                &mut painter.highlighter,
+                config,
+                true,
+            )
+            .into_iter()
+            .map(|(style, section)| {
+                (
+                    paint::to_ansi_style(style, config.true_color),
+                    section.trim_end_matches('\n'),
+                )
+            })
+            .collect();
+            paint_issue_references(&sections)
+        } else {
+            paint_issue_references(&[(Style::new(), text)])
+        };
+        Some(format!("{}{}", MESSAGE_INDENT, painted_text))
+    }
+}
+
+/// Paint sections of text, underlining the references to issues that they contain in blue.
+fn paint_issue_references(sections: &[(Style, &str)]) -> String {
+    let mut painted = Vec::new();
+    for (style, text) in sections {
+        let mut offset = 0;
+        for (start, end) in get_issue_reference_ranges(text) {
+            painted.push(style.paint(&text[offset..start]));
+            let reference_style = Style {
+                foreground: Some(Colour::Blue),
+                is_underline: true,
+                ..*style
+            };
+            painted.push(reference_style.paint(&text[start..end]));
+            offset = end;
+        }
+        painted.push(style.paint(&text[offset..]));
+    }
+    ansi_term::ANSIStrings(&painted).to_string()
+}
+
+/// Return the byte ranges of the references to issues in a text.
+fn get_issue_reference_ranges(text: &str) -> Vec<(usize, usize)> {
+    ISSUE_REFERENCE_REGEX
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(2))
+        .filter(|reference| {
+            let key = reference.as_str().split('-').next().unwrap_or("");
+            !NON_ISSUE_KEYS.contains(&key)
+        })
+        .map(|reference| (reference.start(), reference.end()))
+        .collect()
+}
+
+fn paint_hash(hash: &str, config: &Config) -> String {
+    let hash = match config.commit_hash_length {
+        Some(length) => hash.get(..length).unwrap_or(hash),
+        None => hash,
+    };
+    paint::paint_text_with_style_modifier(hash, config.commit_style_modifier, config)
+}
+
+/// Paint a ref decorating a commit. "HEAD -> master" means that HEAD is a symbolic ref to the
+/// branch master: the two refs are painted separately.
+fn paint_decoration(decoration: &str, config: &Config) -> String {
+    match decoration.find(" -> ") {
+        Some(i) => format!(
+            "{}{}{}",
+            paint_decoration(&decoration[..i], config),
+            paint::paint_text_with_style_modifier(" -> ", config.commit_style_modifier, config),
+            paint_decoration(&decoration[i + 4..], config)
+        ),
+        None => get_decoration_kind(decoration)
+            .get_style()
+            .paint(decoration)
+            .to_string(),
+    }
+}
+
+/// Return the kind of a ref decorating a commit. Git shows the refs by their short names, which
+/// do not tell a remote-tracking branch from a local branch with a slash in its name: the branches
+/// of the remotes conventionally named "origin" and "upstream" are taken for remote-tracking
+/// branches.
+fn get_decoration_kind(decoration: &str) -> DecorationKind {
+    match decoration {
+        "HEAD" => DecorationKind::Head,
+        "grafted" | "replaced" => DecorationKind::Grafted,
+        "refs/stash" | "stash" => DecorationKind::Stash,
+        _ if decoration.starts_with("tag: ") => DecorationKind::Tag,
+        _ if decoration.starts_with("refs/remotes/")
+            || decoration.starts_with("origin/")
+            || decoration.starts_with("upstream/") =>
+        {
+            DecorationKind::RemoteBranch
+        }
+        _ => DecorationKind::Branch,
+    }
+}
+
+/// Return the style of a line written by `git log --show-signature`: green for a good signature,
+/// red for a bad one, yellow for a signature that could not be checked or a warning, and dimmed
+/// for the details of the signature.
+fn get_signature_line_style(line: &str) -> Style {
+    if line.contains("Good signature") {
+        Colour::Green.normal()
+    } else if line.contains("BAD signature") {
+        Colour::Red.bold()
+    } else if line.contains("Can't check signature") || line.contains("WARNING") {
+        Colour::Yellow.normal()
+    } else {
+        Style::new().dimmed()
+    }
+}
+
+fn get_current_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Return the Unix timestamp of a date of `git log` output, in git's default format, e.g. "Thu Nov
+/// 21 16:10:55 2019 +0100", or in its ISO format, e.g. "2019-11-21 16:10:55 +0100".
+fn parse_date(date: &str) -> Option<i64> {
+    let words: Vec<&str> = date.split_whitespace().collect();
+    let (year, month, day, time, tz) = match *words.as_slice() {
+        [_, month, day, time, year, tz] => (
+            year.parse::<i64>().ok()?,
+            MONTHS.iter().position(|name| *name == month)? as i64 + 1,
+            day.parse::<i64>().ok()?,
+            time,
+            tz,
+        ),
+        [date, time, tz] => {
+            let mut parts = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+            (parts.next()??, parts.next()??, parts.next()??, time, tz)
+        }
+        _ => return None,
+    };
+    let mut parts = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let seconds = parts.next()?? * 3600 + parts.next()?? * 60 + parts.next()??;
+    let offset_minutes =
+        tz.get(1..3)?.parse::<i64>().ok()? * 60 + tz.get(3..5)?.parse::<i64>().ok()?;
+    let offset_minutes = match tz.get(..1) {
+        Some("-") => -offset_minutes,
+        Some("+") => offset_minutes,
+        _ => return None,
+    };
+    Some(days_from_civil(year, month, day) * 86400 + seconds - offset_minutes * 60)
+}
+
+/// Convert a (year, month, day) date in the proleptic Gregorian calendar to a number of days since
+/// 1970-01-01.
+// See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Format the time elapsed from a Unix timestamp to the current time as git's --relative-date
+/// does, e.g. "3 hours ago" or "2 years, 1 month ago".
+fn format_relative_date(time: i64, now: i64) -> String {
+    let count = |n: i64, unit: &str| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" });
+    if now < time {
+        return "in the future".to_string();
+    }
+    let seconds = now - time;
+    if seconds < 90 {
+        return format!("{} ago", count(seconds, "second"));
+    }
+    let minutes = (seconds + 30) / 60;
+    if minutes < 90 {
+        return format!("{} ago", count(minutes, "minute"));
+    }
+    let hours = (minutes + 30) / 60;
+    if hours < 36 {
+        return format!("{} ago", count(hours, "hour"));
+    }
+    let days = (hours + 12) / 24;
+    if days < 14 {
+        format!("{} ago", count(days, "day"))
+    } else if days < 70 {
+        format!("{} ago", count((days + 3) / 7, "week"))
+    } else if days < 365 {
+        format!("{} ago", count((days + 15) / 30, "month"))
+    } else if days < 1825 {
+        let total_months = (days * 12 * 2 + 365) / (365 * 2);
+        match total_months % 12 {
+            0 => format!("{} ago", count(total_months / 12, "year")),
+            months => format!(
+                "{}, {} ago",
+                count(total_months / 12, "year"),
+                count(months, "month")
+            ),
+        }
+    } else {
+        format!("{} ago", count((days + 183) / 365, "year"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_decoration_kind() {
+        assert_eq!(get_decoration_kind("HEAD"), DecorationKind::Head);
+        assert_eq!(get_decoration_kind("master"), DecorationKind::Branch);
+        assert_eq!(get_decoration_kind("feature/x"), DecorationKind::Branch);
+        assert_eq!(
+            get_decoration_kind("origin/master"),
+            DecorationKind::RemoteBranch
+        );
+        assert_eq!(get_decoration_kind("tag: v1.0"), DecorationKind::Tag);
+        assert_eq!(get_decoration_kind("refs/stash"), DecorationKind::Stash);
+    }
+
+    #[test]
+    fn test_get_issue_reference_ranges() {
+        let references = |text: &str| -> Vec<String> {
+            get_issue_reference_ranges(text)
+                .into_iter()
+                .map(|(start, end)| text[start..end].to_string())
+                .collect()
+        };
+        assert_eq!(
+            references("Fix #128 and PROJ-42 (see also #7)"),
+            ["#128", "PROJ-42", "#7"]
+        );
+        assert_eq!(references("#12: read UTF-8 and SHA-256"), ["#12"]);
+        assert!(references("a#12 &#39; Proj-42 PROJ-").is_empty());
+    }
+
+    #[test]
+    fn test_trailer_line_regex() {
+        assert!(TRAILER_LINE_REGEX.is_match("Signed-off-by: someone <someone@example.com>"));
+        assert!(TRAILER_LINE_REGEX.is_match("Co-Authored-By: someone <someone@example.com>"));
+        assert!(TRAILER_LINE_REGEX.is_match("Fixes: #128"));
+        assert!(!TRAILER_LINE_REGEX.is_match("Note: the parser is slow"));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("Thu Jan 1 00:00:00 1970 +0000"), Some(0));
+        assert_eq!(
+            parse_date("Wed Nov 20 06:16:08 2019 +0100"),
+            Some(1574226968)
+        );
+        assert_eq!(parse_date("2019-11-20 06:16:08 +0100"), Some(1574226968));
+        assert_eq!(parse_date("2019-11-19 19:16:08 -1000"), Some(1574226968));
+        assert_eq!(
+            parse_date("Tue Feb 29 00:00:00 2000 +0000"),
+            Some(951782400)
+        );
+        assert_eq!(parse_date("3 hours ago"), None);
+    }
+
+    #[test]
+    fn test_format_relative_date() {
+        let now = 1574226968;
+        let hour = 3600;
+        let day = 24 * hour;
+        assert_eq!(format_relative_date(now - 1, now), "1 second ago");
+        assert_eq!(format_relative_date(now - 600, now), "10 minutes ago");
+        assert_eq!(format_relative_date(now - 3 * hour, now), "3 hours ago");
+        assert_eq!(format_relative_date(now - 2 * day, now), "2 days ago");
+        assert_eq!(format_relative_date(now - 21 * day, now), "3 weeks ago");
+        assert_eq!(format_relative_date(now - 100 * day, now), "3 months ago");
+        assert_eq!(
+            format_relative_date(now - 400 * day, now),
+            "1 year, 1 month ago"
+        );
+        assert_eq!(format_relative_date(now - 730 * day, now), "2 years ago");
+        assert_eq!(format_relative_date(now - 3650 * day, now), "10 years ago");
+        assert_eq!(format_relative_date(now + 1, now), "in the future");
+    }
+}
@@ -1,14 +1,13 @@
 use std::process;
-use std::str::FromStr;
 
 use syntect::highlighting::{Color, Style, StyleModifier, Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
 
 use crate::bat::output::PagingMode;
 use crate::cli;
+use crate::encoding::Encoding;
 use crate::env;
-use crate::paint;
-use crate::style;
+use crate::style::{self, StyleColor, StyleSpec};
 
 pub struct Config<'a> {
     pub theme: Option<&'a Theme>,
@@ -19,11 +18,27 @@ pub struct Config<'a> {
     pub minus_emph_style_modifier: StyleModifier,
     pub plus_style_modifier: StyleModifier,
     pub plus_emph_style_modifier: StyleModifier,
+    pub minus_moved_style_modifier: StyleModifier,
+    pub plus_moved_style_modifier: StyleModifier,
+    pub zero_style_modifier: StyleModifier,
+    pub commit_style_modifier: StyleModifier,
+    pub file_style_modifier: StyleModifier,
+    pub hunk_header_style_modifier: StyleModifier,
     pub minus_line_marker: &'a str,
     pub plus_line_marker: &'a str,
     pub highlight_removed: bool,
+    pub detect_moved: bool,
+    pub color_only: bool,
+    pub side_by_side: bool,
+    pub line_numbers: bool,
+    pub line_numbers_minus_style_modifier: StyleModifier,
+    pub line_numbers_plus_style_modifier: StyleModifier,
+    pub line_numbers_zero_style_modifier: StyleModifier,
     pub commit_style: cli::SectionStyle,
     pub commit_color: Color,
+    pub commit_hash_length: Option<usize>,
+    pub relative_dates: bool,
+    pub commit_message_markdown: bool,
     pub file_style: cli::SectionStyle,
     pub file_color: Color,
     pub hunk_style: cli::SectionStyle,
@@ -36,6 +51,7 @@ pub struct Config<'a> {
     pub no_style: Style,
     pub max_buffered_lines: usize,
     pub paging_mode: PagingMode,
+    pub encoding: Encoding,
 }
 
 pub fn get_config<'a>(
@@ -45,6 +61,7 @@ pub fn get_config<'a>(
     true_color: bool,
     terminal_width: usize,
     paging_mode: PagingMode,
+    encoding: Encoding,
 ) -> Config<'a> {
     // Implement --color-only
     let keep_plus_minus_markers = if opt.color_only {
@@ -54,22 +71,6 @@ pub fn get_config<'a>(
     };
     let background_color_extends_to_terminal_width = opt.width != Some("variable".to_string());
     let tab_width = if opt.color_only { 0 } else { opt.tab_width };
-    let commit_style = if opt.color_only {
-        cli::SectionStyle::Plain
-    } else {
-        opt.commit_style
-    };
-    let file_style = if opt.color_only {
-        cli::SectionStyle::Plain
-    } else {
-        opt.file_style
-    };
-    let hunk_style = if opt.color_only {
-        cli::SectionStyle::Plain
-    } else {
-        opt.hunk_style
-    };
-
     let theme_name_from_bat_pager = env::get_env_var("BAT_THEME");
     let (is_light_mode, theme_name) = get_is_light_mode_and_theme_name(
         opt.theme.as_ref(),
@@ -84,49 +85,93 @@ pub fn get_config<'a>(
         Some(&theme_set.themes[&theme_name])
     };
 
-    let minus_style_modifier = StyleModifier {
-        background: Some(color_from_rgb_or_ansi_code_with_default(
-            opt.minus_color.as_ref(),
-            style::get_minus_color_default(is_light_mode, true_color),
-        )),
-        foreground: if opt.highlight_removed {
-            None
-        } else {
-            Some(style::NO_COLOR)
-        },
-        font_style: None,
-    };
+    // The style of each element is given by its style string. The parts of the style that are not
+    // given are taken from the element's default style string, in which a deprecated --*-color
+    // option replaces a color.
+    let default_styles =
+        style::get_default_styles(is_light_mode, true_color, opt.highlight_removed);
+    let minus_style_modifier = get_line_style_modifier(
+        &opt.minus_style,
+        DeprecatedColor::Background(&opt.minus_color),
+        &default_styles.minus,
+    );
+    let minus_emph_style_modifier = get_line_style_modifier(
+        &opt.minus_emph_style,
+        DeprecatedColor::Background(&opt.minus_emph_color),
+        &default_styles.minus_emph,
+    );
+    let plus_style_modifier = get_line_style_modifier(
+        &opt.plus_style,
+        DeprecatedColor::Background(&opt.plus_color),
+        &default_styles.plus,
+    );
+    let plus_emph_style_modifier = get_line_style_modifier(
+        &opt.plus_emph_style,
+        DeprecatedColor::Background(&opt.plus_emph_color),
+        &default_styles.plus_emph,
+    );
+    let minus_moved_style_modifier = get_line_style_modifier(
+        &opt.minus_moved_style,
+        DeprecatedColor::Background(&opt.minus_moved_color),
+        &default_styles.minus_moved,
+    );
+    let plus_moved_style_modifier = get_line_style_modifier(
+        &opt.plus_moved_style,
+        DeprecatedColor::Background(&opt.plus_moved_color),
+        &default_styles.plus_moved,
+    );
+    let zero_style_modifier =
+        get_line_style_modifier(&opt.zero_style, DeprecatedColor::None, &default_styles.zero);
+    let line_numbers_minus_style_modifier = get_line_style_modifier(
+        &opt.line_numbers_minus_style,
+        DeprecatedColor::Foreground(&opt.line_numbers_minus_color),
+        &default_styles.line_numbers_minus,
+    );
+    let line_numbers_plus_style_modifier = get_line_style_modifier(
+        &opt.line_numbers_plus_style,
+        DeprecatedColor::Foreground(&opt.line_numbers_plus_color),
+        &default_styles.line_numbers_plus,
+    );
+    let line_numbers_zero_style_modifier = get_line_style_modifier(
+        &opt.line_numbers_zero_style,
+        DeprecatedColor::Foreground(&opt.line_numbers_zero_color),
+        &default_styles.line_numbers_zero,
+    );
 
-    let minus_emph_style_modifier = StyleModifier {
-        background: Some(color_from_rgb_or_ansi_code_with_default(
-            opt.minus_emph_color.as_ref(),
-            style::get_minus_emph_color_default(is_light_mode, true_color),
-        )),
-        foreground: if opt.highlight_removed {
-            None
-        } else {
-            Some(style::NO_COLOR)
-        },
-        font_style: None,
-    };
+    // The commit, file and hunk header sections are decorated in the foreground color of their
+    // style, and under --color-only are not decorated.
+    let commit_style_spec = get_style_spec(
+        &opt.commit_style,
+        DeprecatedColor::Foreground(&opt.commit_color),
+        &default_styles.commit,
+    );
+    let commit_color = get_section_color(&commit_style_spec);
+    let commit_style_modifier =
+        commit_style_spec.get_style_modifier(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER);
+    let commit_style = get_section_style(&commit_style_spec, opt.color_only);
 
-    let plus_style_modifier = StyleModifier {
-        background: Some(color_from_rgb_or_ansi_code_with_default(
-            opt.plus_color.as_ref(),
-            style::get_plus_color_default(is_light_mode, true_color),
-        )),
-        foreground: None,
-        font_style: None,
-    };
+    let file_style_spec = get_style_spec(
+        &opt.file_style,
+        DeprecatedColor::Foreground(&opt.file_color),
+        &default_styles.file,
+    );
+    let file_color = get_section_color(&file_style_spec);
+    let file_style_modifier =
+        file_style_spec.get_style_modifier(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER);
+    let file_style = get_section_style(&file_style_spec, opt.color_only);
 
-    let plus_emph_style_modifier = StyleModifier {
-        background: Some(color_from_rgb_or_ansi_code_with_default(
-            opt.plus_emph_color.as_ref(),
-            style::get_plus_emph_color_default(is_light_mode, true_color),
-        )),
+    let hunk_style_spec = get_style_spec(
+        &opt.hunk_style,
+        DeprecatedColor::Foreground(&opt.hunk_color),
+        &default_styles.hunk_header,
+    );
+    let hunk_color = get_section_color(&hunk_style_spec);
+    // The code fragment of the hunk header is syntax highlighted.
+    let hunk_header_style_modifier = StyleModifier {
         foreground: None,
-        font_style: None,
+        ..hunk_style_spec.get_style_modifier(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER)
     };
+    let hunk_style = get_section_style(&hunk_style_spec, opt.color_only);
 
     let minus_line_marker = if keep_plus_minus_markers { "-" } else { " " };
     let plus_line_marker = if keep_plus_minus_markers { "+" } else { " " };
@@ -145,15 +190,31 @@ pub fn get_config<'a>(
         minus_emph_style_modifier,
         plus_style_modifier,
         plus_emph_style_modifier,
+        minus_moved_style_modifier,
+        plus_moved_style_modifier,
+        zero_style_modifier,
+        commit_style_modifier,
+        file_style_modifier,
+        hunk_header_style_modifier,
         highlight_removed: opt.highlight_removed,
+        detect_moved: opt.detect_moved,
+        color_only: opt.color_only,
+        side_by_side: opt.side_by_side && !opt.color_only,
+        line_numbers: opt.line_numbers && !opt.color_only,
+        line_numbers_minus_style_modifier,
+        line_numbers_plus_style_modifier,
+        line_numbers_zero_style_modifier,
         minus_line_marker,
         plus_line_marker,
         commit_style,
-        commit_color: color_from_rgb_or_ansi_code(&opt.commit_color),
+        commit_color,
+        commit_hash_length: opt.commit_hash_length,
+        relative_dates: opt.relative_dates,
+        commit_message_markdown: opt.commit_message_markdown,
         file_style,
-        file_color: color_from_rgb_or_ansi_code(&opt.file_color),
+        file_color,
         hunk_style,
-        hunk_color: color_from_rgb_or_ansi_code(&opt.hunk_color),
+        hunk_color,
         true_color,
         terminal_width,
         background_color_extends_to_terminal_width,
@@ -162,6 +223,7 @@ pub fn get_config<'a>(
         no_style: style::get_no_style(),
         max_buffered_lines: 32,
         paging_mode,
+        encoding,
     }
 }
 
@@ -221,24 +283,76 @@ fn valid_theme_name_or_none(theme_name: Option<&String>, theme_set: &ThemeSet) -
 }
 
 fn color_from_rgb_or_ansi_code(s: &str) -> Color {
-    let die = || {
+    style::parse_color(s).unwrap_or_else(|| {
         eprintln!("Invalid color: {}", s);
         process::exit(1);
-    };
-    if s.starts_with("#") {
-        Color::from_str(s).unwrap_or_else(|_| die())
-    } else {
-        s.parse::<u8>()
-            .ok()
-            .and_then(paint::color_from_ansi_number)
-            .or_else(|| paint::color_from_ansi_name(s))
-            .unwrap_or_else(die)
-    }
+    })
+}
+
+fn parse_style_string(s: &str) -> StyleSpec {
+    style::parse_style_string(s).unwrap_or_else(|error| {
+        eprintln!("Invalid style: {} ({})", s, error);
+        process::exit(1);
+    })
+}
+
+/// A color given by a deprecated --*-color option, which replaces the foreground or the background
+/// color of the default style of an element.
+enum DeprecatedColor<'a> {
+    Foreground(&'a Option<String>),
+    Background(&'a Option<String>),
+    None,
 }
 
-fn color_from_rgb_or_ansi_code_with_default(arg: Option<&String>, default: Color) -> Color {
-    match arg {
-        Some(string) => color_from_rgb_or_ansi_code(&string),
+/// Return the style of an element given by its style string, taking the parts of the style that
+/// are not given from the element's default style string, in which a deprecated color replaces
+/// the color it stands for.
+fn get_style_spec(
+    style_string: &Option<String>,
+    deprecated_color: DeprecatedColor,
+    default_style_string: &str,
+) -> StyleSpec {
+    let mut default = parse_style_string(default_style_string);
+    match deprecated_color {
+        DeprecatedColor::Foreground(Some(color)) => {
+            default.foreground = Some(StyleColor::Color(color_from_rgb_or_ansi_code(color)))
+        }
+        DeprecatedColor::Background(Some(color)) => {
+            default.background = Some(StyleColor::Color(color_from_rgb_or_ansi_code(color)))
+        }
+        _ => {}
+    }
+    match style_string {
+        Some(s) => parse_style_string(s).or(default),
         None => default,
     }
 }
+
+/// Return the style modifier of the lines of a diff, or of their line numbers, given as by
+/// get_style_spec. Only sections have a decoration.
+fn get_line_style_modifier(
+    style_string: &Option<String>,
+    deprecated_color: DeprecatedColor,
+    default_style_string: &str,
+) -> StyleModifier {
+    let spec = get_style_spec(style_string, deprecated_color, default_style_string);
+    if let (Some(s), Some(_)) = (style_string, spec.decoration) {
+        eprintln!("Invalid style: {} (decorations apply to sections only)", s);
+        process::exit(1);
+    }
+    spec.get_style_modifier(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER)
+}
+
+/// Return the color in which a section is decorated: the foreground color of its style, if any.
+fn get_section_color(spec: &StyleSpec) -> Color {
+    spec.get_foreground_color().unwrap_or(style::NO_COLOR)
+}
+
+/// Return the decoration of a section, given by its style.
+fn get_section_style(spec: &StyleSpec, color_only: bool) -> cli::SectionStyle {
+    if color_only {
+        cli::SectionStyle::Plain
+    } else {
+        spec.decoration.unwrap_or(cli::SectionStyle::Plain)
+    }
+}
@@ -0,0 +1,274 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// The line starting the old file's section of a hunk, e.g. "*** 1,5 ****".
+    static ref OLD_RANGE_LINE_REGEX: Regex = Regex::new(r"^\*\*\* (\d+)(?:,(\d+))? \*\*\*\*$").unwrap();
+    /// The line starting the new file's section of a hunk, e.g. "--- 1,6 ----".
+    static ref NEW_RANGE_LINE_REGEX: Regex = Regex::new(r"^--- (\d+)(?:,(\d+))? ----$").unwrap();
+}
+
+/// The line preceding each hunk of a context diff.
+pub const HUNK_SEPARATOR: &str = "***************";
+
+/// A hunk of a context diff (`diff -c`). The hunk lists the lines of the old file, marking removed
+/// lines with "- " and changed lines with "! ", and then the lines of the new file, marking added
+/// lines with "+ " and changed lines with "! ". Unchanged lines are marked with two spaces. A
+/// section that would hold no removed (or added) lines is omitted.
+#[derive(Debug, Default, PartialEq)]
+pub struct ContextHunk {
+    pub old_range_line: Option<String>,
+    pub new_range_line: Option<String>,
+    pub old_range: (usize, usize), // (start, count)
+    pub new_range: (usize, usize),
+    pub old_lines: Vec<String>,
+    pub new_lines: Vec<String>,
+}
+
+/// A line of a hunk in unified diff form, together with the index of the context diff line it
+/// comes from in the old and/or the new file's section.
+#[derive(Debug, PartialEq)]
+pub struct UnifiedLine {
+    pub line: String,
+    pub old_index: Option<usize>,
+    pub new_index: Option<usize>,
+}
+
+impl ContextHunk {
+    /// Add a line of the hunk, following the hunk separator. Return false if the line is not part
+    /// of the hunk.
+    pub fn push(&mut self, line: &str) -> bool {
+        if self.old_range_line.is_none() {
+            match parse_range_line(&OLD_RANGE_LINE_REGEX, line) {
+                Some(range) => {
+                    self.old_range = range;
+                    self.old_range_line = Some(line.to_string());
+                    true
+                }
+                None => false,
+            }
+        } else if self.new_range_line.is_none() {
+            if let Some(range) = parse_range_line(&NEW_RANGE_LINE_REGEX, line) {
+                self.new_range = range;
+                self.new_range_line = Some(line.to_string());
+                true
+            } else if is_hunk_line(line) {
+                self.old_lines.push(line.to_string());
+                true
+            } else {
+                false
+            }
+        } else if is_hunk_line(line) {
+            self.new_lines.push(line.to_string());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return the header of the hunk in unified diff form, e.g. "@@ -1,5 +1,6 @@".
+    pub fn get_unified_header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_range.0, self.old_range.1, self.new_range.0, self.new_range.1
+        )
+    }
+
+    /// Return the lines of the hunk in unified diff form. An unchanged line is taken from the old
+    /// file's section, or from the new file's section if the old one is omitted.
+    pub fn get_unified_lines(&self) -> Vec<UnifiedLine> {
+        let (old, new) = (&self.old_lines, &self.new_lines);
+        let marker = |lines: &[String], i: usize| lines.get(i).map(|line| get_marker(line));
+        let mut unified_lines = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        let push = |unified_lines: &mut Vec<UnifiedLine>, marker, line, old_index, new_index| {
+            unified_lines.push(UnifiedLine {
+                line: get_unified_line(marker, line),
+                old_index,
+                new_index,
+            })
+        };
+        loop {
+            match (marker(old, i), marker(new, j)) {
+                (None, None) => break,
+                (Some('-'), _) => {
+                    push(&mut unified_lines, '-', &old[i], Some(i), None);
+                    i += 1;
+                }
+                (_, Some('+')) => {
+                    push(&mut unified_lines, '+', &new[j], None, Some(j));
+                    j += 1;
+                }
+                (Some('!'), _) | (_, Some('!')) => {
+                    // A block of changed lines: the old lines are followed by the new lines.
+                    while marker(old, i) == Some('!') {
+                        push(&mut unified_lines, '-', &old[i], Some(i), None);
+                        i += 1;
+                    }
+                    while marker(new, j) == Some('!') {
+                        push(&mut unified_lines, '+', &new[j], None, Some(j));
+                        j += 1;
+                    }
+                }
+                (Some(' '), Some(' ')) => {
+                    push(&mut unified_lines, ' ', &old[i], Some(i), Some(j));
+                    i += 1;
+                    j += 1;
+                }
+                (Some(' '), None) => {
+                    push(&mut unified_lines, ' ', &old[i], Some(i), None);
+                    i += 1;
+                }
+                (None, Some(' ')) => {
+                    push(&mut unified_lines, ' ', &new[j], None, Some(j));
+                    j += 1;
+                }
+                (Some(_), _) => {
+                    // E.g. "\ No newline at end of file"
+                    unified_lines.push(UnifiedLine {
+                        line: old[i].clone(),
+                        old_index: Some(i),
+                        new_index: None,
+                    });
+                    i += 1;
+                }
+                (None, Some(_)) => {
+                    unified_lines.push(UnifiedLine {
+                        line: new[j].clone(),
+                        old_index: None,
+                        new_index: Some(j),
+                    });
+                    j += 1;
+                }
+            }
+        }
+        unified_lines
+    }
+}
+
+/// Return true if the line is a file header line of a context diff, e.g.
+/// "*** a.txt    2020-01-01 10:00:00.000000000 +0100".
+pub fn is_file_meta_line(line: &str) -> bool {
+    line.starts_with("*** ") && !OLD_RANGE_LINE_REGEX.is_match(line)
+}
+
+/// Parse a range line, returning the start line and number of lines of the range. A range with a
+/// single line is written without its end line; an empty range at the start of a file is "0".
+fn parse_range_line(regex: &Regex, line: &str) -> Option<(usize, usize)> {
+    let captures = regex.captures(line)?;
+    let start: usize = captures[1].parse().ok()?;
+    let count = match captures.get(2) {
+        Some(end) => (end.as_str().parse::<usize>().ok()? + 1).saturating_sub(start),
+        None if start == 0 => 0,
+        None => 1,
+    };
+    Some((start, count))
+}
+
+fn is_hunk_line(line: &str) -> bool {
+    // Unchanged empty lines may have lost their trailing whitespace.
+    line.is_empty()
+        || line.starts_with('\\')
+        || ["  ", "- ", "+ ", "! "]
+            .iter()
+            .any(|marker| line.starts_with(marker))
+}
+
+/// Return the marker of a hunk line: '-', '+', '!', ' ' or e.g. '\'.
+fn get_marker(line: &str) -> char {
+    line.chars().next().unwrap_or(' ')
+}
+
+/// Replace the two-character marker of a context diff hunk line with a unified diff marker. An
+/// empty line (an unchanged empty line that lost its trailing whitespace) is left as it is.
+fn get_unified_line(marker: char, line: &str) -> String {
+    if line.is_empty() {
+        return String::new();
+    }
+    format!("{}{}", marker, line.get(2..).unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_line() {
+        assert_eq!(
+            parse_range_line(&OLD_RANGE_LINE_REGEX, "*** 1,5 ****"),
+            Some((1, 5))
+        );
+        assert_eq!(
+            parse_range_line(&NEW_RANGE_LINE_REGEX, "--- 7 ----"),
+            Some((7, 1))
+        );
+        assert_eq!(
+            parse_range_line(&NEW_RANGE_LINE_REGEX, "--- 0 ----"),
+            Some((0, 0))
+        );
+        assert_eq!(parse_range_line(&NEW_RANGE_LINE_REGEX, "--- a.txt"), None);
+    }
+
+    #[test]
+    fn test_is_file_meta_line() {
+        assert!(is_file_meta_line(
+            "*** a.txt\t2020-01-01 10:00:00.000000000 +0100"
+        ));
+        assert!(!is_file_meta_line("*** 1,5 ****"));
+        assert!(!is_file_meta_line(HUNK_SEPARATOR));
+    }
+
+    #[test]
+    fn test_get_unified_lines() {
+        let mut hunk = ContextHunk::default();
+        for line in &[
+            "*** 1,5 ****",
+            "  a",
+            "! b",
+            "  c",
+            "- d",
+            "  e",
+            "--- 1,5 ----",
+            "  a",
+            "! B",
+            "  c",
+            "  e",
+            "+ f",
+        ] {
+            assert!(hunk.push(line));
+        }
+        assert!(!hunk.push(HUNK_SEPARATOR));
+        assert_eq!(hunk.get_unified_header(), "@@ -1,5 +1,5 @@");
+        let unified_lines = hunk.get_unified_lines();
+        let lines: Vec<(&str, Option<usize>, Option<usize>)> = unified_lines
+            .iter()
+            .map(|line| (line.line.as_str(), line.old_index, line.new_index))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (" a", Some(0), Some(0)),
+                ("-b", Some(1), None),
+                ("+B", None, Some(1)),
+                (" c", Some(2), Some(2)),
+                ("-d", Some(3), None),
+                (" e", Some(4), Some(3)),
+                ("+f", None, Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_unified_lines_with_omitted_section() {
+        let mut hunk = ContextHunk::default();
+        for line in &["*** 1,2 ****", "--- 1,3 ----", "  a", "+ b", "  c"] {
+            assert!(hunk.push(line));
+        }
+        let lines: Vec<String> = hunk
+            .get_unified_lines()
+            .into_iter()
+            .map(|line| line.line)
+            .collect();
+        assert_eq!(lines, vec![" a", "+b", " c"]);
+    }
+}
@@ -1,15 +1,35 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::io::Write;
+use std::rc::Rc;
 
 use console::strip_ansi_codes;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::ansi;
 use crate::bat::assets::HighlightingAssets;
+use crate::blame;
 use crate::cli;
+use crate::color_moved;
+use crate::combined_diff;
+use crate::commit_meta::{self, CommitMessage};
 use crate::config::Config;
+use crate::context_diff::{self, ContextHunk};
 use crate::draw;
+use crate::email::{self, EmailHeader};
+use crate::encoding;
+use crate::graph;
+use crate::grep;
+use crate::line_numbers;
+use crate::merge_conflict::MergeConflict;
+use crate::model;
+use crate::moved::{MovedLine, MovedLines};
+use crate::normal_diff::{self, NormalHunk};
 use crate::paint::{self, Painter};
-use crate::parse;
-use crate::style;
+use crate::parse::{self, ExtendedHeader};
+use crate::range_diff::{self, RangeDiff};
+use crate::side_by_side;
+use crate::word_diff;
 
 #[derive(Debug, PartialEq)]
 pub enum State {
@@ -26,9 +46,22 @@ pub enum State {
 pub enum Source {
     GitDiff,     // Coming from a `git diff` command
     DiffUnified, // Coming from a `diff -u` command
+    DiffContext, // Coming from a `diff -c` command
+    DiffNormal,  // Coming from a `diff` command without -u or -c
+    SvnDiff,     // Coming from a `svn diff` command
+    HgDiff,      // Coming from a `hg diff` command
+    Email,       // Coming from a `git format-patch` command, or an mbox of patches
+    RangeDiff,   // Coming from a `git range-diff` command
     Unknown,
 }
 
+impl Source {
+    /// Return true if the file paths of the source have the "a/" and "b/" prefixes of git diffs.
+    pub(crate) fn has_git_diff_names(&self) -> bool {
+        *self == Source::GitDiff || *self == Source::HgDiff || *self == Source::Email
+    }
+}
+
 impl State {
     fn is_in_hunk(&self) -> bool {
         match *self {
@@ -59,35 +92,306 @@ pub fn delta<I>(
 where
     I: Iterator<Item = String>,
 {
-    let mut painter = Painter::new(writer, config, assets);
+    // For each input line not yet written, whether it ended with CRLF. The carriage returns are
+    // removed from the lines, and restored under --color-only.
+    let line_endings = Rc::new(RefCell::new(VecDeque::new()));
+    let mut writer = encoding::RawBytesWriter::new(writer, Rc::clone(&line_endings));
+    let lines = lines.map(|mut line| {
+        let is_crlf = line.ends_with('\r');
+        if is_crlf {
+            line.pop();
+        }
+        if config.color_only {
+            line_endings.borrow_mut().push_back(is_crlf);
+        }
+        line
+    });
+    // The graph prefix of the current line of `git log --graph` output. It is written at the
+    // start of every output line.
+    let graph_prefix = Rc::new(RefCell::new(String::new()));
+    let mut writer = graph::PrefixWriter::new(&mut writer, Rc::clone(&graph_prefix));
+    let mut painter = Painter::new(&mut writer, config, assets);
     let mut minus_file = "".to_string();
     let mut plus_file;
     let mut state = State::Unknown;
     let mut source = Source::Unknown;
+    let mut graph_width = None;
+    let mut context_hunk: Option<ContextHunk> = None;
+    let mut normal_hunk: Option<NormalHunk> = None;
+    let mut diff_line: Option<String> = None;
+    // The extended header lines of the current file of a git diff, until its header is written.
+    let mut extended_header: Option<ExtendedHeader> = None;
+    // The number of lines remaining in each range of the current hunk.
+    let mut hunk_lines_remaining = Vec::new();
+    let mut email_header: Option<EmailHeader> = None;
+    // True while the metadata lines following a decorated "commit" line are read.
+    let mut is_in_commit_metadata = false;
+    // The message of a decorated commit, while its lines are read.
+    let mut commit_message: Option<CommitMessage> = None;
+    let mut range_diff = RangeDiff::default();
+
+    // Moved code is detected in the whole diff, which is read before any line is painted.
+    let mut moved_lines = MovedLines::default();
+    let lines: Box<dyn Iterator<Item = String>> = if config.detect_moved {
+        let lines: Vec<String> = lines.collect();
+        moved_lines = MovedLines::new(&lines);
+        Box::new(lines.into_iter())
+    } else {
+        Box::new(lines)
+    };
 
-    for raw_line in lines {
-        let line = strip_ansi_codes(&raw_line).to_string();
+    let mut lines = lines.peekable();
+    // The lines read ahead of the current line, to recognize grep output or the kind of a hunk.
+    let mut lookahead: VecDeque<String> = VecDeque::new();
+    if !config.color_only {
+        if let Some(first_line) = lines.peek() {
+            if blame::is_blame_line(&strip_ansi_codes(first_line)) {
+                blame::paint_blame(lines, &mut painter, config)?;
+                return painter.emit();
+            }
+        }
+        let mut n_read = 0;
+        let read_lines = std::iter::from_fn(|| {
+            if n_read == lookahead.len() {
+                lookahead.push_back(lines.next()?);
+            }
+            n_read += 1;
+            Some(strip_ansi_codes(&lookahead[n_read - 1]).to_string())
+        });
+        if grep::is_grep_output(read_lines) {
+            grep::paint_grep(lookahead.into_iter().chain(lines), &mut painter, config)?;
+            return painter.emit();
+        }
+    }
+
+    let mut next_line_index = 0;
+    while let Some(mut raw_line) = lookahead.pop_front().or_else(|| lines.next()) {
+        let line_index = next_line_index;
+        next_line_index += 1;
+        let mut line = strip_ansi_codes(&raw_line).to_string();
+        // The lines of a hunk are never commit lines, whatever their text.
+        if hunk_lines_remaining.is_empty() {
+            if let Some(width) = graph::get_commit_line_graph_width(&line) {
+                graph_width = Some(width);
+            }
+        }
+        if let Some(graph_width) = graph_width {
+            // Remove the graph prefix, so that the rest of the line is handled as usual.
+            let width = graph::get_graph_prefix_width(&line, graph_width);
+            let (raw_prefix, raw_rest) = ansi::split_at_visible_char(&raw_line, width);
+            let mut prefix = raw_prefix.to_string();
+            if prefix.contains('\x1b') {
+                prefix.push_str(ansi::ANSI_SGR_RESET);
+            }
+            if prefix != *graph_prefix.borrow() {
+                painter.paint_buffered_lines();
+                painter.emit()?;
+                *graph_prefix.borrow_mut() = prefix;
+            }
+            raw_line = raw_rest.to_string();
+            line = line[width..].to_string();
+        }
         if source == Source::Unknown {
             source = detect_source(&line);
         }
-        if line.starts_with("commit ") {
+        if !hunk_lines_remaining.is_empty() {
+            if count_hunk_line(&line, painter.n_parents, &mut hunk_lines_remaining) {
+                // While lines remain in the hunk, a line is never taken for metadata, e.g. a
+                // removed line starting with "--" is not a "---" file metadata line.
+                state = handle_hunk_line(
+                    &mut painter,
+                    &line,
+                    &raw_line,
+                    moved_lines.get(line_index),
+                    state,
+                    config,
+                );
+                painter.emit()?;
+                if hunk_lines_remaining.iter().all(|n| *n == 0) {
+                    hunk_lines_remaining.clear();
+                }
+                continue;
+            } else {
+                painter.paint_buffered_lines();
+                painter.emit()?;
+                if !config.color_only {
+                    handle_incomplete_hunk(&mut painter, &hunk_lines_remaining, config)?;
+                }
+                hunk_lines_remaining.clear();
+            }
+        }
+        if source == Source::RangeDiff {
+            if range_diff.push(&mut painter, &line) {
+                painter.emit()?;
+                continue;
+            }
+            range_diff.paint_buffered_lines(&mut painter);
+            if range_diff::is_commit_pair_line(&line) {
+                painter.emit()?;
+                if should_decorate(&config.commit_style, config) {
+                    let header = paint::paint_text_with_style_modifier(
+                        &range_diff::get_commit_pair_header(&line).unwrap(),
+                        config.commit_style_modifier,
+                        config,
+                    );
+                    handle_commit_meta_header_line(&mut painter, &header, config)?;
+                } else {
+                    writeln!(painter.writer, "{}", raw_line)?;
+                }
+                continue;
+            }
+        }
+        if source == Source::DiffContext {
+            if let Some(hunk) = context_hunk.as_mut() {
+                if hunk.push(&line) {
+                    continue;
+                }
+                paint_context_hunk(&mut painter, &context_hunk.take().unwrap(), config)?;
+                state = State::Unknown;
+            }
+            if line == context_diff::HUNK_SEPARATOR {
+                painter.paint_buffered_lines();
+                painter.emit()?;
+                context_hunk = Some(ContextHunk::default());
+                if config.color_only {
+                    writeln!(painter.writer, "{}", raw_line)?;
+                }
+                continue;
+            }
+        } else if source == Source::DiffNormal {
+            if let Some(hunk) = normal_hunk.as_mut() {
+                if hunk.push(&line) {
+                    continue;
+                }
+                paint_normal_hunk(&mut painter, &normal_hunk.take().unwrap(), config)?;
+                state = State::Unknown;
+            }
+            if let Some(hunk) = NormalHunk::new(&line) {
+                painter.paint_buffered_lines();
+                painter.emit()?;
+                // A normal diff of directories has no file metadata lines other than the "diff"
+                // line, e.g. "diff -r a/src/delta.rs b/src/delta.rs".
+                if let Some(diff_line) = diff_line.take().filter(|_| state == State::FileMeta) {
+                    painter.set_syntax(parse::get_file_extension_from_diff_line(&diff_line));
+                    if should_decorate(&config.file_style, config) {
+                        handle_generic_file_meta_header_line(&mut painter, &diff_line, config)?;
+                    }
+                }
+                normal_hunk = Some(hunk);
+                if config.color_only {
+                    writeln!(painter.writer, "{}", raw_line)?;
+                }
+                continue;
+            }
+        }
+        if let Some(header) = email_header.as_mut() {
+            if header.push(&line, &raw_line) {
+                continue;
+            }
+            handle_email_header(&mut painter, &email_header.take().unwrap(), config)?;
+        }
+        if is_in_commit_metadata {
+            match commit_meta::get_painted_metadata_line(&line, config) {
+                Some(painted_line) => {
+                    writeln!(painter.writer, "{}", painted_line)?;
+                    continue;
+                }
+                None => {
+                    is_in_commit_metadata = false;
+                    // The blank line preceding the commit message.
+                    if line.is_empty() {
+                        commit_message = Some(CommitMessage::new(&mut painter));
+                    }
+                }
+            }
+        } else if let Some(message) = commit_message.as_mut() {
+            match message.paint_line(&line, &mut painter) {
+                Some(painted_line) => {
+                    writeln!(painter.writer, "{}", painted_line)?;
+                    continue;
+                }
+                None => commit_message = None,
+            }
+        }
+        if line.starts_with("commit ")
+            || line.starts_with("diff ")
+            || source == Source::Email && (email::is_mbox_from_line(&line) || line == "-- ")
+        {
+            handle_pending_file_meta_header(&mut painter, extended_header.take(), config)?;
+        }
+        if source == Source::Email && email::is_mbox_from_line(&line) {
             painter.paint_buffered_lines();
             state = State::CommitMeta;
-            if config.commit_style != cli::SectionStyle::Plain {
+            if should_decorate(&config.commit_style, config) {
                 painter.emit()?;
-                handle_commit_meta_header_line(&mut painter, &raw_line, config)?;
+                email_header = Some(EmailHeader::default());
+                continue;
+            }
+        } else if line.starts_with("commit ") {
+            painter.paint_buffered_lines();
+            state = State::CommitMeta;
+            if should_decorate(&config.commit_style, config) {
+                painter.emit()?;
+                let header = commit_meta::get_commit_header(&line, config);
+                handle_commit_meta_header_line(&mut painter, &header, config)?;
+                is_in_commit_metadata = true;
                 continue;
             }
         } else if line.starts_with("diff ") {
             painter.paint_buffered_lines();
             state = State::FileMeta;
+            if line.starts_with("diff --git ") {
+                extended_header = Some(ExtendedHeader::new(&line));
+            }
+            diff_line = Some(line.clone());
+        } else if state == State::FileMeta
+            && extended_header.as_mut().map(|header| header.push(&line)) == Some(true)
+        {
+            // An extended header line of a git diff, such as "new file mode 100755", shown in the
+            // file header.
+        } else if source == Source::SvnDiff && line.starts_with("Index: ") {
+            // Followed by a "=====" line, and the "---" and "+++" lines.
+            painter.paint_buffered_lines();
+            state = State::FileMeta;
+        } else if source == Source::SvnDiff && line.starts_with("Property changes on: ") {
+            // The changes to the Subversion properties of the file are written as they are.
+            painter.paint_buffered_lines();
+            state = State::Unknown;
+        } else if source == Source::DiffContext && context_diff::is_file_meta_line(&line) {
+            painter.paint_buffered_lines();
+            minus_file = parse::get_file_path_from_file_meta_line(&line, false);
+            painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+                &minus_file,
+            ));
+            state = State::FileMeta;
+        } else if source == Source::DiffContext
+            && state == State::FileMeta
+            && line.starts_with("--- ")
+        {
+            plus_file = parse::get_file_path_from_file_meta_line(&line, false);
+            painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+                &plus_file,
+            ));
+            if should_decorate(&config.file_style, config) {
+                painter.emit()?;
+                handle_file_meta_header_line(
+                    &mut painter,
+                    &minus_file,
+                    &plus_file,
+                    None,
+                    config,
+                    true,
+                )?;
+            }
         } else if (state == State::FileMeta || source == Source::DiffUnified)
-            // FIXME: For unified diff input, removal ("-") of a line starting with "--" (e.g. a
-            // Haskell or SQL comment) will be confused with the "---" file metadata marker.
-            && (line.starts_with("--- ") || line.starts_with("rename from "))
-            && config.file_style != cli::SectionStyle::Plain
+            && line.starts_with("--- ")
         {
-            minus_file = parse::get_file_path_from_file_meta_line(&line, source == Source::GitDiff);
+            minus_file = match &extended_header {
+                Some(extended_header) => extended_header.get_file_path_from_file_meta_line(&line),
+                None => {
+                    parse::get_file_path_from_file_meta_line(&line, source.has_git_diff_names())
+                }
+            };
             if source == Source::DiffUnified {
                 state = State::FileMeta;
                 painter.set_syntax(parse::get_file_extension_from_marker_line(&line));
@@ -97,30 +401,57 @@ where
                     &minus_file,
                 ));
             }
-        } else if (line.starts_with("+++ ") || line.starts_with("rename to "))
-            && config.file_style != cli::SectionStyle::Plain
-        {
-            plus_file = parse::get_file_path_from_file_meta_line(&line, source == Source::GitDiff);
+        } else if state == State::FileMeta && line.starts_with("+++ ") {
+            plus_file = match &extended_header {
+                Some(extended_header) => extended_header.get_file_path_from_file_meta_line(&line),
+                None => {
+                    parse::get_file_path_from_file_meta_line(&line, source.has_git_diff_names())
+                }
+            };
             painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
                 &plus_file,
             ));
-            painter.emit()?;
-            handle_file_meta_header_line(
-                &mut painter,
-                &minus_file,
-                &plus_file,
-                config,
-                source == Source::DiffUnified,
-            )?;
+            let extended_header = extended_header.take();
+            if should_decorate(&config.file_style, config) {
+                painter.emit()?;
+                handle_file_meta_header_line(
+                    &mut painter,
+                    &minus_file,
+                    &plus_file,
+                    extended_header.as_ref(),
+                    config,
+                    source == Source::DiffUnified,
+                )?;
+            }
         } else if line.starts_with("@@") {
+            hunk_lines_remaining = start_hunk(&mut painter, &line);
+            if painter.n_parents == 1 && !hunk_lines_remaining.is_empty() {
+                // The lines of the hunk are read ahead until it is known whether they are the
+                // lines of a word diff.
+                let mut n_read = 0;
+                let hunk_lines = std::iter::from_fn(|| {
+                    if n_read == lookahead.len() {
+                        lookahead.push_back(lines.next()?);
+                    }
+                    n_read += 1;
+                    Some(strip_graph_prefix(&lookahead[n_read - 1], graph_width))
+                });
+                painter.word_diff = word_diff::is_word_diff_hunk(hunk_lines, &hunk_lines_remaining);
+            }
+            if painter.word_diff {
+                // The lines of a word diff do not correspond to the lines of the hunk's ranges.
+                hunk_lines_remaining.clear();
+            }
             state = State::HunkMeta;
-            painter.set_highlighter();
-            if config.hunk_style != cli::SectionStyle::Plain {
+            if should_decorate(&config.hunk_style, config) {
                 painter.emit()?;
                 handle_hunk_meta_line(&mut painter, &line, config)?;
                 continue;
             }
-        } else if source == Source::DiffUnified && line.starts_with("Only in ")
+        } else if (source == Source::DiffUnified
+            || source == Source::DiffContext
+            || source == Source::DiffNormal)
+            && line.starts_with("Only in ")
             || line.starts_with("Submodule ")
             || line.starts_with("Binary files ")
         {
@@ -139,20 +470,34 @@ where
 
             state = State::FileMeta;
             painter.paint_buffered_lines();
-            if config.file_style != cli::SectionStyle::Plain {
+            // The line is shown as the file header, in place of a pending header.
+            extended_header = None;
+            if should_decorate(&config.file_style, config) {
                 painter.emit()?;
                 handle_generic_file_meta_header_line(&mut painter, &raw_line, config)?;
                 continue;
             }
+        } else if source == Source::Email && line == "-- " {
+            // The signature following the last patch of `git format-patch` output, e.g. the git
+            // version.
+            painter.paint_buffered_lines();
+            state = State::Unknown;
         } else if state.is_in_hunk() {
             // A true hunk line should start with one of: '+', '-', ' '. However, handle_hunk_line
             // handles all lines until the state machine transitions away from the hunk states.
-            state = handle_hunk_line(&mut painter, &line, &raw_line, state, config);
+            state = handle_hunk_line(
+                &mut painter,
+                &line,
+                &raw_line,
+                moved_lines.get(line_index),
+                state,
+                config,
+            );
             painter.emit()?;
             continue;
         }
 
-        if state == State::FileMeta && config.file_style != cli::SectionStyle::Plain {
+        if state == State::FileMeta && should_decorate(&config.file_style, config) {
             // The file metadata section is 4 lines. Skip them under non-plain file-styles.
             continue;
         } else {
@@ -161,29 +506,454 @@ where
         }
     }
 
+    if let Some(header) = email_header {
+        handle_email_header(&mut painter, &header, config)?;
+    }
+    handle_pending_file_meta_header(&mut painter, extended_header, config)?;
+    range_diff.paint_buffered_lines(&mut painter);
+    if let Some(hunk) = context_hunk {
+        paint_context_hunk(&mut painter, &hunk, config)?;
+    }
+    if let Some(hunk) = normal_hunk {
+        paint_normal_hunk(&mut painter, &hunk, config)?;
+    }
     painter.paint_buffered_lines();
     painter.emit()?;
     Ok(())
 }
 
+/// Return true if a section with the given style is to be drawn, replacing the input lines of the
+/// section. Under --color-only every input line is written as exactly one output line (as
+/// required by git's interactive.diffFilter), so nothing is drawn and no line is skipped.
+fn should_decorate(section_style: &cli::SectionStyle, config: &Config) -> bool {
+    !config.color_only && *section_style != cli::SectionStyle::Plain
+}
+
+/// Render a parsed diff, painting its commits, file diffs and hunks as delta does the lines of
+/// the diff.
+pub fn render(
+    diff: &model::Diff,
+    config: &Config,
+    assets: &HighlightingAssets,
+    writer: &mut dyn Write,
+) -> std::io::Result<()> {
+    // The parsed lines have no line endings to restore.
+    let mut writer = encoding::RawBytesWriter::new(writer, Rc::new(RefCell::new(VecDeque::new())));
+    let mut painter = Painter::new(&mut writer, config, assets);
+    for commit in &diff.commits {
+        render_commit_meta(&mut painter, &commit.meta, config)?;
+        for file in &commit.files {
+            render_file_meta(&mut painter, file, config)?;
+            for hunk in &file.hunks {
+                render_hunk(&mut painter, hunk, config)?;
+            }
+        }
+    }
+    painter.emit()
+}
+
+/// Write the metadata lines of a commit: the commit header, and the painted metadata lines and
+/// message of a decorated commit.
+fn render_commit_meta(
+    painter: &mut Painter,
+    meta: &[String],
+    config: &Config,
+) -> std::io::Result<()> {
+    let mut lines = meta.iter().peekable();
+    let mut commit_message = None;
+    if let Some(line) = lines.next_if(|line| {
+        line.starts_with("commit ") && should_decorate(&config.commit_style, config)
+    }) {
+        let header = commit_meta::get_commit_header(line, config);
+        handle_commit_meta_header_line(painter, &header, config)?;
+        while let Some(painted_line) = lines
+            .peek()
+            .and_then(|line| commit_meta::get_painted_metadata_line(line, config))
+        {
+            writeln!(painter.writer, "{}", painted_line)?;
+            lines.next();
+        }
+        // The blank line preceding the commit message.
+        if lines.peek().map(|line| line.is_empty()) == Some(true) {
+            commit_message = Some(CommitMessage::new(painter));
+            writeln!(painter.writer)?;
+            lines.next();
+        }
+    }
+    for line in lines {
+        match commit_message
+            .as_mut()
+            .and_then(|message| message.paint_line(line, painter))
+        {
+            Some(painted_line) => writeln!(painter.writer, "{}", painted_line)?,
+            None => {
+                commit_message = None;
+                writeln!(painter.writer, "{}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the metadata lines of a file diff, or the file header replacing them.
+fn render_file_meta(
+    painter: &mut Painter,
+    file: &model::FileDiff,
+    config: &Config,
+) -> std::io::Result<()> {
+    painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+        &file.plus_file,
+    ));
+    if !should_decorate(&config.file_style, config) {
+        for line in &file.meta {
+            writeln!(painter.writer, "{}", line)?;
+        }
+        return Ok(());
+    }
+    let diff_line = file.meta.first().map(String::as_str).unwrap_or("");
+    let mut extended_header = Some(diff_line)
+        .filter(|line| line.starts_with("diff --git "))
+        .map(ExtendedHeader::new);
+    if let Some(extended_header) = extended_header.as_mut() {
+        for line in &file.meta[1..] {
+            extended_header.push(line);
+        }
+    }
+    let generic_line = file.meta.iter().find(|line| {
+        line.starts_with("Only in ")
+            || line.starts_with("Submodule ")
+            || line.starts_with("Binary files ")
+    });
+    if file.meta.iter().any(|line| line.starts_with("+++ ")) {
+        handle_file_meta_header_line(
+            painter,
+            &file.minus_file,
+            &file.plus_file,
+            extended_header.as_ref(),
+            config,
+            detect_source(diff_line) == Source::DiffUnified,
+        )
+    } else if let Some(line) = generic_line {
+        handle_generic_file_meta_header_line(painter, line, config)
+    } else if extended_header.is_some() {
+        handle_pending_file_meta_header(painter, extended_header, config)
+    } else {
+        // Lines following a hunk that describe no file are written as they are.
+        for line in &file.meta {
+            writeln!(painter.writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the header of a hunk and paint its lines.
+fn render_hunk(painter: &mut Painter, hunk: &model::Hunk, config: &Config) -> std::io::Result<()> {
+    start_hunk(painter, &hunk.header);
+    painter.emit()?;
+    if should_decorate(&config.hunk_style, config) {
+        handle_hunk_meta_line(painter, &hunk.header, config)?;
+    } else {
+        writeln!(painter.writer, "{}", hunk.header)?;
+    }
+    let mut state = State::HunkMeta;
+    for line in &hunk.lines {
+        let marker = match line.kind {
+            model::LineKind::Minus => Some('-'),
+            model::LineKind::Plus => Some('+'),
+            model::LineKind::Zero => Some(' '),
+            model::LineKind::Other => None,
+        };
+        state = paint_hunk_line(
+            painter,
+            marker,
+            (&line.markers, &line.text),
+            &line.text,
+            None,
+            state,
+            config,
+        );
+        painter.emit()?;
+    }
+    painter.paint_buffered_lines();
+    painter.emit()
+}
+
 /// Try to detect what is producing the input for delta.
 ///
 /// Currently can detect:
-/// * git diff
+/// * git diff, including the combined diffs of merge commits
 /// * diff -u
-fn detect_source(line: &str) -> Source {
-    if line.starts_with("commit ") || line.starts_with("diff --git ") {
+/// * diff -c
+/// * diff (normal format)
+/// * svn diff
+/// * hg diff
+/// * git format-patch
+/// * git range-diff
+pub(crate) fn detect_source(line: &str) -> Source {
+    if line.starts_with("commit ")
+        || line.starts_with("diff --git ")
+        || line.starts_with("diff --cc ")
+        || line.starts_with("diff --combined ")
+    {
         Source::GitDiff
+    } else if range_diff::is_commit_pair_line(line) {
+        Source::RangeDiff
+    } else if email::is_mbox_from_line(line) {
+        Source::Email
+    } else if line.starts_with("Index: ") {
+        Source::SvnDiff
+    } else if parse::is_hg_diff_line(line) {
+        Source::HgDiff
     } else if line.starts_with("diff -u ")
         || line.starts_with("diff -U")
         || line.starts_with("--- ")
     {
         Source::DiffUnified
+    } else if line.starts_with("diff -c ")
+        || line.starts_with("diff -C")
+        || context_diff::is_file_meta_line(line)
+    {
+        Source::DiffContext
+    } else if normal_diff::is_change_command(line) {
+        Source::DiffNormal
     } else {
         Source::Unknown
     }
 }
 
+/// Return a line without its ANSI escape sequences, and the line itself, both without the graph
+/// prefix of `git log --graph` output, given the width of the graph prefix of the current commit.
+fn strip_graph_prefix(raw_line: &str, graph_width: Option<usize>) -> (String, String) {
+    let line = strip_ansi_codes(raw_line).to_string();
+    match graph_width {
+        Some(graph_width) => {
+            let width = graph::get_graph_prefix_width(&line, graph_width);
+            (
+                line[width..].to_string(),
+                ansi::split_at_visible_char(raw_line, width).1.to_string(),
+            )
+        }
+        None => (line, raw_line.to_string()),
+    }
+}
+
+/// Prepare the painter for the hunk with the given header line: set the line numbers of the hunk's
+/// first line, and the number of parents. Return the number of lines of each of the hunk's ranges.
+fn start_hunk(painter: &mut Painter, line: &str) -> Vec<usize> {
+    painter.paint_buffered_lines();
+    painter.set_highlighter();
+    painter.word_diff = false;
+    painter.merge_conflict.clear();
+    let (_, ranges) = parse::parse_hunk_metadata(line);
+    painter.minus_line_number = ranges.first().map(|(start, _)| *start).unwrap_or(0);
+    painter.plus_line_number = ranges.last().map(|(start, _)| *start).unwrap_or(0);
+    painter.line_number_width = line_numbers::get_line_number_width(&ranges);
+    // A combined diff hunk header has a range for each parent, and one for the result.
+    painter.n_parents = ranges.len().saturating_sub(1).max(1);
+    get_hunk_lines_remaining(&ranges)
+}
+
+/// Return the number of lines of each of the ranges of a hunk header: one for each parent,
+/// followed by one for the result. A header without such ranges gives no counts.
+pub(crate) fn get_hunk_lines_remaining(ranges: &[(usize, usize)]) -> Vec<usize> {
+    if ranges.len() >= 2 {
+        ranges.iter().map(|(_, count)| *count).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Count a line of the current hunk against the number of lines remaining in each of the hunk's
+/// ranges: one for each parent, followed by one for the result. A line is present in the result
+/// unless it is removed from some parent. A line present in the result is present in a parent
+/// unless it is added to it; any other line is present in the parents it is removed from. Return
+/// false if the line is not a hunk line, or if the ranges have no lines left for it, meaning that
+/// the hunk is truncated or corrupt.
+pub fn count_hunk_line(line: &str, n_parents: usize, hunk_lines_remaining: &mut [usize]) -> bool {
+    if line.starts_with('\\') {
+        // "\ No newline at end of file"
+        return true;
+    }
+    // An unchanged empty line may have lost its trailing whitespace.
+    let markers: Vec<char> = if line.is_empty() {
+        vec![' '; n_parents]
+    } else {
+        line.chars().take(n_parents).collect()
+    };
+    if markers.len() < n_parents || !markers.iter().all(|c| *c == ' ' || *c == '-' || *c == '+') {
+        return false;
+    }
+    let is_in_result = !markers.contains(&'-');
+    let mut is_present: Vec<bool> = markers
+        .iter()
+        .map(|c| *c == '-' || *c == ' ' && is_in_result)
+        .collect();
+    is_present.push(is_in_result);
+    let has_lines_left = hunk_lines_remaining
+        .iter()
+        .zip(&is_present)
+        .all(|(n, is_present)| *n > 0 || !is_present);
+    if has_lines_left {
+        for (n, is_present) in hunk_lines_remaining.iter_mut().zip(&is_present) {
+            if *is_present {
+                *n -= 1;
+            }
+        }
+    }
+    has_lines_left
+}
+
+/// Write a warning that the hunk ended before the number of lines given in its header was read.
+fn handle_incomplete_hunk(
+    painter: &mut Painter,
+    hunk_lines_remaining: &[usize],
+    config: &Config,
+) -> std::io::Result<()> {
+    let n_missing = hunk_lines_remaining.iter().max().unwrap_or(&0);
+    let warning = format!(
+        "(hunk is incomplete: {} more line{} expected)",
+        n_missing,
+        if *n_missing == 1 { "" } else { "s" }
+    );
+    writeln!(
+        painter.writer,
+        "{}",
+        paint::paint_text_foreground(&warning, config.hunk_color, config.true_color)
+    )
+}
+
+/// Paint a hunk of a context diff. Under --color-only, the painted lines are put back into the
+/// layout of the context diff.
+fn paint_context_hunk(
+    painter: &mut Painter,
+    hunk: &ContextHunk,
+    config: &Config,
+) -> std::io::Result<()> {
+    let unified_lines = hunk.get_unified_lines();
+    let painted_lines = paint_unified_hunk(
+        painter,
+        &hunk.get_unified_header(),
+        unified_lines
+            .iter()
+            .map(|unified_line| unified_line.line.as_str()),
+        config,
+    )?;
+    if config.color_only {
+        let mut old_lines = hunk.old_lines.clone();
+        let mut new_lines = hunk.new_lines.clone();
+        if let Some(painted_lines) = painted_lines {
+            for (unified_line, painted_line) in unified_lines.iter().zip(painted_lines) {
+                if let Some(i) = unified_line.old_index {
+                    old_lines[i] =
+                        get_painted_input_line(&painted_line, &unified_line.line, &old_lines[i]);
+                }
+                if let Some(j) = unified_line.new_index {
+                    new_lines[j] =
+                        get_painted_input_line(&painted_line, &unified_line.line, &new_lines[j]);
+                }
+            }
+        }
+        for line in hunk
+            .old_range_line
+            .iter()
+            .chain(&old_lines)
+            .chain(&hunk.new_range_line)
+            .chain(&new_lines)
+        {
+            writeln!(painter.writer, "{}", line)?;
+        }
+    }
+    painter.emit()
+}
+
+/// Paint a hunk of a normal diff. Under --color-only, the painted lines are put back into the
+/// layout of the normal diff.
+fn paint_normal_hunk(
+    painter: &mut Painter,
+    hunk: &NormalHunk,
+    config: &Config,
+) -> std::io::Result<()> {
+    let unified_lines = hunk.get_unified_lines();
+    let painted_lines = paint_unified_hunk(
+        painter,
+        &hunk.get_unified_header(),
+        unified_lines.iter().flatten().map(String::as_str),
+        config,
+    )?;
+    if config.color_only {
+        let mut painted_lines = painted_lines.map(Vec::into_iter);
+        for (line, unified_line) in hunk.lines.iter().zip(&unified_lines) {
+            // The separator line has no painted line.
+            let painted_line = unified_line
+                .as_ref()
+                .and_then(|_| painted_lines.as_mut()?.next());
+            match (unified_line, painted_line) {
+                (Some(unified_line), Some(painted_line)) => writeln!(
+                    painter.writer,
+                    "{}",
+                    get_painted_input_line(&painted_line, unified_line, line)
+                )?,
+                _ => writeln!(painter.writer, "{}", line)?,
+            }
+        }
+    }
+    painter.emit()
+}
+
+/// Paint a hunk of a diff in another format, given in unified diff form, with the given header.
+/// Under --color-only nothing is written: the painted lines are returned instead, or None if they
+/// do not correspond one-to-one with the given lines.
+fn paint_unified_hunk<'a>(
+    painter: &mut Painter,
+    header: &str,
+    unified_lines: impl Iterator<Item = &'a str>,
+    config: &Config,
+) -> std::io::Result<Option<Vec<String>>> {
+    start_hunk(painter, header);
+    painter.emit()?;
+    if should_decorate(&config.hunk_style, config) {
+        handle_hunk_meta_line(painter, header, config)?;
+    } else if !config.color_only {
+        writeln!(painter.writer, "{}", header)?;
+    }
+    let mut state = State::HunkMeta;
+    let mut n_lines = 0;
+    for unified_line in unified_lines {
+        state = handle_hunk_line(painter, unified_line, unified_line, None, state, config);
+        n_lines += 1;
+    }
+    painter.paint_buffered_lines();
+    if !config.color_only {
+        return Ok(None);
+    }
+    let painted_lines: Vec<String> = painter.output_buffer.lines().map(String::from).collect();
+    painter.output_buffer.clear();
+    Ok(Some(painted_lines).filter(|painted_lines| painted_lines.len() == n_lines))
+}
+
+/// Return a painted line of a hunk in unified diff form, with its marker replaced by the marker of
+/// the input line it comes from (e.g. "! " in a context diff, or "< " in a normal diff).
+fn get_painted_input_line(painted_line: &str, unified_line: &str, line: &str) -> String {
+    if unified_line == line {
+        return painted_line.to_string();
+    }
+    // The marker is the last character of the first part, following the escape sequences that
+    // style it.
+    let (painted_marker, painted_rest) = ansi::split_at_visible_char(painted_line, 1);
+    let marker_start = painted_marker
+        .char_indices()
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let marker: String = line.chars().take(2).collect();
+    format!(
+        "{}{}{}",
+        &painted_marker[..marker_start],
+        marker,
+        painted_rest
+    )
+}
+
 fn handle_commit_meta_header_line(
     painter: &mut Painter,
     line: &str,
@@ -206,20 +976,65 @@ fn handle_commit_meta_header_line(
     Ok(())
 }
 
-/// Construct file change line from minus and plus file and write with FileMeta styling.
+/// Write the email headers of a patch as a commit-style header showing the position of the patch
+/// in its series and its subject, followed by the sender and date.
+fn handle_email_header(
+    painter: &mut Painter,
+    header: &EmailHeader,
+    config: &Config,
+) -> std::io::Result<()> {
+    let commit_header = paint::paint_text_with_style_modifier(
+        &header.get_commit_header(),
+        config.commit_style_modifier,
+        config,
+    );
+    handle_commit_meta_header_line(painter, &commit_header, config)?;
+    for line in header.get_shown_lines() {
+        writeln!(painter.writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Construct file change line from minus and plus file, and the extended header lines of a git
+/// diff, and write with FileMeta styling.
 fn handle_file_meta_header_line(
     painter: &mut Painter,
     minus_file: &str,
     plus_file: &str,
+    extended_header: Option<&ExtendedHeader>,
     config: &Config,
     comparing: bool,
 ) -> std::io::Result<()> {
-    let line = parse::get_file_change_description_from_file_paths(minus_file, plus_file, comparing);
+    let line = match extended_header {
+        Some(extended_header) => extended_header.get_file_change_description(minus_file, plus_file),
+        None => {
+            parse::get_file_change_description_from_file_paths(minus_file, plus_file, comparing)
+        }
+    };
     handle_generic_file_meta_header_line(painter, &line, config)
 }
 
+/// Write the header of a file of a git diff that had no "---" and "+++" lines, such as a renamed
+/// file with unchanged contents, or a file whose mode changed, once its extended header lines
+/// have been read.
+fn handle_pending_file_meta_header(
+    painter: &mut Painter,
+    extended_header: Option<ExtendedHeader>,
+    config: &Config,
+) -> std::io::Result<()> {
+    let description = extended_header
+        .and_then(|extended_header| extended_header.get_file_change_description_without_hunks());
+    match description {
+        Some(description) if should_decorate(&config.file_style, config) => {
+            painter.emit()?;
+            handle_generic_file_meta_header_line(painter, &description, config)
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Write `line` with FileMeta styling.
-fn handle_generic_file_meta_header_line(
+pub fn handle_generic_file_meta_header_line(
     painter: &mut Painter,
     line: &str,
     config: &Config,
@@ -233,7 +1048,7 @@ fn handle_generic_file_meta_header_line(
     writeln!(painter.writer)?;
     draw_fn(
         painter.writer,
-        &paint::paint_text_foreground(line, config.file_color, config.true_color),
+        &paint::paint_text_with_style_modifier(line, config.file_style_modifier, config),
         config.terminal_width,
         config.file_color,
         false,
@@ -253,8 +1068,12 @@ fn handle_hunk_meta_line(
         cli::SectionStyle::Plain => panic!(),
         cli::SectionStyle::Omit => return Ok(()),
     };
-    let (raw_code_fragment, line_number) = parse::parse_hunk_metadata(&line);
-    let code_fragment = prepare(raw_code_fragment, false, config);
+    let (raw_code_fragment, ranges) = parse::parse_hunk_metadata(&line);
+    let line_number = ranges
+        .last()
+        .map(|(start, _)| start.to_string())
+        .unwrap_or_default();
+    let code_fragment = prepare(raw_code_fragment, 1, false, config);
     if !code_fragment.is_empty() {
         let syntax_style_sections = Painter::get_line_syntax_style_sections(
             &code_fragment,
@@ -264,14 +1083,11 @@ fn handle_hunk_meta_line(
         );
         Painter::paint_lines(
             vec![syntax_style_sections],
-            vec![vec![(
-                style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
-                &code_fragment,
-            )]],
+            vec![vec![(config.hunk_header_style_modifier, &code_fragment)]],
             &mut painter.output_buffer,
             config,
             "",
-            style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+            config.hunk_header_style_modifier,
             Some(false),
         );
         painter.output_buffer.pop(); // trim newline
@@ -288,12 +1104,13 @@ fn handle_hunk_meta_line(
     writeln!(
         painter.writer,
         "\n{}",
-        paint::paint_text_foreground(line_number, config.hunk_color, config.true_color)
+        paint::paint_text_foreground(&line_number, config.hunk_color, config.true_color)
     )?;
     Ok(())
 }
 
-/// Handle a hunk line, i.e. a minus line, a plus line, or an unchanged line.
+/// Handle a hunk line, i.e. a minus line, a plus line, or an unchanged line. A minus or plus line
+/// may be moved code, detected by --detect-moved or colored as moved by git.
 // In the case of a minus or plus line, we store the line in a
 // buffer. When we exit the changed region we process the collected
 // minus and plus lines jointly, in order to paint detailed
@@ -303,6 +1120,37 @@ fn handle_hunk_line(
     painter: &mut Painter,
     line: &str,
     raw_line: &str,
+    moved_line: Option<&MovedLine>,
+    state: State,
+    config: &Config,
+) -> State {
+    if painter.word_diff {
+        // Word diff lines have no marker column: each line is painted as it is read.
+        painter.paint_buffered_lines();
+        word_diff::paint_word_diff_line(painter, line, raw_line, config);
+        return State::HunkZero;
+    }
+    let marker = combined_diff::get_hunk_line_marker(line, painter.n_parents);
+    paint_hunk_line(
+        painter,
+        marker,
+        combined_diff::split_markers(line, painter.n_parents),
+        raw_line,
+        moved_line,
+        state,
+        config,
+    )
+}
+
+/// Paint a hunk line given its marker (see combined_diff::get_hunk_line_marker), and its marker
+/// columns and the text following them. A line with no '-', '+' or ' ' marker is written as the
+/// raw line.
+fn paint_hunk_line(
+    painter: &mut Painter,
+    marker: Option<char>,
+    (markers, text): (&str, &str),
+    raw_line: &str,
+    moved_line: Option<&MovedLine>,
     state: State,
     config: &Config,
 ) -> State {
@@ -314,37 +1162,99 @@ fn handle_hunk_line(
     {
         painter.paint_buffered_lines();
     }
-    match line.chars().next() {
+    let n_parents = painter.n_parents;
+    if marker == Some('-') || marker == Some('+') {
+        // Moved lines are painted apart from other removed and added lines.
+        let is_moved = moved_line.is_some()
+            || n_parents == 1 && color_moved::is_moved_line(raw_line, marker.unwrap());
+        if is_moved != painter.moved {
+            painter.paint_buffered_lines();
+            painter.moved = is_moved;
+        }
+    }
+    let moved_counterpart = moved_line
+        .and_then(|moved_line| moved_line.counterpart.as_ref())
+        .map(|counterpart| prepare(counterpart, 1, true, config));
+    match marker {
         Some('-') => {
             if state == State::HunkPlus {
                 painter.paint_buffered_lines();
             }
-            painter.minus_lines.push(prepare(&line, true, config));
+            if n_parents > 1 {
+                painter.minus_markers.push(markers.to_string());
+            }
+            if painter.moved {
+                painter.minus_moved_counterparts.push(moved_counterpart);
+            }
+            painter.minus_lines.push(prepare_text(text, config));
             State::HunkMinus
         }
+        Some('+') if painter.merge_conflict.is_active() || MergeConflict::is_start_marker(text) => {
+            // The lines buffered before the region, or before a line interrupting it, are
+            // painted first.
+            if !painter.merge_conflict.is_buffered() {
+                painter.paint_buffered_lines();
+            }
+            let is_end = painter
+                .merge_conflict
+                .push(markers, text, prepare_text(text, config));
+            if is_end {
+                painter.paint_buffered_lines();
+            } else if painter.merge_conflict.lines.len() > config.max_buffered_lines {
+                // The region is too large to be buffered: it is painted as added lines.
+                painter.abandon_merge_conflict();
+            }
+            State::HunkPlus
+        }
         Some('+') => {
-            painter.plus_lines.push(prepare(&line, true, config));
+            if n_parents > 1 {
+                painter.plus_markers.push(markers.to_string());
+            }
+            if painter.moved {
+                painter.plus_moved_counterparts.push(moved_counterpart);
+            }
+            painter.plus_lines.push(prepare_text(text, config));
             State::HunkPlus
         }
         Some(' ') => {
-            let prefix = if line.is_empty() { "" } else { &line[..1] };
             painter.paint_buffered_lines();
-            let line = prepare(&line, true, config);
+            let line = prepare_text(text, config);
             let syntax_style_sections = Painter::get_line_syntax_style_sections(
                 &line,
                 &mut painter.highlighter,
                 &painter.config,
                 true,
             );
-            Painter::paint_lines(
-                vec![syntax_style_sections],
-                vec![vec![(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER, &line)]],
-                &mut painter.output_buffer,
-                config,
-                prefix,
-                style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
-                None,
-            );
+            if config.side_by_side && n_parents == 1 {
+                side_by_side::paint_zero_line_side_by_side(
+                    &syntax_style_sections,
+                    &[(config.zero_style_modifier, &line)],
+                    markers,
+                    (
+                        painter.minus_line_number,
+                        painter.plus_line_number,
+                        painter.line_number_width,
+                    ),
+                    &mut painter.output_buffer,
+                    config,
+                );
+            } else {
+                Painter::paint_lines_with_line_numbers(
+                    vec![syntax_style_sections],
+                    vec![vec![(config.zero_style_modifier, &line)]],
+                    (
+                        Some(painter.minus_line_number),
+                        Some(painter.plus_line_number),
+                        painter.line_number_width,
+                    ),
+                    &mut painter.output_buffer,
+                    config,
+                    markers,
+                    config.zero_style_modifier,
+                );
+            }
+            painter.minus_line_number += 1;
+            painter.plus_line_number += 1;
             State::HunkZero
         }
         _ => {
@@ -361,20 +1271,19 @@ fn handle_hunk_line(
     }
 }
 
-/// Replace initial -/+ character with ' ', expand tabs as spaces, and optionally terminate with
-/// newline.
+/// Replace the initial marker columns (a single -/+ character, or one column per parent in a
+/// combined diff) with ' ', expand tabs as spaces, and optionally terminate with newline.
 // Terminating with newline character is necessary for many of the sublime syntax definitions to
 // highlight correctly.
 // See https://docs.rs/syntect/3.2.0/syntect/parsing/struct.SyntaxSetBuilder.html#method.add_from_folder
-fn prepare(line: &str, append_newline: bool, config: &Config) -> String {
+pub fn prepare(line: &str, n_markers: usize, append_newline: bool, config: &Config) -> String {
     let terminator = if append_newline { "\n" } else { "" };
     if !line.is_empty() {
-        let mut line = line.graphemes(true);
+        let line = line.graphemes(true).skip(n_markers);
 
-        // The first column contains a -/+/space character, added by git. We substitute it for a
-        // space now, so that it is not present during syntax highlighting, and substitute again
-        // when emitting the line.
-        line.next();
+        // The first columns contain -/+/space characters, added by git. We substitute them for a
+        // space now, so that they are not present during syntax highlighting, and substitute
+        // again when emitting the line.
 
         format!(" {}{}", expand_tabs(line, config.tab_width), terminator)
     } else {
@@ -382,9 +1291,14 @@ fn prepare(line: &str, append_newline: bool, config: &Config) -> String {
     }
 }
 
+/// Prepare the text of a hunk line following its marker columns, as prepare() does the whole line.
+fn prepare_text(text: &str, config: &Config) -> String {
+    format!(" {}\n", expand_tabs(text.graphemes(true), config.tab_width))
+}
+
 /// Expand tabs as spaces.
 /// tab_width = 0 is documented to mean do not replace tabs.
-fn expand_tabs<'a, I>(line: I, tab_width: usize) -> String
+pub fn expand_tabs<'a, I>(line: I, tab_width: usize) -> String
 where
     I: Iterator<Item = &'a str>,
 {
@@ -402,15 +1316,17 @@ mod tests {
     use super::*;
     use console::strip_ansi_codes;
     use std::env;
-    use syntect::highlighting::StyleModifier;
+    use syntect::highlighting::{Color, FontStyle, StyleModifier};
 
+    use crate::bat::terminal::to_ansi_color;
     use crate::paint;
+    use crate::style;
 
     #[test]
     fn test_added_file() {
         let options = get_command_line_options();
         let output = strip_ansi_codes(&run_delta(ADDED_FILE_INPUT, &options)).to_string();
-        assert!(output.contains("\nadded: a.py\n"));
+        assert!(output.contains("\nadded: a.py (index 8c55b7d)\n"));
         if false {
             // TODO: hline width
             assert_eq!(output, ADDED_FILE_EXPECTED_OUTPUT);
@@ -418,11 +1334,10 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // #128
     fn test_added_empty_file() {
         let options = get_command_line_options();
         let output = strip_ansi_codes(&run_delta(ADDED_EMPTY_FILE, &options)).to_string();
-        assert!(output.contains("\nadded: file\n"));
+        assert!(output.contains("\nadded: file (index e69de29)\n"));
     }
 
     #[test]
@@ -433,8 +1348,8 @@ mod tests {
             &options,
         ))
         .to_string();
-        assert!(output.contains("\nadded: with space/file1\n"));
-        assert!(output.contains("\nadded: nospace/file2\n"));
+        assert!(output.contains("\nadded: with space/file1 (index 84d55c5)\n"));
+        assert!(output.contains("\nadded: nospace/file2 (index af1b8ae)\n"));
     }
 
     #[test]
@@ -444,6 +1359,158 @@ mod tests {
         assert!(output.contains("\nrenamed: a.py ⟶   b.py\n"));
     }
 
+    #[test]
+    fn test_file_header_shows_extended_header_lines() {
+        let options = get_command_line_options();
+        let output =
+            strip_ansi_codes(&run_delta(GIT_DIFF_WITH_EXTENDED_HEADERS, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // A file whose mode changed, with no hunks, is given a header.
+        assert!(lines.contains(&"run.sh (mode 644 ⟶   755)"));
+        // A renamed file with changes is given a single header, showing its similarity.
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("renamed (92%): src/a.rs ⟶   src/b.rs"))
+                .count(),
+            1
+        );
+        assert!(lines.contains(&"renamed (92%): src/a.rs ⟶   src/b.rs (index 3d31e23..c171e6a)"));
+        assert!(lines.contains(&"copied: src/c.rs ⟶   src/d.rs"));
+        assert!(!output.contains("similarity index"));
+        assert!(!output.contains("old mode"));
+    }
+
+    #[test]
+    fn test_file_header_with_quoted_paths() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(GIT_DIFF_WITH_QUOTED_PATHS, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert!(lines.contains(&"café.rs (mode 644 ⟶   755, index ca05282..77c3e26)"));
+        assert!(lines.contains(&"renamed: my file.txt ⟶   my fïle.txt"));
+        assert!(!output.contains("\\303"));
+    }
+
+    #[test]
+    fn test_file_header_with_prefixes_of_diff_git_line() {
+        let options = get_command_line_options();
+        let output =
+            strip_ansi_codes(&run_delta(GIT_DIFF_WITH_UNUSUAL_PREFIXES, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // diff.mnemonicPrefix
+        assert!(lines.contains(&"src/parse.rs (index 3d31e23..c171e6a)"));
+        // diff.noprefix, with directories named "a" and "b"
+        assert!(lines.contains(&"a/x.rs (index 8c55b7d..1f1c8a0)"));
+        assert!(lines.contains(&"b/my a/file.rs (index 3d31e23..c171e6a)"));
+    }
+
+    #[test]
+    fn test_style_strings_of_lines() {
+        let mut options = get_command_line_options();
+        options.minus_style = Some("bold italic #ffaa00 ul".to_string());
+        options.zero_style = Some("syntax #222222".to_string());
+        let output = run_delta(DIFF_UNIFIED_TWO_FILES, &options);
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let orange = Color {
+            r: 0xff,
+            g: 0xaa,
+            b: 0x00,
+            a: 0xff,
+        };
+        // The parts of the style that are not given keep their default.
+        assert_eq!(
+            config.minus_style_modifier,
+            StyleModifier {
+                foreground: Some(orange),
+                background: Some(Color {
+                    r: 0x3f,
+                    g: 0x00,
+                    b: 0x01,
+                    a: 0xff,
+                }),
+                font_style: Some(FontStyle::BOLD | FontStyle::ITALIC | FontStyle::UNDERLINE),
+            }
+        );
+        assert_eq!(config.zero_style_modifier.foreground, None);
+        for style_modifier in &[config.minus_style_modifier, config.zero_style_modifier] {
+            let ansi_style =
+                paint::to_ansi_style(config.no_style.apply(*style_modifier), config.true_color);
+            assert!(output.contains(&ansi_style.prefix().to_string()));
+        }
+    }
+
+    #[test]
+    fn test_deprecated_color_options() {
+        let mut options = get_command_line_options();
+        options.minus_color = Some("#123456".to_string());
+        options.plus_color = Some("#123456".to_string());
+        options.plus_style = Some("bold".to_string());
+        options.hunk_color = Some("red".to_string());
+        options.line_numbers_minus_color = Some("blue".to_string());
+        options.line_numbers_plus_style = Some("bold yellow".to_string());
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let color = Color {
+            r: 0x12,
+            g: 0x34,
+            b: 0x56,
+            a: 0xff,
+        };
+        // A deprecated color option replaces a color of the default style of its element.
+        assert_eq!(
+            config.minus_style_modifier,
+            StyleModifier {
+                foreground: Some(style::NO_COLOR),
+                background: Some(color),
+                font_style: Some(FontStyle::empty()),
+            }
+        );
+        assert_eq!(
+            config.plus_style_modifier,
+            StyleModifier {
+                foreground: None,
+                background: Some(color),
+                font_style: Some(FontStyle::BOLD),
+            }
+        );
+        assert_eq!(
+            config.hunk_color,
+            paint::color_from_ansi_name("red").unwrap()
+        );
+        assert_eq!(config.hunk_style, cli::SectionStyle::Box);
+        assert_eq!(
+            config.line_numbers_minus_style_modifier.foreground,
+            paint::color_from_ansi_name("blue")
+        );
+        assert_eq!(
+            config.line_numbers_plus_style_modifier,
+            StyleModifier {
+                foreground: paint::color_from_ansi_name("yellow"),
+                background: Some(style::NO_COLOR),
+                font_style: Some(FontStyle::BOLD),
+            }
+        );
+    }
+
+    #[test]
+    fn test_style_string_of_file_header() {
+        let mut options = get_command_line_options();
+        options.file_style = Some("box bold red".to_string());
+        let output = run_delta(DIFF_UNIFIED_TWO_FILES, &options);
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        assert_eq!(config.file_style, cli::SectionStyle::Box);
+        assert_eq!(
+            config.file_color,
+            paint::color_from_ansi_name("red").unwrap()
+        );
+        let header = to_ansi_color(config.file_color, config.true_color)
+            .bold()
+            .paint("comparing: one.rs ⟶   src/two.rs");
+        assert!(output.contains(&header.to_string()));
+    }
+
     #[test]
     fn test_recognized_file_type() {
         // In addition to the background color, the code has language syntax highlighting.
@@ -566,21 +1633,29 @@ mod tests {
             } else {
                 assert_eq!(config.theme.unwrap().name.as_ref().unwrap(), expected_theme);
             }
+            let default_styles =
+                style::get_default_styles(expected_mode == Mode::Light, is_true_color, false);
+            let get_background = |style_string: &str| {
+                style::parse_style_string(style_string)
+                    .unwrap()
+                    .get_style_modifier(style::NO_BACKGROUND_COLOR_STYLE_MODIFIER)
+                    .background
+            };
             assert_eq!(
-                config.minus_style_modifier.background.unwrap(),
-                style::get_minus_color_default(expected_mode == Mode::Light, is_true_color)
+                config.minus_style_modifier.background,
+                get_background(&default_styles.minus)
             );
             assert_eq!(
-                config.minus_emph_style_modifier.background.unwrap(),
-                style::get_minus_emph_color_default(expected_mode == Mode::Light, is_true_color)
+                config.minus_emph_style_modifier.background,
+                get_background(&default_styles.minus_emph)
             );
             assert_eq!(
-                config.plus_style_modifier.background.unwrap(),
-                style::get_plus_color_default(expected_mode == Mode::Light, is_true_color)
+                config.plus_style_modifier.background,
+                get_background(&default_styles.plus)
             );
             assert_eq!(
-                config.plus_emph_style_modifier.background.unwrap(),
-                style::get_plus_emph_color_default(expected_mode == Mode::Light, is_true_color)
+                config.plus_emph_style_modifier.background,
+                get_background(&default_styles.plus_emph)
             );
         }
     }
@@ -650,19 +1725,41 @@ mod tests {
             minus_emph_color: None,
             plus_color: None,
             plus_emph_color: None,
+            minus_moved_color: None,
+            plus_moved_color: None,
+            minus_style: None,
+            minus_emph_style: None,
+            plus_style: None,
+            plus_emph_style: None,
+            minus_moved_style: None,
+            plus_moved_style: None,
+            zero_style: None,
             color_only: false,
+            side_by_side: false,
+            line_numbers: false,
+            line_numbers_minus_style: None,
+            line_numbers_plus_style: None,
+            line_numbers_zero_style: None,
+            line_numbers_minus_color: None,
+            line_numbers_plus_color: None,
+            line_numbers_zero_color: None,
             keep_plus_minus_markers: false,
             theme: None,
             highlight_removed: false,
-            commit_style: cli::SectionStyle::Plain,
-            commit_color: "Yellow".to_string(),
-            file_style: cli::SectionStyle::Underline,
-            file_color: "Blue".to_string(),
-            hunk_style: cli::SectionStyle::Box,
-            hunk_color: "blue".to_string(),
+            detect_moved: false,
+            commit_style: None,
+            commit_color: None,
+            commit_hash_length: None,
+            relative_dates: false,
+            commit_message_markdown: false,
+            file_style: None,
+            file_color: None,
+            hunk_style: None,
+            hunk_color: None,
             true_color: "always".to_string(),
             width: Some("variable".to_string()),
             paging_mode: "auto".to_string(),
+            encoding: "utf-8".to_string(),
             tab_width: 4,
             show_background_colors: false,
             list_languages: false,
@@ -673,21 +1770,690 @@ mod tests {
     }
 
     #[test]
-    fn test_diff_unified_two_files() {
+    fn test_diff_unified_two_files() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options)).to_string();
+        let mut lines = output.split('\n');
+
+        // Header
+        assert_eq!(lines.nth(1).unwrap(), "comparing: one.rs ⟶   src/two.rs");
+        // Line
+        assert_eq!(lines.nth(2).unwrap(), "5");
+        // Change
+        assert_eq!(lines.nth(2).unwrap(), " println!(\"Hello ruster\");");
+        // Next chunk
+        assert_eq!(lines.nth(2).unwrap(), "43");
+        // Unchanged in second chunk
+        assert_eq!(lines.nth(2).unwrap(), " Unchanged");
+    }
+
+    #[test]
+    fn test_diff_context_two_files() {
+        // A context diff is displayed as the equivalent unified diff.
+        let options = get_command_line_options();
+        assert_eq!(
+            strip_ansi_codes(&run_delta(DIFF_CONTEXT_TWO_FILES, &options)),
+            strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options))
+        );
+    }
+
+    #[test]
+    fn test_diff_normal_two_directories() {
+        let options = get_command_line_options();
+        let output =
+            strip_ansi_codes(&run_delta(DIFF_NORMAL_TWO_DIRECTORIES, &options)).to_string();
+        let mut lines = output.split('\n');
+
+        // Header
+        assert_eq!(lines.nth(1).unwrap(), "diff -r a/src/one.rs b/src/one.rs");
+        // Line
+        assert_eq!(lines.nth(2).unwrap(), "6");
+        // Change
+        assert_eq!(lines.next().unwrap(), " println!(\"Hello rust\");");
+        assert_eq!(lines.next().unwrap(), " println!(\"Hello ruster\");");
+        // Deletion, following line 43 of the new file
+        assert_eq!(lines.nth(1).unwrap(), "43");
+        assert_eq!(lines.next().unwrap(), " Change one");
+        // Addition
+        assert_eq!(lines.nth(1).unwrap(), "45");
+        assert_eq!(lines.next().unwrap(), " Change two");
+    }
+
+    #[test]
+    fn test_get_painted_input_line() {
+        // Lines of a context diff
+        assert_eq!(
+            get_painted_input_line("\x1b[41m-x\x1b[0m", "-x", "! x"),
+            "\x1b[41m! x\x1b[0m"
+        );
+        assert_eq!(
+            get_painted_input_line("\x1b[42m+x\x1b[0m", "+x", "+ x"),
+            "\x1b[42m+ x\x1b[0m"
+        );
+        assert_eq!(get_painted_input_line(" x", " x", "  x"), "  x");
+        // Lines of a normal diff
+        assert_eq!(
+            get_painted_input_line("\x1b[41m-é\x1b[0m", "-é", "< é"),
+            "\x1b[41m< é\x1b[0m"
+        );
+        assert_eq!(get_painted_input_line("+", "+", ">"), ">");
+        assert_eq!(
+            get_painted_input_line("\\ No newline", "\\ No newline", "\\ No newline"),
+            "\\ No newline"
+        );
+    }
+
+    #[test]
+    fn test_removed_line_starting_with_dashes_is_not_file_metadata() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(
+            DIFF_UNIFIED_REMOVED_LINE_STARTING_WITH_DASHES,
+            &options,
+        ))
+        .to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert_eq!(lines[1], "comparing: a/query.sql ⟶   b/query.sql");
+        assert!(lines.contains(&" -- a comment"));
+        assert!(lines.contains(&"comparing: b/other.sql ⟶   b/other.sql"));
+        assert!(!output.contains("hunk is incomplete"));
+    }
+
+    #[test]
+    fn test_incomplete_hunk_is_flagged() {
+        let mut options = get_command_line_options();
+        let output =
+            strip_ansi_codes(&run_delta(GIT_DIFF_WITH_INCOMPLETE_HUNK, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        let i = lines
+            .iter()
+            .position(|line| *line == " select 20;")
+            .unwrap();
+        assert_eq!(lines[i + 1], "(hunk is incomplete: 2 more lines expected)");
+        assert!(lines[i + 2..].contains(&"b.sql (index 9c2c5a4..6d0ab5e)"));
+        // Under --color-only, no line is added.
+        options.color_only = true;
+        let output =
+            strip_ansi_codes(&run_delta(GIT_DIFF_WITH_INCOMPLETE_HUNK, &options)).to_string();
+        assert_eq!(output, GIT_DIFF_WITH_INCOMPLETE_HUNK.to_string() + "\n");
+    }
+
+    #[test]
+    fn test_count_hunk_line() {
+        let mut remaining = vec![2, 1];
+        assert!(count_hunk_line("-a", 1, &mut remaining));
+        assert_eq!(remaining, vec![1, 1]);
+        assert!(count_hunk_line(
+            "\\ No newline at end of file",
+            1,
+            &mut remaining
+        ));
+        assert!(!count_hunk_line("+b", 1, &mut [1, 0]));
+        assert!(!count_hunk_line("diff --git a/b b/b", 1, &mut remaining));
+        assert!(count_hunk_line("", 1, &mut remaining));
+        assert_eq!(remaining, vec![0, 0]);
+        // Combined diff
+        let mut remaining = vec![1, 1, 1];
+        assert!(count_hunk_line(" -a", 2, &mut remaining));
+        assert_eq!(remaining, vec![1, 0, 1]);
+        assert!(count_hunk_line("++b", 2, &mut remaining));
+        assert_eq!(remaining, vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_svn_diff() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(SVN_DIFF, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert_eq!(lines[1], "src/main.c");
+        assert!(!output.contains("Index: "));
+        assert!(!output.contains("(revision 1234)"));
+        assert!(lines.contains(&"added: src/new.py"));
+        assert!(lines.contains(&" x = 1"));
+        // Property changes are written as they are.
+        assert!(output.contains("\nProperty changes on: src/new.py\n"));
+        assert!(output.contains("\n## -0,0 +1 ##\n+Id\n"));
+    }
+
+    #[test]
+    fn test_hg_diff() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(HG_DIFF, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert_eq!(lines[1], "src/main.c");
+        assert!(!output.contains("diff -r "));
+        assert!(lines.contains(&"added: src/new.py"));
+        assert!(lines.contains(&" x = 1"));
+    }
+
+    #[test]
+    fn test_git_format_patch() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        let output = strip_ansi_codes(&run_delta(GIT_FORMAT_PATCH, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+
+        // Each patch has a commit-style header showing its position in the series and subject.
+        assert!(lines[1].starts_with("PATCH 1/2: Return an error code "));
+        assert!(lines.contains(&"From: Someone <someone@example.com>"));
+        assert!(!output.contains("Subject:"));
+        assert!(!output.contains("From 94907c0f1"));
+        assert!(output
+            .contains("PATCH 2/2: Add a script that is long enough for its subject to be folded "));
+        // The diffstat is kept, and the files and hunks are recognized.
+        assert!(lines.contains(&" src/main.c | 2 +-"));
+        assert!(lines.contains(&"src/main.c (index 8a5b3c1..1d2e4f5)"));
+        assert!(lines.contains(&"added: src/new.py (index 7c8d9e0)"));
+        assert!(!output.contains("diff --git"));
+        // The signature is not taken for a removed line.
+        assert_eq!(lines.iter().filter(|line| **line == "-- ").count(), 2);
+    }
+
+    #[test]
+    fn test_git_range_diff() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        options.keep_plus_minus_markers = true;
+        let output = run_delta(GIT_RANGE_DIFF, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        let lines: Vec<&str> = stripped_output.split('\n').collect();
+
+        // The commit pair lines are shown as headers.
+        assert!(lines[1].starts_with("1: cb3fb2d ! 1: 49cf354 Change x "));
+        assert!(stripped_output.contains("3: 67a3b12 < - Add f "));
+        assert!(stripped_output.contains("- > 3: f2c1741 Add d "));
+        assert!(!stripped_output.contains("-:  -------"));
+        // The lines of the diff of diffs keep their indentation and both markers.
+        assert!(lines.contains(&"     -    let x = 1;"));
+        assert!(lines.contains(&"    -+    let x = 2;"));
+        assert!(lines.contains(&"    ++    let x = 3;"));
+        assert!(lines.contains(&"    -Author: a <a@b>"));
+        // The outer markers determine the background of the lines, and the edits between the
+        // patches are emphasized.
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let line = output.lines().find(|line| line.contains("2\x1b")).unwrap();
+        let background = |modifier: StyleModifier| {
+            ansi_term::Style::new()
+                .on(to_ansi_color(
+                    modifier.background.unwrap(),
+                    config.true_color,
+                ))
+                .prefix()
+                .to_string()
+                .replace("\x1b[", "")
+                .replace("m", "")
+        };
+        assert!(line.contains(&background(config.minus_style_modifier)));
+        assert!(line.contains(&background(config.minus_emph_style_modifier)));
+    }
+
+    #[test]
+    fn test_git_diff_color_moved() {
+        let options = get_command_line_options();
+        let output = run_delta(GIT_DIFF_COLOR_MOVED, &options);
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let background = |modifier: StyleModifier| {
+            ansi_term::Style::new()
+                .on(to_ansi_color(
+                    modifier.background.unwrap(),
+                    config.true_color,
+                ))
+                .prefix()
+                .to_string()
+                .replace("\x1b[", "")
+                .replace("m", "")
+        };
+        let line = |text: &str| {
+            output
+                .lines()
+                .find(|line| strip_ansi_codes(line) == text)
+                .unwrap()
+        };
+        // Moved lines are painted in the moved styles, with no edits emphasized between them.
+        for text in &[" fn b() {", "     x();"] {
+            let lines: Vec<&str> = output
+                .lines()
+                .filter(|line| strip_ansi_codes(line) == *text)
+                .collect();
+            assert_eq!(lines.len(), 2);
+            assert!(lines[0].contains(&background(config.plus_moved_style_modifier)));
+            assert!(lines[1].contains(&background(config.minus_moved_style_modifier)));
+            for line in lines {
+                assert!(!line.contains(&background(config.minus_emph_style_modifier)));
+                assert!(!line.contains(&background(config.plus_emph_style_modifier)));
+            }
+        }
+        // Other removed and added lines keep their usual styles.
+        assert!(line("     one();").contains(&background(config.minus_style_modifier)));
+        assert!(line("     uno();").contains(&background(config.plus_style_modifier)));
+        assert!(!line("     uno();").contains(&background(config.plus_moved_style_modifier)));
+    }
+
+    #[test]
+    fn test_detect_moved() {
+        let mut options = get_command_line_options();
+        options.detect_moved = true;
+        let output = run_delta(GIT_DIFF_MOVED_BETWEEN_FILES, &options);
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let background = |modifier: StyleModifier| {
+            ansi_term::Style::new()
+                .on(to_ansi_color(
+                    modifier.background.unwrap(),
+                    config.true_color,
+                ))
+                .prefix()
+                .to_string()
+                .replace("\x1b[", "")
+                .replace("m", "")
+        };
+        let line = |text: &str| {
+            output
+                .lines()
+                .find(|line| strip_ansi_codes(line) == text)
+                .unwrap()
+        };
+        // The function moved to another file is painted as moved code, and the edit made while
+        // moving it is emphasized.
+        let minus_moved = background(config.minus_moved_style_modifier);
+        let plus_moved = background(config.plus_moved_style_modifier);
+        assert!(line(" fn moved_function(x: usize) -> usize {").contains(&minus_moved));
+        assert!(line("     fn moved_function(x: usize) -> usize {").contains(&plus_moved));
+        assert!(line("         report_value(scaled);").contains(&plus_moved));
+        let minus_edited_line = line("     let total = compute_total(x, 2);");
+        assert!(minus_edited_line.contains(&minus_moved));
+        assert!(minus_edited_line.contains(&background(config.minus_emph_style_modifier)));
+        let plus_edited_line = line("         let total = compute_total(x, 3);");
+        assert!(plus_edited_line.contains(&plus_moved));
+        assert!(plus_edited_line.contains(&background(config.plus_emph_style_modifier)));
+        // Lines edited in place are painted as usual.
+        assert!(line("     two();").contains(&background(config.minus_style_modifier)));
+        assert!(line("     three();").contains(&background(config.plus_style_modifier)));
+
+        // Without --detect-moved, the moved lines are ordinary removed and added lines.
+        options.detect_moved = false;
+        let output = run_delta(GIT_DIFF_MOVED_BETWEEN_FILES, &options);
+        assert!(!output.contains(&minus_moved));
+        assert!(!output.contains(&plus_moved));
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let mut options = get_command_line_options();
+        options.side_by_side = true;
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let left_panel_width =
+            side_by_side::get_panel_width(side_by_side::PanelSide::Left, &config);
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+
+        // Homologous lines are on the same row.
+        let row = lines
+            .iter()
+            .find(|line| line.contains("Hello rust\""))
+            .unwrap();
+        assert!(row.starts_with(" println!(\"Hello rust\");"));
+        assert_eq!(&row[left_panel_width..], " println!(\"Hello ruster\");");
+        // Unchanged lines are in both columns.
+        let row = lines
+            .iter()
+            .find(|line| line.contains("Unchanged"))
+            .unwrap();
+        assert_eq!(
+            *row,
+            format!(
+                " {:width$} Unchanged",
+                "Unchanged",
+                width = left_panel_width - 1
+            )
+        );
+        // Unpaired lines are opposite an empty cell.
+        let row = lines
+            .iter()
+            .find(|line| line.contains("Change one"))
+            .unwrap();
+        assert_eq!(
+            *row,
+            format!(" {:width$}", "Change one", width = left_panel_width - 1)
+        );
+        let row = lines
+            .iter()
+            .find(|line| line.contains("Change two"))
+            .unwrap();
+        assert_eq!(*row, format!("{} Change two", " ".repeat(left_panel_width)));
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let mut options = get_command_line_options();
+        options.line_numbers = true;
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        let find_line = |text: &str| *lines.iter().find(|line| line.contains(text)).unwrap();
+
+        assert_eq!(
+            find_line("Hello world"),
+            "   5⋮   5│ println!(\"Hello world\");"
+        );
+        assert_eq!(
+            find_line("Hello rust\""),
+            "   6⋮    │ println!(\"Hello rust\");"
+        );
+        assert_eq!(
+            find_line("Hello ruster"),
+            "    ⋮   6│ println!(\"Hello ruster\");"
+        );
+        assert_eq!(find_line("Some more"), "  43⋮  43│ // Some more changes");
+        assert_eq!(find_line("Change one"), "  44⋮    │ Change one");
+        assert_eq!(find_line("Change two"), "    ⋮  45│ Change two");
+        assert_eq!(find_line("Change three"), "  47⋮    │ Change three");
+        assert_eq!(find_line("Change four"), "    ⋮  47│ Change four");
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.contains("Unchanged"))
+                .collect::<Vec<_>>(),
+            vec![
+                &"  45⋮  44│ Unchanged",
+                &"  46⋮  46│ Unchanged",
+                &"  48⋮  48│ Unchanged"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_numbers_of_more_than_four_digits() {
+        let mut options = get_command_line_options();
+        options.line_numbers = true;
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_FIVE_DIGIT_LINE_NUMBERS, &options))
+            .to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        let find_line = |text: &str| *lines.iter().find(|line| line.ends_with(text)).unwrap();
+
+        assert_eq!(find_line(" a"), " 9998⋮ 9998│ a");
+        assert_eq!(find_line("1;"), " 9999⋮     │ let b = 1;");
+        assert_eq!(find_line("2;"), "     ⋮ 9999│ let b = 2;");
+        assert_eq!(find_line(" d"), "10000⋮10000│ d");
+
+        options.side_by_side = true;
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_FIVE_DIGIT_LINE_NUMBERS, &options))
+            .to_string();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let left_panel_width =
+            side_by_side::get_panel_width(side_by_side::PanelSide::Left, &config);
+        let row = output
+            .split('\n')
+            .find(|line| line.starts_with(" 9999│ let b = 1;"))
+            .unwrap();
+        assert_eq!(
+            row.chars().skip(left_panel_width).collect::<String>(),
+            " 9999│ let b = 2;"
+        );
+    }
+
+    #[test]
+    fn test_side_by_side_line_numbers() {
+        let mut options = get_command_line_options();
+        options.side_by_side = true;
+        options.line_numbers = true;
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let left_panel_width =
+            side_by_side::get_panel_width(side_by_side::PanelSide::Left, &config);
+        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options)).to_string();
+        let row = output
+            .split('\n')
+            .find(|line| line.contains("Hello rust\""))
+            .unwrap();
+        assert!(row.starts_with("   6│ println!(\"Hello rust\");"));
+        assert_eq!(
+            row.chars().skip(left_panel_width).collect::<String>(),
+            "   6│ println!(\"Hello ruster\");"
+        );
+    }
+
+    #[test]
+    fn test_render_parsed_diff() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        for input in &[
+            DIFF_IN_DIFF,
+            TRIPLE_DASH_AT_BEGINNING_OF_LINE_IN_CODE,
+            ADDED_FILE_INPUT,
+            GIT_LOG_WITH_COMMIT_METADATA,
+            COMBINED_DIFF,
+        ] {
+            let diff = model::parse(input.split('\n'));
+            let mut writer: Vec<u8> = Vec::new();
+            render(&diff, &config, &assets, &mut writer).unwrap();
+            assert_eq!(
+                String::from_utf8(writer).unwrap(),
+                run_delta(input, &options)
+            );
+        }
+        // The diff in the removed lines is not parsed as a file diff.
+        let diff = model::parse(DIFF_IN_DIFF.split('\n'));
+        assert_eq!(diff.commits[0].files[0].hunks[0].lines.len(), 22);
+        assert_eq!(diff.commits[0].files[1].plus_file, "README.md");
+    }
+
+    #[test]
+    fn test_commit_header_with_parsed_metadata() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        options.commit_hash_length = Some(7);
+        options.relative_dates = true;
+        let output = run_delta(GIT_LOG_WITH_COMMIT_METADATA, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        let lines: Vec<&str> = stripped_output.split('\n').collect();
+        assert!(lines.contains(&"commit 5d4e3f2 (HEAD -> master, tag: v0.2, origin/master) ┃"));
+        assert!(lines.contains(&"Merge: 1281650 d481eaa"));
+        assert!(lines.contains(&"Author: Dan Davison <dandavison7@gmail.com>"));
+        // The date is shown relative to the current time.
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("Date:   ") && line.ends_with(" years ago")));
+        assert!(lines.contains(&"gpg: Good signature from \"Dan Davison <dandavison7@gmail.com>\""));
+        // The refs are painted with git's default colors for their kinds.
+        assert!(output.contains(&ansi_term::Colour::Cyan.bold().paint("HEAD").to_string()));
+        assert!(output.contains(&ansi_term::Colour::Green.bold().paint("master").to_string()));
+        assert!(output.contains(
+            &ansi_term::Colour::Yellow
+                .bold()
+                .paint("tag: v0.2")
+                .to_string()
+        ));
+        assert!(output.contains(
+            &ansi_term::Colour::Red
+                .bold()
+                .paint("origin/master")
+                .to_string()
+        ));
+        // The commit message is left as it is.
+        assert!(lines.contains(&"    Merge branch 'feature'"));
+    }
+
+    #[test]
+    fn test_commit_message_styling() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        let output = run_delta(GIT_LOG_WITH_COMMIT_MESSAGE, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        // The message is written with its indentation and text unchanged.
+        for line in GIT_LOG_WITH_COMMIT_MESSAGE.lines().skip(4).take(8) {
+            assert!(stripped_output
+                .split('\n')
+                .any(|output_line| output_line == line));
+        }
+        let bold = ansi_term::Style::new().bold();
+        let reference = ansi_term::Style::new()
+            .fg(ansi_term::Colour::Blue)
+            .underline();
+        assert!(output.contains(&bold.paint("Fix the parser for quoted paths").to_string()));
+        assert!(output.contains(&format!(
+            "{}{}",
+            ansi_term::Colour::Cyan.bold().paint("Signed-off-by:"),
+            ansi_term::Style::new().paint(" Dan Davison <dandavison7@gmail.com>")
+        )));
+        assert!(output.contains(&reference.paint("#128").to_string()));
+        assert!(output.contains(&reference.paint("DELTA-12").to_string()));
+        assert!(output.contains(&ansi_term::Colour::Cyan.bold().paint("Fixes:").to_string()));
+    }
+
+    #[test]
+    fn test_commit_message_markdown() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        let output = run_delta(GIT_LOG_WITH_COMMIT_MESSAGE, &options);
+        let line = "    Use `unquote_path` for the paths of **all** file metadata lines.";
+        assert!(output.split('\n').any(|output_line| output_line == line));
+        options.commit_message_markdown = true;
+        let output = run_delta(GIT_LOG_WITH_COMMIT_MESSAGE, &options);
+        let output_line = output
+            .split('\n')
+            .find(|output_line| strip_ansi_codes(output_line) == line)
+            .unwrap();
+        assert_ne!(output_line, line);
+    }
+
+    #[test]
+    fn test_git_log_graph() {
+        let mut options = get_command_line_options();
+        options.commit_style = Some("box".to_string());
+        let output = strip_ansi_codes(&run_delta(GIT_LOG_GRAPH, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+
+        // The commit line is boxed, after the graph prefix. The commit's column is marked once.
+        assert!(lines[0].starts_with("* ━"));
+        assert!(lines[1].starts_with("| commit 94907c0f1"));
+        assert!(lines[2].starts_with("| ━"));
+        // The file and hunk sections are recognized.
+        assert!(lines.contains(&"| src/align.rs (index 8e37a9e..6ce4863)"));
+        assert!(!output.contains("diff --git"));
+        assert!(!output.contains("@@"));
+        // Hunk lines are painted without their markers.
+        assert!(lines.contains(&"|  use std::collections::VecDeque;"));
+        assert!(lines.contains(&"|  use std::collections::HashMap;"));
+        // Lines between commits keep their graph.
+        assert!(lines.contains(&"|/  "));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("| commit 6b2aeb0b8")));
+    }
+
+    #[test]
+    fn test_hunk_line_like_graph_commit_line() {
+        let options = get_command_line_options();
+        let output = run_delta(GIT_LOG_WITH_COMMIT_LINE_IN_HUNK, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        let lines: Vec<&str> = stripped_output.split('\n').collect();
+        // The context line of a C comment is not taken for a graph commit line.
+        assert!(lines.contains(&"  * commit 1a2b3c4d introduced this"));
+        assert!(!stripped_output.contains("hunk is incomplete"));
+        assert!(lines.contains(&" int x = 1;"));
+        assert!(lines.contains(&" int x = 2;"));
+        assert!(!output.contains("-int x"));
+        assert!(!output.contains("+int x"));
+    }
+
+    #[test]
+    fn test_git_log_graph_is_passed_on_unchanged_under_color_only() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        let output = run_delta(GIT_LOG_GRAPH, &options);
+        assert_eq!(
+            strip_ansi_codes(&output).to_string(),
+            GIT_LOG_GRAPH.to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_git_blame() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(GIT_BLAME, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // The commit metadata is shown on the first line of each group only.
+        assert_eq!(
+            lines[0],
+            "^be6dffc A                2019-11-20│    1│ use std::io;"
+        );
+        assert_eq!(lines[1], "                                    │    2│");
+        assert_eq!(
+            lines[2],
+            "6f8166e3 Dan Davison      2019-11-21│    3│ fn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_git_blame_is_passed_on_unchanged_under_color_only() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        let output = run_delta(GIT_BLAME, &options);
+        assert_eq!(
+            strip_ansi_codes(&output).to_string(),
+            GIT_BLAME.to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn test_git_grep() {
         let options = get_command_line_options();
-        let output = strip_ansi_codes(&run_delta(DIFF_UNIFIED_TWO_FILES, &options)).to_string();
-        let mut lines = output.split('\n');
+        let output = run_delta(GIT_GREP, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        let lines: Vec<&str> = stripped_output.split('\n').collect();
+        // Each file has a header.
+        assert_eq!(lines[1], "src/delta.rs");
+        assert_eq!(lines[3], "  61│ pub fn delta<I>(");
+        assert_eq!(lines[4], "  62│     lines: I,");
+        assert_eq!(lines[5], "--");
+        assert_eq!(lines[7], "src/paint.rs");
+        assert_eq!(lines[9], "  42│ impl<'a> Painter<'a> {");
+        // The match, which is colored in the input, is emphasized.
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let emph_background = paint::to_ansi_style(
+            config.no_style.apply(config.plus_emph_style_modifier),
+            config.true_color,
+        )
+        .prefix()
+        .to_string();
+        let emph_background = emph_background.trim_end_matches('m');
+        assert_eq!(output.matches(emph_background).count(), 1);
+        let emphasized = &output[output.find(emph_background).unwrap()..];
+        assert!(emphasized[emphasized.find('m').unwrap() + 1..].starts_with("fn"));
+    }
 
-        // Header
+    #[test]
+    fn test_git_grep_starting_with_context_lines() {
+        let options = get_command_line_options();
+        let input = "src/delta.rs-60-\nsrc/delta.rs:61:pub fn delta<I>(";
+        let output = strip_ansi_codes(&run_delta(input, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert_eq!(lines[1], "src/delta.rs");
+        assert_eq!(lines[3], "  60│ ");
+        assert_eq!(lines[4], "  61│ pub fn delta<I>(");
+    }
+
+    #[test]
+    fn test_first_line_like_grep_context_line_is_not_grep_output() {
+        let options = get_command_line_options();
+        let input = format!("2020-01-01 10:00:00 Build\n{}", DIFF_UNIFIED_TWO_FILES);
+        let output = strip_ansi_codes(&run_delta(&input, &options)).to_string();
+        let mut lines = output.split('\n');
+        assert_eq!(lines.next().unwrap(), "2020-01-01 10:00:00 Build");
         assert_eq!(lines.nth(1).unwrap(), "comparing: one.rs ⟶   src/two.rs");
-        // Line
-        assert_eq!(lines.nth(2).unwrap(), "5");
-        // Change
-        assert_eq!(lines.nth(2).unwrap(), " println!(\"Hello ruster\");");
-        // Next chunk
-        assert_eq!(lines.nth(2).unwrap(), "43");
-        // Unchanged in second chunk
-        assert_eq!(lines.nth(2).unwrap(), " Unchanged");
+    }
+
+    #[test]
+    fn test_git_grep_is_passed_on_unchanged_under_color_only() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        let output = run_delta(GIT_GREP, &options);
+        assert_eq!(output, GIT_GREP.to_owned() + "\n");
     }
 
     #[test]
@@ -746,11 +2512,406 @@ mod tests {
     fn test_diff_with_merge_conflict_is_not_truncated() {
         let options = get_command_line_options();
         let output = run_delta(DIFF_WITH_MERGE_CONFLICT, &options);
-        // TODO: The + in the first column is being removed.
-        assert!(strip_ansi_codes(&output).contains("+>>>>>>> Stashed changes"));
+        assert!(strip_ansi_codes(&output).contains("\n  >>>>>>> Stashed changes\n"));
         assert_eq!(output.split('\n').count(), 47);
     }
 
+    #[test]
+    fn test_diff_with_merge_conflict_keeps_marker_columns() {
+        let mut options = get_command_line_options();
+        options.keep_plus_minus_markers = true;
+        let output = strip_ansi_codes(&run_delta(DIFF_WITH_MERGE_CONFLICT, &options)).to_string();
+        assert!(output.contains("\n++>>>>>>> Stashed changes\n"));
+        assert!(output.contains("\n +test: unit-test end-to-end-test\n"));
+        assert!(output.contains("\n+ test:\n"));
+        assert!(output.contains("\n  lint:\n"));
+    }
+
+    #[test]
+    fn test_merge_conflict_sections_are_painted_differently() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let output = run_delta(DIFF_WITH_MERGE_CONFLICT_MARKERS, &options);
+        let line_containing = |text: &str| {
+            output
+                .split('\n')
+                .find(|line| strip_ansi_codes(line).contains(text))
+                .unwrap()
+        };
+        let background = |style_modifier: StyleModifier| {
+            ansi_term::Style::new()
+                .on(to_ansi_color(
+                    style_modifier.background.unwrap(),
+                    config.true_color,
+                ))
+                .prefix()
+                .to_string()
+                .replace("\x1b[", "")
+                .replace("m", "")
+        };
+        let (minus, minus_emph, plus, plus_emph) = (
+            background(config.minus_style_modifier),
+            background(config.minus_emph_style_modifier),
+            background(config.plus_style_modifier),
+            background(config.plus_emph_style_modifier),
+        );
+
+        // "Ours" is painted as removed, and "theirs" as added, with the edits between them
+        // emphasized. The base has no background.
+        assert!(line_containing("y = 1").contains(&minus));
+        assert!(line_containing("y = 1").contains(&minus_emph));
+        assert!(line_containing("y = 2").contains(&plus));
+        assert!(line_containing("y = 2").contains(&plus_emph));
+        for line in &[line_containing("y = 0"), line_containing("<<<<<<< HEAD")] {
+            assert!(![&minus, &plus].iter().any(|color| line.contains(*color)));
+        }
+        assert!(line_containing("z = 3").contains(&plus));
+    }
+
+    fn get_plus_background(config: &Config) -> String {
+        ansi_term::Style::new()
+            .on(to_ansi_color(
+                config.plus_style_modifier.background.unwrap(),
+                config.true_color,
+            ))
+            .prefix()
+            .to_string()
+    }
+
+    #[test]
+    fn test_merge_conflict_region_continues_after_unchanged_line() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let plus = get_plus_background(&config);
+        let output = run_delta(DIFF_WITH_MERGE_CONFLICT_AROUND_UNCHANGED_LINE, &options);
+        let line_containing = |text: &str| {
+            output
+                .split('\n')
+                .find(|line| strip_ansi_codes(line).contains(text))
+                .unwrap()
+        };
+        // The marker lines following the unchanged line are still read as marker lines.
+        assert!(!line_containing("=======").contains(&plus));
+        assert!(!line_containing(">>>>>>> branch").contains(&plus));
+        assert!(line_containing("y = 2").contains(&plus));
+    }
+
+    #[test]
+    fn test_merge_conflict_region_too_large_to_buffer() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let plus = get_plus_background(&config);
+        let n_lines = config.max_buffered_lines + 8;
+        let mut input = format!(
+            "--- a/a.py\n+++ b/a.py\n@@ -1 +1,{} @@\n x = 0\n+<<<<<<< HEAD\n",
+            n_lines + 5
+        );
+        for i in 0..n_lines {
+            input.push_str(&format!("+y = {}\n", i));
+        }
+        input.push_str("+=======\n+y = 2\n+>>>>>>> branch\n");
+        let output = run_delta(&input, &options);
+        let stripped_output = strip_ansi_codes(&output).to_string();
+        for i in 0..n_lines {
+            assert!(stripped_output.contains(&format!("\n y = {}\n", i)));
+        }
+        // The region is painted as added lines.
+        let end_marker_line = output
+            .split('\n')
+            .find(|line| strip_ansi_codes(line).contains(">>>>>>> branch"))
+            .unwrap();
+        assert!(end_marker_line.contains(&plus));
+    }
+
+    #[test]
+    fn test_merge_conflict_side_by_side() {
+        let mut options = get_command_line_options();
+        options.side_by_side = true;
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let left_panel_width =
+            side_by_side::get_panel_width(side_by_side::PanelSide::Left, &config);
+        let output =
+            strip_ansi_codes(&run_delta(DIFF_WITH_MERGE_CONFLICT_MARKERS, &options)).to_string();
+        // The lines of the region are in the right column, opposite empty cells.
+        for text in &["<<<<<<< HEAD", "y = 1", "y = 0", "y = 2", ">>>>>>> branch"] {
+            let row = output.split('\n').find(|line| line.contains(text)).unwrap();
+            assert_eq!(row[..left_panel_width].trim(), "");
+            assert_eq!(row[left_panel_width..].trim_end(), format!(" {}", text));
+        }
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let background = |style_modifier: StyleModifier| {
+            ansi_term::Style::new()
+                .on(to_ansi_color(
+                    style_modifier.background.unwrap(),
+                    config.true_color,
+                ))
+                .prefix()
+                .to_string()
+                .replace("\x1b[", "")
+                .replace("m", "")
+        };
+        for input in &[GIT_DIFF_WORD_DIFF, GIT_DIFF_COLOR_WORDS] {
+            let output = run_delta(input, &options);
+            let lines: Vec<&str> = output.split('\n').collect();
+            let stripped_output = strip_ansi_codes(&output).to_string();
+            let stripped_lines: Vec<&str> = stripped_output.split('\n').collect();
+            // The markers are removed, and the lines have the usual space in place of a marker.
+            assert_eq!(
+                &stripped_lines[5..10],
+                [
+                    " fn main() {",
+                    "     let xy = 1;2;",
+                    "     println!(\"{}\", x);y);",
+                    "     // new line",
+                    " }"
+                ]
+            );
+            // Removed and added words are emphasized.
+            assert!(lines[6].contains(&background(config.minus_emph_style_modifier)));
+            assert!(lines[6].contains(&background(config.plus_emph_style_modifier)));
+            // A line holding only added words is painted as an added line.
+            assert!(lines[8].contains(&background(config.plus_style_modifier)));
+            assert!(!lines[8].contains(&background(config.plus_emph_style_modifier)));
+        }
+    }
+
+    #[test]
+    fn test_word_diff_hunk_starting_with_indented_lines() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(
+            GIT_DIFF_WORD_DIFF_WITH_INDENTED_CONTEXT,
+            &options,
+        ))
+        .to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // The unchanged lines preceding the first changed line of the hunk keep their indentation.
+        assert_eq!(
+            &lines[7..11],
+            [
+                "     let a = 1;",
+                "     let b = 2;",
+                "     let xxy = 3;",
+                " }"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_line_like_word_diff_line() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(
+            GIT_DIFF_WITH_WORD_DIFF_MARKERS_IN_CODE,
+            &options,
+        ))
+        .to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // The hunk is an ordinary unified diff hunk: the markers are part of the code.
+        assert_eq!(
+            &lines[5..8],
+            [" let y = x[-1-];", " let a = 1;", " let a = 2;"]
+        );
+    }
+
+    #[test]
+    fn test_combined_diff_file_header() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(COMBINED_DIFF, &options)).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        // A stand-alone combined diff is recognized as git output: its paths have no prefixes.
+        assert!(lines.contains(&"fruit.rs"));
+        assert!(!output.contains("comparing:"));
+    }
+
+    #[test]
+    fn test_combined_diff_with_multibyte_character_in_marker_column() {
+        let options = get_command_line_options();
+        let output = strip_ansi_codes(&run_delta(
+            COMBINED_DIFF_WITH_MULTIBYTE_CHARACTER_IN_MARKER_COLUMN,
+            &options,
+        ))
+        .to_string();
+        assert!(output.contains('é'));
+    }
+
+    #[test]
+    fn test_combined_diff_edits_are_inferred_against_each_parent() {
+        let options = get_command_line_options();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let output = run_delta(COMBINED_DIFF, &options);
+        let line_containing = |text: &str| {
+            output
+                .split('\n')
+                .find(|line| strip_ansi_codes(line).contains(text))
+        };
+        let minus_emph_background = ansi_term::Style::new()
+            .on(to_ansi_color(
+                config.minus_emph_style_modifier.background.unwrap(),
+                config.true_color,
+            ))
+            .prefix()
+            .to_string()
+            .replace("\x1b[", "")
+            .replace("m", "");
+
+        // The added line differs from the second parent, and so is paired with the line removed
+        // from the second parent, not with the line removed from the first parent.
+        assert!(!line_containing("banana = 1")
+            .unwrap()
+            .contains(&minus_emph_background));
+        assert!(line_containing("banana = 2")
+            .unwrap()
+            .contains(&minus_emph_background));
+    }
+
+    #[test]
+    fn test_combined_diff_line_numbers() {
+        let mut options = get_command_line_options();
+        options.line_numbers = true;
+        let output = strip_ansi_codes(&run_delta(COMBINED_DIFF_WITH_ALTERNATING_LINES, &options))
+            .to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        let find_line = |text: &str| *lines.iter().find(|line| line.ends_with(text)).unwrap();
+        // The old line numbers are those of the first parent, which holds the removed lines and
+        // the lines added to the second parent only.
+        assert_eq!(find_line("x"), "   1⋮   1│  x");
+        assert_eq!(find_line("a"), "   2⋮    │  a");
+        assert_eq!(find_line("b"), "   3⋮   2│  b");
+        assert_eq!(find_line("c"), "    ⋮    │  c");
+        assert_eq!(find_line("d"), "    ⋮   3│  d");
+        assert_eq!(find_line("e"), "   4⋮    │  e");
+        assert_eq!(find_line("y"), "   5⋮   4│  y");
+    }
+
+    #[test]
+    fn test_color_only_output_matches_input_line_for_line() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        // Options that alter the layout are ignored under --color-only.
+        options.side_by_side = true;
+        options.line_numbers = true;
+        options.detect_moved = true;
+        for input in &[
+            DIFF_IN_DIFF,
+            ADDED_FILE_INPUT,
+            ADDED_EMPTY_FILE,
+            ADDED_FILES_DIRECTORY_PATH_CONTAINING_SPACE,
+            RENAMED_FILE_INPUT,
+            DIFF_UNIFIED_TWO_FILES,
+            DIFF_UNIFIED_TWO_DIRECTORIES,
+            DIFF_CONTEXT_TWO_FILES,
+            DIFF_NORMAL_TWO_DIRECTORIES,
+            NOT_A_DIFF_OUTPUT,
+            SUBMODULE_CONTAINS_UNTRACKED_CONTENT_INPUT,
+            TRIPLE_DASH_AT_BEGINNING_OF_LINE_IN_CODE,
+            BINARY_FILES_DIFFER,
+            GIT_GREP,
+            GIT_BLAME,
+            GIT_LOG_GRAPH,
+            COMBINED_DIFF,
+            DIFF_WITH_MERGE_CONFLICT,
+            DIFF_WITH_MERGE_CONFLICT_MARKERS,
+            GIT_DIFF_WORD_DIFF,
+            GIT_DIFF_COLOR_WORDS,
+            DIFF_UNIFIED_REMOVED_LINE_STARTING_WITH_DASHES,
+            GIT_DIFF_WITH_INCOMPLETE_HUNK,
+            SVN_DIFF,
+            HG_DIFF,
+            GIT_FORMAT_PATCH,
+            GIT_RANGE_DIFF,
+            GIT_DIFF_COLOR_MOVED,
+            GIT_DIFF_MOVED_BETWEEN_FILES,
+            GIT_DIFF_WITH_EXTENDED_HEADERS,
+            GIT_DIFF_WITH_QUOTED_PATHS,
+            GIT_DIFF_WITH_UNUSUAL_PREFIXES,
+            GIT_LOG_WITH_COMMIT_METADATA,
+            GIT_LOG_WITH_COMMIT_MESSAGE,
+        ] {
+            let output = run_delta(input, &options);
+            assert_eq!(
+                strip_ansi_codes(&output).to_string(),
+                strip_ansi_codes(input).to_string() + "\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_color_only_preserves_input_bytes() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        // CRLF line endings, and Latin-1 and Shift-JIS text, which are not valid UTF-8.
+        let input: &[u8] = b"\
+diff --git a/a.txt b/a.txt\r
+index 8a5b3c1..1d2e4f5 100644\r
+--- a/a.txt\r
++++ b/a.txt\r
+@@ -1,3 +1,3 @@\r
+ caf\xe9\r
+-\x82\xa0\r
++\x82\xa2\r
+ end
+";
+        let mut output = Vec::new();
+        delta(
+            encoding::read_lines(input, config.encoding),
+            &config,
+            &assets,
+            &mut output,
+        )
+        .unwrap();
+        let ansi_regex = regex::bytes::Regex::new(r"\x1b\[[0-9;]*[mK]").unwrap();
+        assert_eq!(ansi_regex.replace_all(&output, &b""[..]), input);
+    }
+
+    #[test]
+    fn test_legacy_encoding_is_decoded_for_display() {
+        let mut options = get_command_line_options();
+        options.encoding = "latin1".to_string();
+        let assets = HighlightingAssets::new();
+        let config = cli::process_command_line_arguments(&assets, &options);
+        let input: &[u8] = b"\
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-caf\xe9
++caf\xe8
+";
+        let mut output = Vec::new();
+        delta(
+            encoding::read_lines(input, config.encoding),
+            &config,
+            &assets,
+            &mut output,
+        )
+        .unwrap();
+        let output = strip_ansi_codes(std::str::from_utf8(&output).unwrap()).to_string();
+        let lines: Vec<&str> = output.split('\n').collect();
+        assert!(lines.contains(&" café"));
+        assert!(lines.contains(&" cafè"));
+    }
+
+    #[test]
+    fn test_color_only_output_is_syntax_highlighted() {
+        let mut options = get_command_line_options();
+        options.color_only = true;
+        options.theme = Some("GitHub".to_string());
+        let python_output = run_delta(ADDED_FILE_INPUT, &options);
+        let text_output = run_delta(&ADDED_FILE_INPUT.replace("a.py", "a.txt"), &options);
+        assert_ne!(python_output, text_output.replace("a.txt", "a.py"));
+    }
+
     #[test]
     fn test_diff_with_merge_conflict_is_passed_on_unchanged_under_color_only() {
         let mut options = get_command_line_options();
@@ -865,7 +3026,7 @@ Date:   Thu Jul 11 10:41:11 2019 -0400
     .
 
 ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
-added: a.py
+added: a.py (index 8c55b7d)
 ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 ────────────────────────────────────────────────────────────────────────────────
 
@@ -942,6 +3103,316 @@ rename to b.py
  Unchanged
 ";
 
+    const DIFF_UNIFIED_FIVE_DIGIT_LINE_NUMBERS: &str = "\
+--- a.txt
++++ b.txt
+@@ -9998,3 +9998,3 @@
+ a
+-let b = 1;
++let b = 2;
+ d";
+
+    const DIFF_CONTEXT_TWO_FILES: &str = "\
+*** one.rs	2019-11-20 06:16:08.000000000 +0100
+--- src/two.rs	2019-11-18 18:41:16.000000000 +0100
+***************
+*** 5,7 ****
+  println!(\"Hello world\");
+! println!(\"Hello rust\");
+
+--- 5,7 ----
+  println!(\"Hello world\");
+! println!(\"Hello ruster\");
+
+***************
+*** 43,48 ****
+  // Some more changes
+- Change one
+  Unchanged
+  Unchanged
+! Change three
+  Unchanged
+--- 43,48 ----
+  // Some more changes
+  Unchanged
++ Change two
+  Unchanged
+! Change four
+  Unchanged
+";
+
+    const DIFF_NORMAL_TWO_DIRECTORIES: &str = "\
+diff -r a/src/one.rs b/src/one.rs
+6c6
+< println!(\"Hello rust\");
+---
+> println!(\"Hello ruster\");
+44d43
+< Change one
+45a45
+> Change two
+";
+
+    const DIFF_UNIFIED_REMOVED_LINE_STARTING_WITH_DASHES: &str = "\
+--- a/query.sql	2020-05-01 10:00:00.000000000 +0100
++++ b/query.sql	2020-05-01 10:00:00.000000000 +0100
+@@ -1,3 +1,2 @@
+ select 1;
+--- a comment
+ select 2;
+--- b/other.sql	2020-05-01 10:00:00.000000000 +0100
++++ b/other.sql	2020-05-01 10:00:00.000000000 +0100
+@@ -1 +1 @@
+-select 3;
++select 4;
+";
+
+    const GIT_DIFF_WITH_INCOMPLETE_HUNK: &str = "\
+diff --git a/a.sql b/a.sql
+index 7a1a2c0..1b8c2a6 100644
+--- a/a.sql
++++ b/a.sql
+@@ -1,4 +1,4 @@
+ select 1;
+-select 2;
++select 20;
+diff --git a/b.sql b/b.sql
+index 9c2c5a4..6d0ab5e 100644
+--- a/b.sql
++++ b/b.sql
+@@ -1 +1 @@
+-select 3;
++select 30;
+";
+
+    const SVN_DIFF: &str = "\
+Index: src/main.c
+===================================================================
+--- src/main.c	(revision 1234)
++++ src/main.c	(working copy)
+@@ -1,3 +1,3 @@
+ int main() {
+-    return 0;
++    return 1;
+ }
+Index: src/new.py
+===================================================================
+--- src/new.py	(nonexistent)
++++ src/new.py	(working copy)
+@@ -0,0 +1 @@
++x = 1
+Property changes on: src/new.py
+___________________________________________________________________
+Added: svn:keywords
+## -0,0 +1 ##
++Id
+\\ No newline at end of property
+";
+
+    const HG_DIFF: &str = "\
+diff -r 8a5b3c1d2e4f src/main.c
+--- a/src/main.c	Thu Jan 01 00:00:00 1970 +0000
++++ b/src/main.c	Sat Oct 17 10:00:00 2026 +0200
+@@ -1,3 +1,3 @@
+ int main() {
+-    return 0;
++    return 1;
+ }
+diff -r 8a5b3c1d2e4f src/new.py
+--- /dev/null	Thu Jan 01 00:00:00 1970 +0000
++++ b/src/new.py	Sat Oct 17 10:00:00 2026 +0200
+@@ -0,0 +1,1 @@
++x = 1
+";
+
+    const GIT_FORMAT_PATCH: &str = "\
+From 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e Mon Sep 17 00:00:00 2001
+From: Someone <someone@example.com>
+Date: Sat, 17 Oct 2026 10:00:00 +0200
+Subject: [PATCH 1/2] Return an error code
+
+---
+ src/main.c | 2 +-
+ 1 file changed, 1 insertion(+), 1 deletion(-)
+
+diff --git a/src/main.c b/src/main.c
+index 8a5b3c1..1d2e4f5 100644
+--- a/src/main.c
++++ b/src/main.c
+@@ -1,3 +1,3 @@
+ int main() {
+-    return 0;
++    return 1;
+ }
+-- 
+2.39.5
+
+
+From 1d2e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e Mon Sep 17 00:00:00 2001
+From: Someone <someone@example.com>
+Date: Sat, 17 Oct 2026 10:05:00 +0200
+Subject: [PATCH 2/2] Add a script that is long enough for its subject to be
+ folded
+
+---
+ src/new.py | 1 +
+ 1 file changed, 1 insertion(+)
+ create mode 100644 src/new.py
+
+diff --git a/src/new.py b/src/new.py
+new file mode 100644
+index 0000000..7c8d9e0
+--- /dev/null
++++ b/src/new.py
+@@ -0,0 +1 @@
++x = 1
+-- 
+2.39.5
+
+";
+
+    const GIT_RANGE_DIFF: &str = "\
+1:  cb3fb2d ! 1:  49cf354 Change x
+    @@ a.rs
+     @@
+      fn main() {
+     -    let x = 1;
+    -+    let x = 2;
+    ++    let x = 3;
+          println!(\"{}\", x);
+      }
+2:  9dea7eb ! 2:  b3dcc51 Add c
+    @@
+      ## Metadata ##
+    -Author: a <a@b>
+    +Author: B <b@c>
+     
+      ## Commit message ##
+    -    Add c
+    +    Add c, renamed
+     
+      ## c.txt (new) ##
+     @@
+3:  67a3b12 < -:  ------- Add f
+-:  ------- > 3:  f2c1741 Add d
+4:  51ad6b6 = 4:  0ee0391 Add e
+";
+
+    const GIT_DIFF_COLOR_MOVED: &str = "\
+\x1b[1mdiff --git a/m.rs b/m.rs\x1b[m
+\x1b[1mindex 3d31e23..c171e6a 100644\x1b[m
+\x1b[1m--- a/m.rs\x1b[m
+\x1b[1m+++ b/m.rs\x1b[m
+\x1b[36m@@ -1,6 +1,6 @@\x1b[m
+\x1b[1;36m+\x1b[m\x1b[1;36mfn b() {\x1b[m
+\x1b[1;36m+\x1b[m\x1b[1;36m    x();\x1b[m
+\x1b[1;36m+\x1b[m\x1b[1;36m}\x1b[m
+ fn a() {\x1b[m
+\x1b[31m-    one();\x1b[m
+\x1b[32m+\x1b[m\x1b[32m    uno();\x1b[m
+ }\x1b[m
+\x1b[1;35m-fn b() {\x1b[m
+\x1b[1;35m-    x();\x1b[m
+\x1b[1;35m-}\x1b[m
+";
+
+    const GIT_DIFF_MOVED_BETWEEN_FILES: &str = "\
+diff --git a/a.rs b/a.rs
+index 524a2e1..090521c 100644
+--- a/a.rs
++++ b/a.rs
+@@ -2,13 +2,6 @@ fn keep() {
+     one();
+ }
+ 
+-fn moved_function(x: usize) -> usize {
+-    let total = compute_total(x, 2);
+-    let scaled = scale_value(total);
+-    report_value(scaled);
+-    scaled
+-}
+-
+ fn other() {
+-    two();
++    three();
+ }
+diff --git a/b.rs b/b.rs
+index 26c3acf..75e8683 100644
+--- a/b.rs
++++ b/b.rs
+@@ -1,3 +1,10 @@
+ mod m {
+     fn b() {}
++
++    fn moved_function(x: usize) -> usize {
++        let total = compute_total(x, 3);
++        let scaled = scale_value(total);
++        report_value(scaled);
++        scaled
++    }
+ }
+";
+
+    const GIT_DIFF_WITH_EXTENDED_HEADERS: &str = "\
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/src/a.rs b/src/b.rs
+similarity index 92%
+rename from src/a.rs
+rename to src/b.rs
+index 3d31e23..c171e6a 100644
+--- a/src/a.rs
++++ b/src/b.rs
+@@ -1 +1 @@
+-fn a() {}
++fn b() {}
+diff --git a/src/c.rs b/src/d.rs
+similarity index 100%
+copy from src/c.rs
+copy to src/d.rs
+";
+
+    const GIT_DIFF_WITH_QUOTED_PATHS: &str = "\
+diff --git \"a/caf\\303\\251.rs\" \"b/caf\\303\\251.rs\"
+old mode 100644
+new mode 100755
+index ca05282..77c3e26
+--- \"a/caf\\303\\251.rs\"
++++ \"b/caf\\303\\251.rs\"
+@@ -1 +1 @@
+-fn a() {}
++fn b() {}
+diff --git a/my file.txt \"b/my f\\303\\257le.txt\"
+similarity index 100%
+rename from my file.txt
+rename to \"my f\\303\\257le.txt\"
+";
+
+    const GIT_DIFF_WITH_UNUSUAL_PREFIXES: &str = "\
+diff --git i/src/parse.rs w/src/parse.rs
+index 3d31e23..c171e6a 100644
+--- i/src/parse.rs
++++ w/src/parse.rs
+@@ -1 +1 @@
+-fn a() {}
++fn b() {}
+diff --git a/x.rs a/x.rs
+index 8c55b7d..1f1c8a0 100644
+--- a/x.rs
++++ a/x.rs
+@@ -1 +1 @@
+-fn a() {}
++fn b() {}
+diff --git b/my a/file.rs b/my a/file.rs
+index 3d31e23..c171e6a 100644
+--- b/my a/file.rs
++++ b/my a/file.rs
+@@ -1 +1 @@
+-fn a() {}
++fn b() {}
+";
+
     const DIFF_UNIFIED_TWO_DIRECTORIES: &str = "\
 diff -u a/different b/different
 --- a/different	2019-11-20 06:47:56.000000000 +0100
@@ -1103,6 +3574,221 @@ index cba6064..ba1a4de 100644
              .ok()
 ";
 
+    const GIT_GREP: &str = "\
+\x1b[35msrc/delta.rs\x1b[m\x1b[36m:\x1b[m\x1b[32m61\x1b[m\x1b[36m:\x1b[mpub \x1b[1;31mfn\x1b[m delta<I>(
+\x1b[35msrc/delta.rs\x1b[m\x1b[36m-\x1b[m\x1b[32m62\x1b[m\x1b[36m-\x1b[m    lines: I,
+\x1b[36m--\x1b[m
+\x1b[35msrc/paint.rs\x1b[m\x1b[36m:\x1b[m\x1b[32m42\x1b[m\x1b[36m:\x1b[mimpl<'a> Painter<'a> {";
+
+    const GIT_BLAME: &str = "\
+^be6dffc (A           2019-11-20 06:16:08 +0100 1) use std::io;
+^be6dffc (A           2019-11-20 06:16:08 +0100 2) 
+6f8166e3 (Dan Davison 2019-11-21 10:02:44 +0100 3) fn main() {}";
+
+    const GIT_LOG_GRAPH: &str = "\
+* commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+| Author: Dan Davison <dandavison7@gmail.com>
+| Date:   Sat Dec 7 12:33:53 2019 -0500
+|
+|     Change things
+|
+| diff --git a/src/align.rs b/src/align.rs
+| index 8e37a9e..6ce4863 100644
+| --- a/src/align.rs
+| +++ b/src/align.rs
+| @@ -1,3 +1,3 @@
+|  use std::cmp::max;
+| -use std::collections::VecDeque;
+| +use std::collections::HashMap;
+|  const SUBSTITUTION_COST: usize = 1;
+|/  
+* commit 6b2aeb0b8cb9f4cd4e1b9e4a8ed04a6eab36b9f3
+  Author: Dan Davison <dandavison7@gmail.com>
+  Date:   Sat Dec 7 12:30:02 2019 -0500
+
+      Initial commit
+";
+
+    const GIT_LOG_WITH_COMMIT_LINE_IN_HUNK: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 7 12:33:53 2019 -0500
+
+    Change x
+
+diff --git a/x.c b/x.c
+index 8e37a9e..6ce4863 100644
+--- a/x.c
++++ b/x.c
+@@ -1,6 +1,6 @@
+ /*
+  * commit 1a2b3c4d introduced this
+  */
+-int x = 1;
++int x = 2;
+ int y;
+";
+
+    const GIT_LOG_WITH_COMMIT_MESSAGE: &str = "\
+commit 5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7d6e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 7 12:40:00 2019 -0500
+
+    Fix the parser for quoted paths
+    
+    Use `unquote_path` for the paths of **all** file metadata lines.
+    This fixes #128, and DELTA-12 for UTF-8 paths.
+    
+    Fixes: #128
+    Signed-off-by: Dan Davison <dandavison7@gmail.com>
+
+diff --git a/src/parse.rs b/src/parse.rs
+index 8e37a9e..6ce4863 100644
+--- a/src/parse.rs
++++ b/src/parse.rs
+@@ -1 +1 @@
+-use std::path::Path;
++use std::path::{Path, PathBuf};
+";
+
+    const GIT_LOG_WITH_COMMIT_METADATA: &str = "\
+commit 5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7d6e (HEAD -> master, tag: v0.2, origin/master)
+gpg: Signature made Sat Dec  7 12:40:00 2019 EST
+gpg:                using RSA key 0123456789ABCDEF
+gpg: Good signature from \"Dan Davison <dandavison7@gmail.com>\"
+Merge: 1281650 d481eaa
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 7 12:40:00 2019 -0500
+
+    Merge branch 'feature'
+
+diff --git a/src/align.rs b/src/align.rs
+index 8e37a9e..6ce4863 100644
+--- a/src/align.rs
++++ b/src/align.rs
+@@ -1,3 +1,3 @@
+ use std::cmp::max;
+-use std::collections::VecDeque;
++use std::collections::HashMap;
+ const SUBSTITUTION_COST: usize = 1;
+";
+
+    const COMBINED_DIFF: &str = "\
+diff --cc fruit.rs
+index 1111111,2222222..3333333
+--- a/fruit.rs
++++ b/fruit.rs
+@@@ -1,3 -1,2 +1,2 @@@
+- let banana = 1;
+ -let banana = 2;
+ +let banana = 3;
+  let cherry = 4;
+";
+
+    const COMBINED_DIFF_WITH_ALTERNATING_LINES: &str = "\
+diff --cc a.txt
+index 1111111,2222222..3333333
+--- a/a.txt
++++ b/a.txt
+@@@ -1,5 -1,4 +1,4 @@@
+  x
+- a
+ +b
+ -c
+++d
+- e
+  y
+";
+
+    const COMBINED_DIFF_WITH_MULTIBYTE_CHARACTER_IN_MARKER_COLUMN: &str = "\
+diff --cc a.txt
+index 1111111,2222222..3333333
+--- a/a.txt
++++ b/a.txt
+@@@ -1,1 -1,1 +1,2 @@@
+  x
++é
+";
+
+    const GIT_DIFF_WORD_DIFF: &str = "\
+diff --git a/a.rs b/a.rs
+index c226300..1cc2218 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1,4 +1,5 @@
+fn main() {
+    let [-x-]{+y+} = [-1;-]{+2;+}
+    println!(\"{}\", [-x);-]{+y);+}
+{+    // new line+}
+}
+";
+
+    const GIT_DIFF_WORD_DIFF_WITH_INDENTED_CONTEXT: &str = "\
+diff --git a/a.rs b/a.rs
+index c226300..1cc2218 100644
+--- a/a.rs
++++ b/a.rs
+@@ -2,4 +2,4 @@ fn main() {
+    let a = 1;
+    let b = 2;
+    let [-x-]{+xy+} = 3;
+}
+";
+
+    const GIT_DIFF_WITH_WORD_DIFF_MARKERS_IN_CODE: &str = "\
+diff --git a/a.rs b/a.rs
+index c226300..1cc2218 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1,2 +1,2 @@
+ let y = x[-1-];
+-let a = 1;
++let a = 2;
+";
+
+    const GIT_DIFF_COLOR_WORDS: &str = "\
+\x1b[1mdiff --git a/a.rs b/a.rs\x1b[m
+\x1b[1mindex c226300..1cc2218 100644\x1b[m
+\x1b[1m--- a/a.rs\x1b[m
+\x1b[1m+++ b/a.rs\x1b[m
+\x1b[36m@@ -1,4 +1,5 @@\x1b[m
+fn main() {\x1b[m
+    let \x1b[31mx\x1b[m\x1b[32my\x1b[m = \x1b[31m1;\x1b[m\x1b[32m2;\x1b[m
+    println!(\"{}\", \x1b[31mx);\x1b[m\x1b[32my);\x1b[m
+\x1b[32m    // new line\x1b[m
+}\x1b[m
+";
+
+    const DIFF_WITH_MERGE_CONFLICT_MARKERS: &str = "\
+diff --git a/a.py b/a.py
+index 6d31f4b..d4a8c2f 100644
+--- a/a.py
++++ b/a.py
+@@ -1 +1,9 @@
+ x = 0
++<<<<<<< HEAD
++y = 1
++||||||| base
++y = 0
++=======
++y = 2
++>>>>>>> branch
++z = 3";
+
+    const DIFF_WITH_MERGE_CONFLICT_AROUND_UNCHANGED_LINE: &str = "\
+diff --git a/a.py b/a.py
+index 6d31f4b..d4a8c2f 100644
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,7 @@
+ x = 0
++<<<<<<< HEAD
+ y = 1
++=======
++y = 2
++>>>>>>> branch
+ z = 3";
+
     const DIFF_WITH_MERGE_CONFLICT: &str = r#"
 diff --cc Makefile
 index 759070d,3daf9eb..0000000
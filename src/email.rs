@@ -0,0 +1,169 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// The line starting a message of an mbox, e.g. the line starting each patch of
+    /// `git format-patch` output: "From 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e Mon Sep 17 00:00:00
+    /// 2001".
+    static ref MBOX_FROM_LINE_REGEX: Regex =
+        Regex::new(r"^From \S+ +[A-Z][a-z]{2} [A-Z][a-z]{2} +\d+ \d\d:\d\d:\d\d \d{4}$").unwrap();
+
+    /// A subject with a tag giving the position of the patch in its series, e.g.
+    /// "[PATCH v2 1/3] Fix the parser".
+    static ref SUBJECT_TAG_REGEX: Regex = Regex::new(r"^\[([^\]]*)\]\s*(.*)$").unwrap();
+
+    /// An encoded word of a header, e.g. "=?UTF-8?q?Fix=20the=20parser?=".
+    static ref ENCODED_WORD_REGEX: Regex = Regex::new(r"=\?[^?]+\?[qQ]\?([^?]*)\?=").unwrap();
+}
+
+/// The email headers shown under a patch's commit-style header. Other headers are omitted.
+const SHOWN_HEADERS: [&str; 4] = ["From:", "Date:", "To:", "Cc:"];
+
+/// Return true if the line starts a message of an mbox, such as a patch of `git format-patch`
+/// output.
+pub fn is_mbox_from_line(line: &str) -> bool {
+    MBOX_FROM_LINE_REGEX.is_match(line)
+}
+
+/// The email headers of a patch: the lines following the mbox "From " line, up to the blank line
+/// ending the headers.
+#[derive(Debug, Default)]
+pub struct EmailHeader {
+    subject: Option<String>,
+    shown_lines: Vec<String>,
+    // Whether the header being continued by folded lines is the subject, or a shown header.
+    continued_header: Option<bool>,
+}
+
+impl EmailHeader {
+    /// Add a line to the header. Return false if the line is the blank line ending the header.
+    pub fn push(&mut self, line: &str, raw_line: &str) -> bool {
+        if line.is_empty() {
+            return false;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // A folded header line, continuing the preceding header.
+            match (self.continued_header, self.subject.as_mut()) {
+                (Some(true), Some(subject)) => subject.push_str(line),
+                (Some(false), _) => self.shown_lines.push(raw_line.to_string()),
+                _ => {}
+            }
+        } else if let Some(subject) = line.strip_prefix("Subject: ") {
+            self.subject = Some(subject.to_string());
+            self.continued_header = Some(true);
+        } else if SHOWN_HEADERS.iter().any(|header| line.starts_with(header)) {
+            self.shown_lines.push(raw_line.to_string());
+            self.continued_header = Some(false);
+        } else {
+            self.continued_header = None;
+        }
+        true
+    }
+
+    /// Return the text of the commit-style header of the patch: the subject, preceded by the
+    /// position of the patch in its series, e.g. "PATCH 1/3: Fix the parser".
+    pub fn get_commit_header(&self) -> String {
+        let subject = decode_encoded_words(self.subject.as_deref().unwrap_or(""));
+        match SUBJECT_TAG_REGEX.captures(&subject) {
+            Some(captures) => format!("{}: {}", &captures[1], &captures[2]),
+            None => subject,
+        }
+    }
+
+    /// Return the headers shown under the commit-style header, e.g. "From:" and "Date:".
+    pub fn get_shown_lines(&self) -> &[String] {
+        &self.shown_lines
+    }
+}
+
+/// Decode the "Q"-encoded words of a header, which `git format-patch` uses for non-ASCII text.
+fn decode_encoded_words(s: &str) -> String {
+    // Whitespace between adjacent encoded words is not part of the text.
+    let s = s.replace("?= =?", "?==?");
+    ENCODED_WORD_REGEX
+        .replace_all(&s, |captures: &regex::Captures| {
+            let encoded = captures[1].as_bytes();
+            let mut bytes = Vec::new();
+            let mut i = 0;
+            while i < encoded.len() {
+                let hex = encoded
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match (encoded[i], hex) {
+                    (b'=', Some(byte)) => {
+                        bytes.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    (b'_', _) => bytes.push(b' '),
+                    (byte, _) => bytes.push(byte),
+                }
+                i += 1;
+            }
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mbox_from_line() {
+        assert!(is_mbox_from_line(
+            "From 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e Mon Sep 17 00:00:00 2001"
+        ));
+        assert!(is_mbox_from_line(
+            "From someone@example.com Thu Jan  2 10:00:00 2020"
+        ));
+        assert!(!is_mbox_from_line("From: someone <someone@example.com>"));
+        assert!(!is_mbox_from_line("From the start of the file"));
+    }
+
+    #[test]
+    fn test_email_header() {
+        let mut header = EmailHeader::default();
+        for line in &[
+            "From: someone <someone@example.com>",
+            "Date: Thu, 2 Jan 2020 10:00:00 +0100",
+            "Subject: [PATCH v2 1/3] Fix the parser when the subject is long enough",
+            " to be folded",
+            "Message-Id: <1@example.com>",
+        ] {
+            assert!(header.push(line, line));
+        }
+        assert!(!header.push("", ""));
+        assert_eq!(
+            header.get_commit_header(),
+            "PATCH v2 1/3: Fix the parser when the subject is long enough to be folded"
+        );
+        assert_eq!(
+            header.get_shown_lines(),
+            [
+                "From: someone <someone@example.com>",
+                "Date: Thu, 2 Jan 2020 10:00:00 +0100"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_commit_header_without_tag() {
+        let mut header = EmailHeader::default();
+        header.push("Subject: Fix the parser", "Subject: Fix the parser");
+        assert_eq!(header.get_commit_header(), "Fix the parser");
+    }
+
+    #[test]
+    fn test_decode_encoded_words() {
+        assert_eq!(
+            decode_encoded_words("[PATCH] =?UTF-8?q?Fix=20the=20na=C3=AFve?= parser"),
+            "[PATCH] Fix the naïve parser"
+        );
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?q?a_b?= =?UTF-8?q?c?="),
+            "a bc"
+        );
+    }
+}
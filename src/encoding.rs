@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+/// The encoding used to decode input lines that are not valid UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,        // Invalid bytes are passed through unchanged
+    Latin1,      // ISO-8859-1
+    Windows1252, // Latin-1 with printable characters in place of the C1 control codes
+}
+
+/// The characters of Windows-1252 for the bytes 0x80 to 0x9F; None for the bytes that it leaves
+/// undefined. The other bytes are the same as in Latin-1.
+const WINDOWS_1252_C1_CHARS: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+/// A byte that cannot be decoded is held in a string as a character of the last private use area,
+/// U+10FF80 to U+10FFFF, one for each of the bytes 0x80 to 0xFF. (Bytes below 0x80 are always
+/// valid UTF-8.) RawBytesWriter writes the byte in place of the character.
+const ESCAPED_BYTE_BASE: u32 = 0x10FF00;
+
+/// Return an iterator over the lines of the input, decoded into strings. A line keeps the carriage
+/// return of a CRLF line ending, which delta() removes and, under --color-only, restores in its
+/// output. Input is read until the end, or until a read error.
+pub fn read_lines<R: BufRead>(mut reader: R, encoding: Encoding) -> impl Iterator<Item = String> {
+    let mut bytes = Vec::new();
+    std::iter::from_fn(move || {
+        bytes.clear();
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                if bytes.last() == Some(&b'\n') {
+                    bytes.pop();
+                }
+                Some(decode_line(&bytes, encoding))
+            }
+        }
+    })
+}
+
+/// Decode a line. A line that is valid UTF-8 is decoded as UTF-8, whatever the encoding. Otherwise
+/// the line is decoded from the given encoding, and bytes that remain undecodable are escaped, so
+/// that RawBytesWriter writes them unchanged.
+pub fn decode_line(bytes: &[u8], encoding: Encoding) -> String {
+    if let Ok(line) = std::str::from_utf8(bytes) {
+        return escape_str(line);
+    }
+    match encoding {
+        Encoding::Utf8 => decode_utf8_lossless(bytes),
+        Encoding::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+        Encoding::Windows1252 => bytes
+            .iter()
+            .map(|byte| match byte {
+                0x80..=0x9F => WINDOWS_1252_C1_CHARS[(byte - 0x80) as usize]
+                    .unwrap_or_else(|| escape_byte(*byte)),
+                _ => *byte as char,
+            })
+            .collect(),
+    }
+}
+
+/// Decode UTF-8, escaping each byte of an invalid sequence.
+fn decode_utf8_lossless(mut bytes: &[u8]) -> String {
+    let mut line = String::new();
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => {
+                line.push_str(&escape_str(valid));
+                return line;
+            }
+            Err(error) => {
+                let (valid, rest) = bytes.split_at(error.valid_up_to());
+                line.push_str(&escape_str(std::str::from_utf8(valid).unwrap()));
+                let invalid_len = error.error_len().unwrap_or(rest.len());
+                line.extend(rest[..invalid_len].iter().map(|byte| escape_byte(*byte)));
+                bytes = &rest[invalid_len..];
+            }
+        }
+    }
+}
+
+/// Escape the bytes of the characters of a string that are themselves in the range of escaped
+/// bytes, so that they are written unchanged.
+fn escape_str(s: &str) -> String {
+    if !s.chars().any(is_escaped_byte) {
+        return s.to_string();
+    }
+    let mut escaped = String::new();
+    for c in s.chars() {
+        if is_escaped_byte(c) {
+            let mut buf = [0; 4];
+            escaped.extend(c.encode_utf8(&mut buf).bytes().map(escape_byte));
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+fn escape_byte(byte: u8) -> char {
+    std::char::from_u32(ESCAPED_BYTE_BASE + byte as u32).unwrap()
+}
+
+fn is_escaped_byte(c: char) -> bool {
+    c as u32 >= ESCAPED_BYTE_BASE + 0x80
+}
+
+/// A writer that writes escaped bytes (see decode_line) as the bytes themselves. It also restores
+/// the CRLF line endings of the input: line_endings holds, for each input line not yet written,
+/// whether it ended with CRLF. It is only filled under --color-only, when each input line is
+/// written as exactly one output line.
+pub struct RawBytesWriter<'a> {
+    writer: &'a mut dyn Write,
+    line_endings: Rc<RefCell<VecDeque<bool>>>,
+    // The start of an escaped byte, when a write ends in its middle
+    pending: Vec<u8>,
+}
+
+impl<'a> RawBytesWriter<'a> {
+    pub fn new(writer: &'a mut dyn Write, line_endings: Rc<RefCell<VecDeque<bool>>>) -> Self {
+        Self {
+            writer,
+            line_endings,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Write for RawBytesWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut output = Vec::with_capacity(buf.len());
+        for byte in buf {
+            self.pending.push(*byte);
+            // The UTF-8 encoding of U+10FF80 to U+10FFFF is F4 8F BE 80 to F4 8F BF BF.
+            match *self.pending.as_slice() {
+                [0xF4] | [0xF4, 0x8F] | [0xF4, 0x8F, 0xBE] | [0xF4, 0x8F, 0xBF] => continue,
+                [0xF4, 0x8F, high @ 0xBE..=0xBF, low] => {
+                    output.push(((high & 0x03) << 6) | (low & 0x3F));
+                }
+                [b'\n'] => {
+                    if self.line_endings.borrow_mut().pop_front() == Some(true) {
+                        output.push(b'\r');
+                    }
+                    output.push(b'\n');
+                }
+                _ => output.extend_from_slice(&self.pending),
+            }
+            self.pending.clear();
+        }
+        self.writer.write_all(&output)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.pending)?;
+        self.pending.clear();
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_raw_bytes(s: &str, line_endings: &[bool]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let line_endings = Rc::new(RefCell::new(line_endings.iter().cloned().collect()));
+        let mut writer = RawBytesWriter::new(&mut output, line_endings);
+        // Write a byte at a time, splitting escaped bytes between writes.
+        for byte in s.as_bytes() {
+            writer.write_all(&[*byte]).unwrap();
+        }
+        writer.flush().unwrap();
+        output
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let input: &[u8] = b"a\r\nb\xff\nc";
+        let lines: Vec<String> = read_lines(input, Encoding::Utf8).collect();
+        assert_eq!(lines[0], "a\r");
+        assert_eq!(lines[1], format!("b{}", escape_byte(0xFF)));
+        assert_eq!(lines[2], "c");
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_bytes_round_trip() {
+        for bytes in &[
+            &b"caf\xe9 \xe2\x82"[..],
+            b"\x82\xa0\x82\xa2", // Shift-JIS
+            "\u{10FF80}\u{10FFFF}".as_bytes(),
+            "naïve".as_bytes(),
+        ] {
+            let line = decode_line(bytes, Encoding::Utf8);
+            assert_eq!(write_raw_bytes(&line, &[]), *bytes);
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy_encoding() {
+        assert_eq!(decode_line(b"caf\xe9", Encoding::Latin1), "café");
+        assert_eq!(
+            decode_line(b"\x80 caf\xe9", Encoding::Windows1252),
+            "€ café"
+        );
+        assert_eq!(
+            write_raw_bytes(&decode_line(b"\x81\xe9", Encoding::Windows1252), &[]),
+            [&[0x81][..], "é".as_bytes()].concat()
+        );
+        // Valid UTF-8 is decoded as UTF-8.
+        assert_eq!(decode_line("café".as_bytes(), Encoding::Latin1), "café");
+    }
+
+    #[test]
+    fn test_crlf_line_endings_are_restored() {
+        assert_eq!(
+            write_raw_bytes("a\nb\nc\n", &[true, false, true]),
+            b"a\r\nb\nc\r\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// A commit line of `git log --graph` output, e.g. "| * commit 94907c0f1". The prefix
+    /// contains the '*' marking the commit's column, and starts with a graph character: a line
+    /// starting with a space, such as the context line " * commit 1a2b3c4d" of a C comment, is
+    /// not a commit line.
+    static ref GRAPH_COMMIT_LINE_REGEX: Regex =
+        Regex::new(r"^((?:[|/\\_][|/\\_ ]*)?\*[*|/\\_ .-]*?)commit [0-9a-f]{7,}").unwrap();
+}
+
+/// Characters that may occur in the graph columns of `git log --graph` output, other than in
+/// the commit line.
+const GRAPH_CHARACTERS: &[char] = &['*', '|', '/', '\\', '_', ' '];
+
+/// If line is a commit line of `git log --graph` output, return the width of its graph prefix.
+pub fn get_commit_line_graph_width(line: &str) -> Option<usize> {
+    GRAPH_COMMIT_LINE_REGEX
+        .captures(line)
+        .and_then(|captures| captures.get(1))
+        .map(|prefix| prefix.end())
+}
+
+/// Return the width of the graph prefix of a line of `git log --graph` output, given the width
+/// of the graph prefix of the current commit's commit line. The lines of a commit have graph
+/// prefixes of that width, but a line between commits may be shorter.
+pub fn get_graph_prefix_width(line: &str, graph_width: usize) -> usize {
+    line.chars()
+        .take(graph_width)
+        .take_while(|c| GRAPH_CHARACTERS.contains(c))
+        .count()
+}
+
+/// A writer that writes a graph prefix at the start of every line. The prefix is shared, so that
+/// it can be changed while the writer is in use. The '*' marking a commit's column is written only
+/// once: on subsequent lines, such as the lines of a box drawn around the commit line, the column
+/// continues with '|'.
+pub struct PrefixWriter<'a> {
+    writer: &'a mut dyn Write,
+    prefix: Rc<RefCell<String>>,
+    at_line_start: bool,
+}
+
+impl<'a> PrefixWriter<'a> {
+    pub fn new(writer: &'a mut dyn Write, prefix: Rc<RefCell<String>>) -> Self {
+        Self {
+            writer,
+            prefix,
+            at_line_start: true,
+        }
+    }
+}
+
+impl<'a> Write for PrefixWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for line in buf.split_inclusive(|b| *b == b'\n') {
+            if self.at_line_start {
+                let mut prefix = self.prefix.borrow_mut();
+                self.writer.write_all(prefix.as_bytes())?;
+                if prefix.contains('*') {
+                    *prefix = prefix.replace('*', "|");
+                }
+            }
+            self.writer.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_commit_line_graph_width() {
+        assert_eq!(get_commit_line_graph_width("* commit 94907c0f1"), Some(2));
+        assert_eq!(get_commit_line_graph_width("| * commit 94907c0f1"), Some(4));
+        assert_eq!(get_commit_line_graph_width("*   commit 94907c0f1"), Some(4));
+        assert_eq!(
+            get_commit_line_graph_width("*-.   commit 94907c0f1"),
+            Some(6)
+        );
+        assert_eq!(get_commit_line_graph_width("commit 94907c0f1"), None);
+        assert_eq!(get_commit_line_graph_width("| +commit 94907c0f1"), None);
+        assert_eq!(get_commit_line_graph_width(" * commit 1a2b3c4d"), None);
+    }
+
+    #[test]
+    fn test_get_graph_prefix_width() {
+        assert_eq!(get_graph_prefix_width("| diff --git a/x b/x", 2), 2);
+        assert_eq!(get_graph_prefix_width("|  unchanged", 2), 2);
+        assert_eq!(get_graph_prefix_width("| -removed", 4), 2);
+        assert_eq!(get_graph_prefix_width("|", 2), 1);
+        assert_eq!(get_graph_prefix_width("| | ", 4), 4);
+    }
+
+    #[test]
+    fn test_prefix_writer_continues_commit_column() {
+        let mut output: Vec<u8> = Vec::new();
+        let prefix = Rc::new(RefCell::new("| * ".to_string()));
+        {
+            let mut writer = PrefixWriter::new(&mut output, Rc::clone(&prefix));
+            write!(writer, "─\ncommit\n─\n").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "| * ─\n| | commit\n| | ─\n"
+        );
+    }
+
+    #[test]
+    fn test_prefix_writer() {
+        let mut output: Vec<u8> = Vec::new();
+        let prefix = Rc::new(RefCell::new("| ".to_string()));
+        {
+            let mut writer = PrefixWriter::new(&mut output, Rc::clone(&prefix));
+            write!(writer, "a\nb").unwrap();
+            *prefix.borrow_mut() = "|/".to_string();
+            writeln!(writer, "c").unwrap();
+            writeln!(writer, "d").unwrap();
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "| a\n| bc\n|/d\n");
+    }
+}
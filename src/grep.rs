@@ -0,0 +1,276 @@
+use console::strip_ansi_codes;
+use lazy_static::lazy_static;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ansi;
+use crate::cli;
+use crate::config::Config;
+use crate::delta;
+use crate::line_numbers;
+use crate::paint::Painter;
+use crate::parse;
+use crate::style;
+
+lazy_static! {
+    /// A matching line of `git grep -n` or `rg --no-heading -n` output, e.g.
+    /// "src/delta.rs:61:pub fn delta<I>(". The path may contain any character but ':'.
+    static ref GREP_MATCH_LINE_REGEX: Regex = Regex::new(r"^([^:]+?):(\d+):(.*)$").unwrap();
+
+    /// A context line of grep output, e.g. "src/delta.rs-62-    lines: I,".
+    static ref GREP_CONTEXT_LINE_REGEX: Regex = Regex::new(r"^([^:]+?)-(\d+)-(.*)$").unwrap();
+
+    /// The first matching line of grep output. The path may not contain whitespace, so that other
+    /// input (e.g. "--- a.txt 2020-01-01 10:00:00") is not taken for grep output.
+    static ref GREP_FIRST_MATCH_LINE_REGEX: Regex = Regex::new(r"^([^:\s]+):\d+:").unwrap();
+
+    /// A context line preceding the first matching line of grep output.
+    static ref GREP_FIRST_CONTEXT_LINE_REGEX: Regex = Regex::new(r"^[^:\s]+-\d+-").unwrap();
+}
+
+/// A line of grep output.
+#[derive(Debug, PartialEq)]
+pub struct GrepLine {
+    pub path: String,
+    pub line_number: usize,
+    pub is_match: bool,    // False for a context line
+    pub code_start: usize, // Number of visible characters preceding the code
+}
+
+/// Return true if the lines are `git grep -n` or `rg --no-heading -n` output. Lines are read up to
+/// the first matching line, e.g. "src/delta.rs:61:pub fn delta<I>(", which may only be preceded
+/// by context lines of the same file, e.g. "src/delta.rs-60-". A first line that merely looks like
+/// a context line, such as "2020-01-01 10:00:00 Started", is not enough. Nor is a matching line
+/// whose path does not look like a file path, such as "localhost:8080: refused", unless another
+/// line of the same path follows or precedes it.
+pub fn is_grep_output<I>(mut lines: I) -> bool
+where
+    I: Iterator<Item = String>,
+{
+    let mut context_lines: Vec<String> = Vec::new();
+    for line in lines.by_ref() {
+        if let Some(captures) = GREP_FIRST_MATCH_LINE_REGEX.captures(&line) {
+            let path = &captures[1];
+            let is_line_of_path = |line: &str| {
+                line.strip_prefix(path)
+                    .and_then(|rest| {
+                        parse_line_number(rest, ':').or_else(|| parse_line_number(rest, '-'))
+                    })
+                    .is_some()
+            };
+            if path.chars().all(|c| c.is_ascii_digit())
+                || !context_lines.iter().all(|line| is_line_of_path(line))
+            {
+                return false;
+            }
+            return !context_lines.is_empty()
+                || is_path_like(path)
+                || lines.next().map(|line| is_line_of_path(&line)) == Some(true);
+        } else if GREP_FIRST_CONTEXT_LINE_REGEX.is_match(&line) {
+            context_lines.push(line);
+        } else {
+            return false;
+        }
+    }
+    false
+}
+
+/// Return true if the path of a grep line looks like a file path, e.g. "src/delta.rs" or
+/// "README.md", rather than, say, a host name followed by a port.
+fn is_path_like(path: &str) -> bool {
+    (path.contains('/') || path.contains('.'))
+        && !path.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Parse a line of grep output. A context line is recognized by the path of the preceding line,
+/// since a path may itself contain "-<number>-".
+pub fn parse_grep_line(line: &str, current_path: Option<&str>) -> Option<GrepLine> {
+    if let Some(path) = current_path {
+        if let Some(rest) = line.strip_prefix(path) {
+            for (separator, is_match) in [(':', true), ('-', false)] {
+                if let Some((line_number, n_digits)) = parse_line_number(rest, separator) {
+                    return Some(GrepLine {
+                        path: path.to_string(),
+                        line_number,
+                        is_match,
+                        code_start: path.chars().count() + n_digits + 2,
+                    });
+                }
+            }
+        }
+    }
+    [
+        (&*GREP_MATCH_LINE_REGEX, true),
+        (&*GREP_CONTEXT_LINE_REGEX, false),
+    ]
+    .iter()
+    .find_map(|(regex, is_match)| {
+        regex.captures(line).map(|captures| GrepLine {
+            path: captures[1].to_string(),
+            line_number: captures[2].parse().unwrap_or(0),
+            is_match: *is_match,
+            code_start: line[..captures.get(3).unwrap().start()].chars().count(),
+        })
+    })
+}
+
+/// Parse "<sep><number><sep>" at the start of s, returning the number and its number of digits.
+fn parse_line_number(s: &str, separator: char) -> Option<(usize, usize)> {
+    let rest = s.strip_prefix(separator)?;
+    let n_digits = rest.chars().take_while(char::is_ascii_digit).count();
+    if n_digits == 0 || !rest[n_digits..].starts_with(separator) {
+        return None;
+    }
+    Some((rest[..n_digits].parse().ok()?, n_digits))
+}
+
+/// Paint grep output: each file's lines are preceded by a file header, and each line is shown
+/// with its line number and syntax highlighted code. Matches that are colored in the input are
+/// emphasized.
+pub fn paint_grep<I>(lines: I, painter: &mut Painter, config: &Config) -> std::io::Result<()>
+where
+    I: Iterator<Item = String>,
+{
+    let mut current_path: Option<String> = None;
+    for raw_line in lines {
+        let line = strip_ansi_codes(&raw_line).to_string();
+        let grep_line = match parse_grep_line(&line, current_path.as_deref()) {
+            Some(grep_line) => grep_line,
+            None => {
+                // E.g. the "--" separator between groups of context lines.
+                writeln!(painter.writer, "{}", raw_line)?;
+                continue;
+            }
+        };
+        if current_path.as_deref() != Some(grep_line.path.as_str()) {
+            painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+                &grep_line.path,
+            ));
+            painter.set_highlighter();
+            if config.file_style != cli::SectionStyle::Plain {
+                delta::handle_generic_file_meta_header_line(painter, &grep_line.path, config)?;
+            }
+            current_path = Some(grep_line.path.clone());
+        }
+        let (_, raw_code) = ansi::split_at_visible_char(&raw_line, grep_line.code_start);
+        paint_grep_line(&grep_line, raw_code, painter, config);
+        painter.emit()?;
+    }
+    Ok(())
+}
+
+/// Paint the code of a grep line, preceded by its line number. The sections of the code that are
+/// styled in the input are painted with the emphasis background color.
+fn paint_grep_line(grep_line: &GrepLine, raw_code: &str, painter: &mut Painter, config: &Config) {
+    let mut code = " ".to_string();
+    let mut sections = Vec::new();
+    let mut section_start = 0;
+    for (styled, text) in ansi::get_styled_sections(raw_code) {
+        code.push_str(&delta::expand_tabs(text.graphemes(true), config.tab_width));
+        sections.push((styled, section_start, code.len()));
+        section_start = code.len();
+    }
+    code.push('\n');
+    let mut diff_style_sections: Vec<_> = sections
+        .iter()
+        .map(|(styled, start, end)| {
+            let style_modifier = if *styled {
+                config.plus_emph_style_modifier
+            } else {
+                style::NO_BACKGROUND_COLOR_STYLE_MODIFIER
+            };
+            (style_modifier, &code[*start..*end])
+        })
+        .collect();
+    diff_style_sections.push((
+        style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+        &code[section_start..],
+    ));
+
+    let line_number_style_modifier = if grep_line.is_match {
+        config.line_numbers_plus_style_modifier
+    } else {
+        config.line_numbers_zero_style_modifier
+    };
+    painter
+        .output_buffer
+        .push_str(&line_numbers::format_line_number_panel(
+            Some(grep_line.line_number),
+            line_number_style_modifier,
+            line_numbers::MIN_LINE_NUMBER_WIDTH,
+            config,
+        ));
+    let syntax_style_sections =
+        Painter::get_line_syntax_style_sections(&code, &mut painter.highlighter, config, true);
+    Painter::paint_lines(
+        vec![syntax_style_sections],
+        vec![diff_style_sections],
+        &mut painter.output_buffer,
+        config,
+        "",
+        style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+        Some(false),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_grep_output() {
+        let is_grep_output = |input: &str| super::is_grep_output(input.lines().map(String::from));
+        assert!(is_grep_output("src/delta.rs:61:pub fn delta<I>("));
+        assert!(is_grep_output("src/delta.rs:61:"));
+        assert!(is_grep_output(
+            "src/delta.rs-60-\nsrc/delta.rs-61-\nsrc/delta.rs:62:    lines: I,"
+        ));
+        assert!(!is_grep_output("src/delta.rs-62-    lines: I,"));
+        assert!(!is_grep_output("src/delta.rs-62-\nsrc/paint.rs:63:"));
+        assert!(!is_grep_output("--- a.txt 2020-01-01 10:00:00"));
+        assert!(!is_grep_output("2020-01-01 10:00:00 Started\nDone"));
+        assert!(!is_grep_output("foo-12-bar\n+++ b.txt"));
+        assert!(!is_grep_output("12:30:45 server started\n12:30:46 ready"));
+        assert!(!is_grep_output("localhost:8080: refused\nretrying"));
+        assert!(!is_grep_output("10.0.0.1:22: refused"));
+        assert!(is_grep_output("Makefile:3:all:\nMakefile:4:\tcargo build"));
+        assert!(is_grep_output("Makefile-2-\nMakefile:3:all:"));
+        assert!(!is_grep_output("Makefile:3:all:"));
+        assert!(!is_grep_output(
+            "commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"
+        ));
+    }
+
+    #[test]
+    fn test_parse_grep_line() {
+        assert_eq!(
+            parse_grep_line("src/delta.rs:61:pub fn delta<I>(", None),
+            Some(GrepLine {
+                path: "src/delta.rs".to_string(),
+                line_number: 61,
+                is_match: true,
+                code_start: 16,
+            })
+        );
+        assert_eq!(
+            parse_grep_line("src/delta.rs-62-    lines: I,", None),
+            Some(GrepLine {
+                path: "src/delta.rs".to_string(),
+                line_number: 62,
+                is_match: false,
+                code_start: 16,
+            })
+        );
+        // The path of the preceding line disambiguates paths containing "-<number>-".
+        assert_eq!(
+            parse_grep_line("a-1-b.rs-7-x", Some("a-1-b.rs")),
+            Some(GrepLine {
+                path: "a-1-b.rs".to_string(),
+                line_number: 7,
+                is_match: false,
+                code_start: 11,
+            })
+        );
+        assert_eq!(parse_grep_line("--", Some("a.rs")), None);
+    }
+}
@@ -0,0 +1,42 @@
+#[macro_use]
+extern crate error_chain;
+
+mod align;
+mod ansi;
+pub mod bat;
+mod blame;
+pub mod cli;
+mod color_moved;
+mod combined_diff;
+mod commit_meta;
+pub mod config;
+mod context_diff;
+pub mod delta;
+mod draw;
+pub mod edits;
+mod email;
+pub mod encoding;
+mod env;
+mod graph;
+mod grep;
+mod line_numbers;
+mod merge_conflict;
+pub mod model;
+mod moved;
+mod normal_diff;
+pub mod paint;
+mod parse;
+mod range_diff;
+mod side_by_side;
+pub mod style;
+mod word_diff;
+
+mod errors {
+    error_chain! {
+        foreign_links {
+            Io(::std::io::Error);
+            SyntectError(::syntect::LoadingError);
+            ParseIntError(::std::num::ParseIntError);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use syntect::highlighting::StyleModifier;
+
+use crate::config::Config;
+use crate::paint;
+
+/// Minimum width of a line number column, not counting its separator.
+pub const MIN_LINE_NUMBER_WIDTH: usize = 4;
+
+/// Return the width of the line number columns of a hunk with the given (start line, line count)
+/// ranges: the number of digits of the largest line number in the hunk, and at least
+/// MIN_LINE_NUMBER_WIDTH, so that the columns of neighbouring hunks line up.
+pub fn get_line_number_width(ranges: &[(usize, usize)]) -> usize {
+    ranges
+        .iter()
+        .map(|(start, count)| (start + count).saturating_sub(1).to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(MIN_LINE_NUMBER_WIDTH)
+}
+
+/// Return the gutter for a line in the traditional layout: the line number in the old file and
+/// the line number in the new file, in columns of the given width. A number is blank if the line
+/// does not exist in that file.
+pub fn format_line_numbers(
+    minus_number: Option<usize>,
+    plus_number: Option<usize>,
+    width: usize,
+    config: &Config,
+) -> String {
+    let (minus_style_modifier, plus_style_modifier) = match (minus_number, plus_number) {
+        (Some(_), Some(_)) => (
+            config.line_numbers_zero_style_modifier,
+            config.line_numbers_zero_style_modifier,
+        ),
+        _ => (
+            config.line_numbers_minus_style_modifier,
+            config.line_numbers_plus_style_modifier,
+        ),
+    };
+    format!(
+        "{}{}{}{}",
+        format_line_number(minus_number, minus_style_modifier, width, config),
+        paint_separator("⋮", config),
+        format_line_number(plus_number, plus_style_modifier, width, config),
+        paint_separator("│", config),
+    )
+}
+
+/// Return the gutter for a line in one panel of the side-by-side layout, with a line number
+/// column of the given width.
+pub fn format_line_number_panel(
+    number: Option<usize>,
+    style_modifier: StyleModifier,
+    width: usize,
+    config: &Config,
+) -> String {
+    format!(
+        "{}{}",
+        format_line_number(number, style_modifier, width, config),
+        paint_separator("│", config),
+    )
+}
+
+fn format_line_number(
+    number: Option<usize>,
+    style_modifier: StyleModifier,
+    width: usize,
+    config: &Config,
+) -> String {
+    match number {
+        Some(number) => paint::paint_text_with_style_modifier(
+            &format!("{:>width$}", number, width = width),
+            style_modifier,
+            config,
+        ),
+        None => " ".repeat(width),
+    }
+}
+
+fn paint_separator(separator: &str, config: &Config) -> String {
+    paint::paint_text_with_style_modifier(
+        separator,
+        config.line_numbers_zero_style_modifier,
+        config,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_line_number_width() {
+        assert_eq!(get_line_number_width(&[]), 4);
+        assert_eq!(get_line_number_width(&[(5, 3), (5, 3)]), 4);
+        assert_eq!(get_line_number_width(&[(9998, 3), (9998, 2)]), 5);
+        assert_eq!(get_line_number_width(&[(9998, 2), (9998, 3)]), 5);
+        assert_eq!(get_line_number_width(&[(123456, 0), (123456, 1)]), 6);
+    }
+}
@@ -1,39 +1,15 @@
-#[macro_use]
-extern crate error_chain;
-
-mod align;
-mod bat;
-mod cli;
-mod config;
-mod delta;
-mod draw;
-mod edits;
-mod env;
-mod paint;
-mod parse;
-mod style;
-
-use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::io::{self, ErrorKind, Read, Write};
 use std::process;
 
 use ansi_term;
 use atty;
 use structopt::StructOpt;
-use syntect::highlighting::{Color, FontStyle, Style};
-
-use crate::bat::assets::{list_languages, HighlightingAssets};
-use crate::bat::output::{OutputType, PagingMode};
-use crate::delta::delta;
-
-mod errors {
-    error_chain! {
-        foreign_links {
-            Io(::std::io::Error);
-            SyntectError(::syntect::LoadingError);
-            ParseIntError(::std::num::ParseIntError);
-        }
-    }
-}
+use syntect::highlighting::{Color, FontStyle, Style, StyleModifier};
+
+use git_delta::bat::assets::{list_languages, HighlightingAssets};
+use git_delta::bat::output::{OutputType, PagingMode};
+use git_delta::delta::delta;
+use git_delta::{cli, config, encoding, paint, style};
 
 fn main() -> std::io::Result<()> {
     let opt = cli::Opt::from_args();
@@ -62,10 +38,7 @@ fn main() -> std::io::Result<()> {
     let mut writer = output_type.handle().unwrap();
 
     if let Err(error) = delta(
-        io::stdin()
-            .lock()
-            .lines()
-            .map(|l| l.unwrap_or_else(|_| "<delta: invalid utf-8 data>".to_string())),
+        encoding::read_lines(io::stdin().lock(), config.encoding),
         &config,
         &assets,
         &mut writer,
@@ -81,29 +54,44 @@ fn main() -> std::io::Result<()> {
 fn show_background_colors(config: &config::Config) {
     println!(
         "delta \
-         --minus-color=\"{minus_color}\" \
-         --minus-emph-color=\"{minus_emph_color}\" \
-         --plus-color=\"{plus_color}\" \
-         --plus-emph-color=\"{plus_emph_color}\"",
-        minus_color = get_painted_rgb_string(
-            config.minus_style_modifier.background.unwrap(),
-            config.true_color
-        ),
-        minus_emph_color = get_painted_rgb_string(
-            config.minus_emph_style_modifier.background.unwrap(),
-            config.true_color
-        ),
-        plus_color = get_painted_rgb_string(
-            config.plus_style_modifier.background.unwrap(),
-            config.true_color
-        ),
-        plus_emph_color = get_painted_rgb_string(
-            config.plus_emph_style_modifier.background.unwrap(),
-            config.true_color
-        ),
+         --minus-style=\"{minus_style}\" \
+         --minus-emph-style=\"{minus_emph_style}\" \
+         --plus-style=\"{plus_style}\" \
+         --plus-emph-style=\"{plus_emph_style}\" \
+         --minus-moved-style=\"{minus_moved_style}\" \
+         --plus-moved-style=\"{plus_moved_style}\"",
+        minus_style = get_painted_style_string(config.minus_style_modifier, config.true_color),
+        minus_emph_style =
+            get_painted_style_string(config.minus_emph_style_modifier, config.true_color),
+        plus_style = get_painted_style_string(config.plus_style_modifier, config.true_color),
+        plus_emph_style =
+            get_painted_style_string(config.plus_emph_style_modifier, config.true_color),
+        minus_moved_style =
+            get_painted_style_string(config.minus_moved_style_modifier, config.true_color),
+        plus_moved_style =
+            get_painted_style_string(config.plus_moved_style_modifier, config.true_color),
+    )
+}
+
+/// Return the foreground color and the background color of a style as a style string, the
+/// background color being written as an RGB hex code painted with that color.
+fn get_painted_style_string(style_modifier: StyleModifier, true_color: bool) -> String {
+    let foreground = match style_modifier.foreground {
+        None => "syntax".to_string(),
+        Some(color) if color == style::NO_COLOR => "normal".to_string(),
+        Some(color) => get_rgb_string(color),
+    };
+    format!(
+        "{} {}",
+        foreground,
+        get_painted_rgb_string(style_modifier.background.unwrap(), true_color)
     )
 }
 
+fn get_rgb_string(color: Color) -> String {
+    format!("#{:02x?}{:02x?}{:02x?}", color.r, color.g, color.b)
+}
+
 fn get_painted_rgb_string(color: Color, true_color: bool) -> String {
     let mut string = String::new();
     let style = Style {
@@ -111,12 +99,7 @@ fn get_painted_rgb_string(color: Color, true_color: bool) -> String {
         background: color,
         font_style: FontStyle::empty(),
     };
-    paint::paint_text(
-        &format!("#{:02x?}{:02x?}{:02x?}", color.r, color.g, color.b),
-        style,
-        &mut string,
-        true_color,
-    );
+    paint::paint_text(&get_rgb_string(color), style, &mut string, true_color);
     string.push_str("\x1b[0m"); // reset
     string
 }
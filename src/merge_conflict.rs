@@ -0,0 +1,257 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Style, StyleModifier};
+
+use crate::combined_diff;
+use crate::config::Config;
+use crate::edits;
+use crate::line_numbers;
+use crate::paint::Painter;
+use crate::side_by_side;
+use crate::style;
+
+lazy_static! {
+    /// The marker line that starts a merge conflict region, e.g. "<<<<<<< Updated upstream".
+    static ref START_MARKER_REGEX: Regex = Regex::new(r"^<{7}(?: |$)").unwrap();
+    /// The marker line that starts the base section, e.g. "||||||| constructed merge base".
+    static ref BASE_MARKER_REGEX: Regex = Regex::new(r"^\|{7}(?: |$)").unwrap();
+    /// The marker line that starts the "theirs" section.
+    static ref SEPARATOR_MARKER_REGEX: Regex = Regex::new(r"^={7}$").unwrap();
+    /// The marker line that ends a merge conflict region, e.g. ">>>>>>> Stashed changes".
+    static ref END_MARKER_REGEX: Regex = Regex::new(r"^>{7}(?: |$)").unwrap();
+}
+
+/// A section of a merge conflict region.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// A line of a merge conflict region.
+pub struct MergeConflictLine {
+    pub markers: String,          // The marker columns of the diff line
+    pub line: String,             // The line, prepared for painting
+    pub section: Option<Section>, // None for a marker line
+}
+
+/// The lines of the merge conflict region currently being read from the diff. The region is
+/// painted when its end marker has been read, so that the edits between the "ours" and "theirs"
+/// sections can be inferred. A line of the region that is not an added line, e.g. an unchanged
+/// line of the "ours" section, is painted as usual: the lines buffered before it are painted, and
+/// the region continues with the section it was in.
+#[derive(Default)]
+pub struct MergeConflict {
+    pub lines: Vec<MergeConflictLine>,
+    section: Option<Section>,
+}
+
+impl MergeConflict {
+    /// Return true if a merge conflict region has been started and its end marker not yet read.
+    pub fn is_active(&self) -> bool {
+        self.section.is_some()
+    }
+
+    /// Return true if lines of the region are buffered, waiting to be painted.
+    pub fn is_buffered(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    /// Return true if code (an added line, without its marker columns) starts a merge conflict
+    /// region.
+    pub fn is_start_marker(code: &str) -> bool {
+        START_MARKER_REGEX.is_match(code)
+    }
+
+    /// Add a line to the region. code is the line without its marker columns, and line is the
+    /// line prepared for painting. Return true if the line ends the region.
+    pub fn push(&mut self, markers: &str, code: &str, line: String) -> bool {
+        let (section, is_marker) = if START_MARKER_REGEX.is_match(code) && !self.is_active() {
+            (Some(Section::Ours), true)
+        } else if BASE_MARKER_REGEX.is_match(code) && self.section == Some(Section::Ours) {
+            (Some(Section::Base), true)
+        } else if SEPARATOR_MARKER_REGEX.is_match(code) && self.section != Some(Section::Theirs) {
+            (Some(Section::Theirs), true)
+        } else if END_MARKER_REGEX.is_match(code) && self.section == Some(Section::Theirs) {
+            (None, true)
+        } else {
+            (self.section, false)
+        };
+        self.lines.push(MergeConflictLine {
+            markers: markers.to_string(),
+            line,
+            section: if is_marker { None } else { section },
+        });
+        self.section = section;
+        is_marker && section.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.section = None;
+    }
+}
+
+/// Paint the lines of a merge conflict region. The "ours" and "theirs" sections are painted with
+/// the backgrounds of removed and added lines respectively, with the edits between them
+/// emphasized; the base section has no background. Marker lines are painted in the hunk color.
+/// line_numbers holds the line numbers of the first line in the old and new files, and the line
+/// numbers following the region are returned; line_number_width is the width of their columns.
+/// The lines are all present in the new file; in a combined diff, a line is present in the old
+/// file (the first parent) if its first marker column is blank. In side-by-side mode, the lines
+/// are painted in the right column, opposite empty cells.
+pub fn paint_merge_conflict(
+    conflict: &MergeConflict,
+    highlighter: &mut HighlightLines,
+    line_numbers: (usize, usize),
+    line_number_width: usize,
+    n_parents: usize,
+    output_buffer: &mut String,
+    config: &Config,
+) -> (usize, usize) {
+    let section_lines = |section| -> Vec<String> {
+        conflict
+            .lines
+            .iter()
+            .filter(|line| line.section == Some(section))
+            .map(|line| line.line.clone())
+            .collect()
+    };
+    let (ours, base, theirs) = (
+        section_lines(Section::Ours),
+        section_lines(Section::Base),
+        section_lines(Section::Theirs),
+    );
+    // Both sides are syntax highlighted, even if removed lines are not.
+    let with_foreground = |style_modifier: StyleModifier| StyleModifier {
+        foreground: None,
+        ..style_modifier
+    };
+    let (mut ours_diff_style_sections, mut theirs_diff_style_sections, _) = edits::infer_edits(
+        &ours,
+        &theirs,
+        with_foreground(config.minus_style_modifier),
+        with_foreground(config.minus_emph_style_modifier),
+        config.plus_style_modifier,
+        config.plus_emph_style_modifier,
+        config.max_line_distance,
+        config.max_line_distance_for_naively_paired_lines,
+    );
+    ours_diff_style_sections.reverse();
+    theirs_diff_style_sections.reverse();
+    let mut base_lines = base.iter().rev().collect::<Vec<_>>();
+    let marker_style = Style {
+        foreground: config.hunk_color,
+        ..config.no_style
+    };
+
+    let (mut minus_line_number, mut plus_line_number) = line_numbers;
+    for line in &conflict.lines {
+        let (syntax_style_sections, diff_style_sections, background_style_modifier) = match line
+            .section
+        {
+            Some(section) => {
+                let diff_style_sections = match section {
+                    Section::Ours => ours_diff_style_sections.pop(),
+                    Section::Theirs => theirs_diff_style_sections.pop(),
+                    Section::Base => base_lines
+                        .pop()
+                        .map(|line| vec![(config.zero_style_modifier, line.as_str())]),
+                }
+                .unwrap_or_default();
+                let background_style_modifier = match section {
+                    Section::Ours => config.minus_style_modifier,
+                    Section::Base => config.zero_style_modifier,
+                    Section::Theirs => config.plus_style_modifier,
+                };
+                (
+                    Painter::get_line_syntax_style_sections(&line.line, highlighter, config, true),
+                    diff_style_sections,
+                    background_style_modifier,
+                )
+            }
+            None => (
+                vec![(marker_style, line.line.as_str())],
+                vec![(
+                    style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+                    line.line.as_str(),
+                )],
+                style::NO_BACKGROUND_COLOR_STYLE_MODIFIER,
+            ),
+        };
+        let in_old_file = n_parents > 1 && line.markers.starts_with(' ');
+        if config.side_by_side && n_parents == 1 {
+            side_by_side::paint_plus_line_side_by_side(
+                &syntax_style_sections,
+                &diff_style_sections,
+                config.plus_line_marker,
+                (plus_line_number, line_number_width),
+                background_style_modifier,
+                output_buffer,
+                config,
+            );
+            plus_line_number += 1;
+            continue;
+        }
+        if config.line_numbers {
+            output_buffer.push_str(&line_numbers::format_line_numbers(
+                Some(minus_line_number).filter(|_| in_old_file),
+                Some(plus_line_number),
+                line_number_width,
+                config,
+            ));
+        }
+        let prefix = if n_parents > 1 {
+            combined_diff::paint_markers(&line.markers, config)
+        } else {
+            config.plus_line_marker.to_string()
+        };
+        Painter::paint_lines(
+            vec![syntax_style_sections],
+            vec![diff_style_sections],
+            output_buffer,
+            config,
+            &prefix,
+            background_style_modifier,
+            None,
+        );
+        if in_old_file {
+            minus_line_number += 1;
+        }
+        plus_line_number += 1;
+    }
+    (minus_line_number, plus_line_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let mut conflict = MergeConflict::default();
+        assert!(MergeConflict::is_start_marker("<<<<<<< Updated upstream"));
+        assert!(!MergeConflict::is_start_marker("<<<<<<<< x"));
+        let lines = [
+            ("<<<<<<< Updated upstream", None),
+            ("ours", Some(Section::Ours)),
+            ("||||||| constructed merge base", None),
+            ("base", Some(Section::Base)),
+            ("=======", None),
+            ("theirs", Some(Section::Theirs)),
+            ("=======", Some(Section::Theirs)),
+        ];
+        for (code, _) in &lines {
+            assert!(!conflict.push("+", code, code.to_string()));
+        }
+        assert!(conflict.push("+", ">>>>>>> Stashed changes", "".to_string()));
+        let sections: Vec<Option<Section>> = conflict.lines.iter().map(|l| l.section).collect();
+        let mut expected: Vec<Option<Section>> = lines.iter().map(|(_, s)| *s).collect();
+        expected.push(None);
+        assert_eq!(sections, expected);
+        conflict.clear();
+        assert!(!conflict.is_active());
+    }
+}
@@ -0,0 +1,400 @@
+use console::strip_ansi_codes;
+
+use crate::combined_diff;
+use crate::delta::{self, Source};
+use crate::parse;
+
+/// A diff, as output by e.g. `git log -p`, `git show`, `git diff` or `diff -u`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    pub commits: Vec<Commit>,
+}
+
+/// A commit and the file diffs it introduces. Input without commit metadata, such as the output
+/// of `git diff`, is parsed as a single commit with no metadata lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Commit {
+    /// The lines preceding the first file diff: "commit <hash>", the author and date lines and
+    /// the commit message.
+    pub meta: Vec<String>,
+    pub files: Vec<FileDiff>,
+}
+
+/// The changes to a single file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileDiff {
+    /// The lines preceding the first hunk, e.g. "diff --git a/x b/x", "index ...", "--- a/x",
+    /// "+++ b/x". A file diff with no hunks holds other lines describing a file, such as "Binary
+    /// files a/x and b/x differ" or "Only in a: x".
+    pub meta: Vec<String>,
+    /// The path of the file before the change, or "/dev/null" if it was added.
+    pub minus_file: String,
+    /// The path of the file after the change, or "/dev/null" if it was removed.
+    pub plus_file: String,
+    pub hunks: Vec<Hunk>,
+}
+
+/// A hunk: a header line followed by a run of removed, added and unchanged lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hunk {
+    /// The header line, e.g. "@@ -74,15 +75,14 @@ pub fn delta(".
+    pub header: String,
+    /// The (start line, line count) range of the hunk in each parent, followed by its range in
+    /// the new file.
+    pub ranges: Vec<(usize, usize)>,
+    pub lines: Vec<Line>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineKind {
+    Minus, // Removed line
+    Plus,  // Added line
+    Zero,  // Unchanged line
+    Other, // Any other line in a hunk, e.g. "\ No newline at end of file"
+}
+
+/// A line of a hunk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub kind: LineKind,
+    /// The marker columns: one '-', '+' or ' ' character for each parent. Empty for a line of
+    /// kind Other.
+    pub markers: String,
+    /// The text of the line, following the marker columns.
+    pub text: String,
+}
+
+impl Hunk {
+    /// The number of parents: 1 for an ordinary diff, and more for a combined diff.
+    pub fn n_parents(&self) -> usize {
+        self.ranges.len().saturating_sub(1).max(1)
+    }
+
+    /// The text following the header ranges, typically the signature of the enclosing function.
+    pub fn code_fragment(&self) -> &str {
+        parse::parse_hunk_metadata(&self.header).0
+    }
+}
+
+/// Parse diff input into commits, file diffs, hunks and lines. The end of a hunk is determined by
+/// the line counts in its header, so that, for example, a removed line starting with "--" is not
+/// mistaken for file metadata. ANSI escape sequences in the input are ignored.
+pub fn parse<I, S>(lines: I) -> Diff
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut diff = Diff::default();
+    let mut commit = Commit::default();
+    let mut source = Source::Unknown;
+    // Lines remaining in the current hunk for each parent, and for the new file.
+    let mut hunk_lines_remaining: Vec<usize> = Vec::new();
+
+    for raw_line in lines {
+        let line = strip_ansi_codes(raw_line.as_ref()).to_string();
+        if source == Source::Unknown {
+            source = delta::detect_source(&line);
+        }
+
+        if let Some(hunk) = commit
+            .files
+            .last_mut()
+            .and_then(|file| file.hunks.last_mut())
+        {
+            // As in the main loop, the lines counted against the hunk's ranges are hunk lines,
+            // whatever their text; a line that cannot be counted ends the hunk.
+            if !hunk_lines_remaining.is_empty()
+                && delta::count_hunk_line(&line, hunk.n_parents(), &mut hunk_lines_remaining)
+            {
+                hunk.lines.push(parse_hunk_line(&line, hunk.n_parents()));
+                if hunk_lines_remaining.iter().all(|n| *n == 0) {
+                    hunk_lines_remaining.clear();
+                }
+                continue;
+            }
+            hunk_lines_remaining.clear();
+            if line.starts_with('\\') {
+                hunk.lines.push(Line {
+                    kind: LineKind::Other,
+                    markers: "".to_string(),
+                    text: line,
+                });
+                continue;
+            }
+        }
+
+        if line.starts_with("commit ") {
+            if !commit.meta.is_empty() || !commit.files.is_empty() {
+                diff.commits.push(commit);
+            }
+            commit = Commit::default();
+            commit.meta.push(line);
+        } else if line.starts_with("diff ") {
+            commit.files.push(FileDiff {
+                meta: vec![line],
+                ..FileDiff::default()
+            });
+        } else if line.starts_with("@@") && !commit.files.is_empty() {
+            let (_, ranges) = parse::parse_hunk_metadata(&line);
+            hunk_lines_remaining = delta::get_hunk_lines_remaining(&ranges);
+            let file = commit.files.last_mut().unwrap();
+            file.hunks.push(Hunk {
+                header: line,
+                ranges,
+                lines: Vec::new(),
+            });
+        } else {
+            // A unified diff that is not preceded by a "diff" line starts with "--- ".
+            let starts_file = match commit.files.last() {
+                Some(file) => !file.hunks.is_empty(),
+                None => line.starts_with("--- "),
+            };
+            if starts_file {
+                commit.files.push(FileDiff::default());
+            }
+            match commit.files.last_mut() {
+                Some(file) => {
+                    // The paths are read from the file metadata lines as the main loop reads
+                    // them.
+                    let extended_header = file
+                        .meta
+                        .first()
+                        .filter(|line| line.starts_with("diff --git "))
+                        .map(|line| parse::ExtendedHeader::new(line));
+                    let get_file_path = |line: &str| match &extended_header {
+                        Some(extended_header) => {
+                            extended_header.get_file_path_from_file_meta_line(line)
+                        }
+                        None => parse::get_file_path_from_file_meta_line(
+                            line,
+                            source.has_git_diff_names(),
+                        ),
+                    };
+                    if line.starts_with("--- ") || line.starts_with("rename from ") {
+                        file.minus_file = get_file_path(&line);
+                    } else if line.starts_with("+++ ") || line.starts_with("rename to ") {
+                        file.plus_file = get_file_path(&line);
+                    }
+                    file.meta.push(line);
+                }
+                None => commit.meta.push(line),
+            }
+        }
+    }
+    if !commit.meta.is_empty() || !commit.files.is_empty() {
+        diff.commits.push(commit);
+    }
+    diff
+}
+
+fn parse_hunk_line(line: &str, n_parents: usize) -> Line {
+    let kind = match combined_diff::get_hunk_line_marker(line, n_parents) {
+        Some('-') => LineKind::Minus,
+        Some('+') => LineKind::Plus,
+        // An empty line is an unchanged line whose trailing whitespace has been removed.
+        Some(' ') | None => LineKind::Zero,
+        _ => LineKind::Other,
+    };
+    let (markers, text) = match kind {
+        LineKind::Other => ("".to_string(), line.to_string()),
+        _ if line.chars().count() < n_parents => {
+            (format!("{:1$}", line, n_parents), "".to_string())
+        }
+        _ => {
+            let (markers, text) = combined_diff::split_markers(line, n_parents);
+            (markers.to_string(), text.to_string())
+        }
+    };
+    Line {
+        kind,
+        markers,
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_diff() {
+        let diff = parse(GIT_LOG.lines());
+        assert_eq!(diff.commits.len(), 2);
+
+        let commit = &diff.commits[0];
+        assert_eq!(
+            commit.meta[0],
+            "commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e"
+        );
+        assert_eq!(commit.files.len(), 2);
+
+        let file = &commit.files[0];
+        assert_eq!(file.minus_file, "src/align.rs");
+        assert_eq!(file.plus_file, "src/align.rs");
+        assert_eq!(file.meta.len(), 4);
+        assert_eq!(file.hunks.len(), 1);
+
+        let hunk = &file.hunks[0];
+        assert_eq!(hunk.ranges, vec![(1, 4), (1, 3)]);
+        assert_eq!(hunk.code_fragment(), " fn align()");
+        assert_eq!(
+            hunk.lines
+                .iter()
+                .map(|line| line.kind)
+                .collect::<Vec<LineKind>>(),
+            vec![
+                LineKind::Zero,
+                LineKind::Minus,
+                LineKind::Minus,
+                LineKind::Plus,
+                LineKind::Zero,
+                LineKind::Other,
+            ]
+        );
+        // A removed line starting with "--" is part of the hunk.
+        assert_eq!(hunk.lines[2].markers, "-");
+        assert_eq!(hunk.lines[2].text, "-- a comment");
+
+        let file = &commit.files[1];
+        assert_eq!(file.minus_file, "/dev/null");
+        assert_eq!(file.plus_file, "src/new.rs");
+        assert_eq!(file.hunks[0].lines.len(), 1);
+
+        let commit = &diff.commits[1];
+        assert_eq!(commit.files.len(), 1);
+        assert_eq!(commit.files[0].minus_file, "");
+        assert_eq!(commit.files[0].meta[2], "Binary files a/x and b/x differ");
+        assert!(commit.files[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let diff = parse(GIT_LOG.lines());
+        assert_eq!(to_lines(&diff), GIT_LOG.lines().collect::<Vec<&str>>());
+    }
+
+    /// Return the lines of a diff in the form in which they were parsed.
+    fn to_lines(diff: &Diff) -> Vec<String> {
+        let mut lines = Vec::new();
+        for commit in &diff.commits {
+            lines.extend(commit.meta.iter().cloned());
+            for file in &commit.files {
+                lines.extend(file.meta.iter().cloned());
+                for hunk in &file.hunks {
+                    lines.push(hunk.header.clone());
+                    lines.extend(
+                        hunk.lines
+                            .iter()
+                            .map(|line| format!("{}{}", line.markers, line.text)),
+                    );
+                }
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let diff = parse(DIFF_UNIFIED.lines());
+        assert_eq!(diff.commits.len(), 1);
+        let commit = &diff.commits[0];
+        assert!(commit.meta.is_empty());
+        assert_eq!(commit.files.len(), 2);
+        assert_eq!(commit.files[0].minus_file, "one.rs");
+        assert_eq!(commit.files[0].plus_file, "two.rs");
+        // The empty line is an unchanged line.
+        assert_eq!(commit.files[0].hunks[0].lines[2].kind, LineKind::Zero);
+        assert_eq!(commit.files[1].minus_file, "three.rs");
+    }
+
+    #[test]
+    fn test_parse_combined_diff() {
+        let diff = parse(COMBINED_DIFF.lines());
+        let hunk = &diff.commits[0].files[0].hunks[0];
+        assert_eq!(hunk.n_parents(), 2);
+        assert_eq!(
+            hunk.lines
+                .iter()
+                .map(|line| (line.kind, line.markers.as_str()))
+                .collect::<Vec<(LineKind, &str)>>(),
+            vec![
+                (LineKind::Minus, "- "),
+                (LineKind::Minus, " -"),
+                (LineKind::Plus, "++"),
+                (LineKind::Zero, "  "),
+            ]
+        );
+        assert_eq!(diff.commits[0].files.len(), 1);
+        assert_eq!(diff.commits[0].files[0].plus_file, "fruit.rs");
+    }
+
+    #[test]
+    fn test_parse_malformed_combined_diff_line() {
+        // The marker columns are characters: a multibyte character in place of a marker is not
+        // split.
+        let line = parse_hunk_line("+éa", 2);
+        assert_eq!(line.kind, LineKind::Plus);
+        assert_eq!(line.markers, "+é");
+        assert_eq!(line.text, "a");
+    }
+
+    const GIT_LOG: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 7 12:33:53 2019 -0500
+
+    Change things
+
+diff --git a/src/align.rs b/src/align.rs
+index 8e37a9e..6ce4863 100644
+--- a/src/align.rs
++++ b/src/align.rs
+@@ -1,4 +1,3 @@ fn align()
+ use std::cmp::max;
+-use std::collections::VecDeque;
+--- a comment
++use std::collections::HashMap;
+ const SUBSTITUTION_COST: usize = 1;
+\\ No newline at end of file
+diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..e69de29
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1 @@
++fn new() {}
+commit 6b2aeb0b8cb9f4cd4e1b9e4a8ed04a6eab36b9f3
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 7 12:35:02 2019 -0500
+
+    Add binary file
+
+diff --git a/x b/x
+index 3b18e51..a5e38c2 100644
+Binary files a/x and b/x differ";
+
+    const DIFF_UNIFIED: &str = "\
+--- one.rs	2019-11-20 06:16:08.000000000 +0100
++++ two.rs	2019-11-18 18:41:16.000000000 +0100
+@@ -5,3 +5,3 @@
+ println!(\"Hello world\");
+-println!(\"Hello rust\");
+
++println!(\"Hello ruster\");
+--- three.rs	2019-11-20 06:16:08.000000000 +0100
++++ four.rs	2019-11-18 18:41:16.000000000 +0100
+@@ -1 +1 @@
+-a
++b";
+
+    const COMBINED_DIFF: &str = "\
+diff --cc fruit.rs
+index 1111111,2222222..3333333
+--- a/fruit.rs
++++ b/fruit.rs
+@@@ -1,2 -1,2 +1,2 @@@
+- let banana = 1;
+ -let banana = 2;
+++let banana = 3;
+  let cherry = 4;";
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use console::strip_ansi_codes;
+
+use crate::combined_diff;
+use crate::delta;
+use crate::graph;
+use crate::parse;
+
+/// The minimum number of alphanumeric characters of the lines of a block of moved code. Smaller
+/// blocks, such as a lone closing brace, are too common to be taken for moved code (git's
+/// --color-moved uses the same minimum).
+const MIN_ALNUM_CHARS: usize = 20;
+
+/// A removed or added line of a diff that is part of a block of moved code.
+#[derive(Debug, PartialEq)]
+pub struct MovedLine {
+    /// The line (with its marker) at the other end of the move, if the line was changed by the
+    /// move. It is given the indentation of this line, so that a change of indentation is not
+    /// taken for an edit.
+    pub counterpart: Option<String>,
+}
+
+/// The removed and added lines of a diff that are moved code, by their index in the input.
+#[derive(Debug, Default)]
+pub struct MovedLines {
+    lines: HashMap<usize, MovedLine>,
+}
+
+/// A removed or added line of a hunk of the diff.
+struct ChangedLine {
+    index: usize,  // The index of the line in the input
+    line: String,  // The line, with a single marker in place of its graph prefix and markers
+    region: usize, // The run of removed and added lines between unchanged lines holding the line
+}
+
+impl ChangedLine {
+    /// The text of the line compared with other lines: indentation is not significant, since
+    /// moved code is often indented differently at its new location.
+    fn key(&self) -> &str {
+        self.line[1..].trim()
+    }
+}
+
+impl MovedLines {
+    /// Find the blocks of removed lines of a diff that are added elsewhere in the diff, in the same
+    /// file or in another. A block of removed lines and a block of added lines are moved code if
+    /// their lines are the same, except for indentation and for single changed lines, each
+    /// followed by a line that is the same. The lines removed and added in the same place are not
+    /// moved code: they are edited in place.
+    pub fn new(lines: &[String]) -> Self {
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| strip_ansi_codes(line).to_string())
+            .collect();
+        let (minus_lines, plus_lines) = get_changed_lines(&lines);
+        let mut plus_index: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (j, plus_line) in plus_lines.iter().enumerate() {
+            plus_index.entry(plus_line.key()).or_default().push(j);
+        }
+        let mut moved_lines = HashMap::new();
+        let mut is_moved = vec![false; plus_lines.len()];
+        let mut i = 0;
+        while i < minus_lines.len() {
+            // The longest block starting with this line, by its alphanumeric characters.
+            let mut best: Option<(usize, Vec<(usize, usize)>)> = None;
+            if count_alnum_chars(minus_lines[i].key()) > 0 {
+                for j in plus_index.get(minus_lines[i].key()).into_iter().flatten() {
+                    let (n_alnum_chars, pairs) =
+                        get_moved_block(&minus_lines, &plus_lines, i, *j, &is_moved);
+                    if !matches!(&best, Some((n, _)) if n_alnum_chars <= *n) {
+                        best = Some((n_alnum_chars, pairs));
+                    }
+                }
+            }
+            match best {
+                Some((n_alnum_chars, pairs)) if n_alnum_chars >= MIN_ALNUM_CHARS => {
+                    i += pairs.len();
+                    for (i, j) in pairs {
+                        let (minus_line, plus_line) = (&minus_lines[i], &plus_lines[j]);
+                        let is_changed = minus_line.key() != plus_line.key();
+                        let get_moved_line = |line: &ChangedLine, counterpart: &ChangedLine| {
+                            let indentation_len =
+                                line.line.len() - 1 - line.line[1..].trim_start().len();
+                            MovedLine {
+                                counterpart: Some(format!(
+                                    "{}{}{}",
+                                    &counterpart.line[..1],
+                                    &line.line[1..1 + indentation_len],
+                                    counterpart.line[1..].trim_start()
+                                ))
+                                .filter(|_| is_changed),
+                            }
+                        };
+                        moved_lines.insert(minus_line.index, get_moved_line(minus_line, plus_line));
+                        moved_lines.insert(plus_line.index, get_moved_line(plus_line, minus_line));
+                        is_moved[j] = true;
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        Self { lines: moved_lines }
+    }
+
+    /// Return the moved line with the given index in the input, if it is moved code.
+    pub fn get(&self, index: usize) -> Option<&MovedLine> {
+        self.lines.get(&index)
+    }
+}
+
+/// Return the removed and added lines of the hunks of a diff. The lines are read as the main loop
+/// reads them: without the graph prefix of `git log --graph` output, and, in a combined diff,
+/// with a single marker in place of the marker columns of the parents.
+fn get_changed_lines(lines: &[String]) -> (Vec<ChangedLine>, Vec<ChangedLine>) {
+    let (mut minus_lines, mut plus_lines) = (Vec::new(), Vec::new());
+    let mut hunk_lines_remaining = Vec::new();
+    let mut n_parents = 1;
+    let mut graph_width = None;
+    let mut region = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if hunk_lines_remaining.is_empty() {
+            if let Some(width) = graph::get_commit_line_graph_width(line) {
+                graph_width = Some(width);
+            }
+        }
+        let line = match graph_width {
+            Some(graph_width) => &line[graph::get_graph_prefix_width(line, graph_width)..],
+            None => line,
+        };
+        if !hunk_lines_remaining.is_empty()
+            && delta::count_hunk_line(line, n_parents, &mut hunk_lines_remaining)
+        {
+            let marker = combined_diff::get_hunk_line_marker(line, n_parents);
+            let (_, text) = combined_diff::split_markers(line, n_parents);
+            let changed_line = |marker: char| ChangedLine {
+                index,
+                line: format!("{}{}", marker, text),
+                region,
+            };
+            match marker {
+                Some('-') => minus_lines.push(changed_line('-')),
+                Some('+') => plus_lines.push(changed_line('+')),
+                _ => region += 1,
+            }
+            if hunk_lines_remaining.iter().all(|n| *n == 0) {
+                hunk_lines_remaining.clear();
+            }
+            continue;
+        }
+        hunk_lines_remaining.clear();
+        if line.starts_with("@@") {
+            let (_, ranges) = parse::parse_hunk_metadata(line);
+            if ranges.len() >= 2 {
+                n_parents = ranges.len() - 1;
+                hunk_lines_remaining = ranges.iter().map(|(_, count)| *count).collect();
+            }
+        }
+        region += 1;
+    }
+    (minus_lines, plus_lines)
+}
+
+/// Return the block of moved code starting with the removed line minus_lines[i] and the added line
+/// plus_lines[j], as the pairs of indices of its removed and added lines, with the number of
+/// alphanumeric characters of its lines that are the same.
+fn get_moved_block(
+    minus_lines: &[ChangedLine],
+    plus_lines: &[ChangedLine],
+    i: usize,
+    j: usize,
+    is_moved: &[bool],
+) -> (usize, Vec<(usize, usize)>) {
+    let is_in_block = |k: usize| {
+        let (minus_line, plus_line) = match (minus_lines.get(i + k), plus_lines.get(j + k)) {
+            (Some(minus_line), Some(plus_line)) => (minus_line, plus_line),
+            _ => return false,
+        };
+        // The lines of a block are consecutive lines of the input.
+        let is_consecutive = k == 0
+            || minus_line.index == minus_lines[i + k - 1].index + 1
+                && plus_line.index == plus_lines[j + k - 1].index + 1;
+        is_consecutive && !is_moved[j + k] && minus_line.region != plus_line.region
+    };
+    let is_same = |k: usize| minus_lines[i + k].key() == plus_lines[j + k].key();
+    let mut n_alnum_chars = 0;
+    let mut pairs = Vec::new();
+    let mut k = 0;
+    while is_in_block(k) {
+        if is_same(k) {
+            n_alnum_chars += count_alnum_chars(minus_lines[i + k].key());
+        } else if !(is_in_block(k + 1) && is_same(k + 1)) {
+            // A changed line must be followed by a line that is the same.
+            break;
+        }
+        pairs.push((i + k, j + k));
+        k += 1;
+    }
+    (n_alnum_chars, pairs)
+}
+
+fn count_alnum_chars(s: &str) -> usize {
+    s.chars().filter(|c| c.is_alphanumeric()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_moved_lines(input: &str) -> MovedLines {
+        let lines: Vec<String> = input.lines().map(String::from).collect();
+        MovedLines::new(&lines)
+    }
+
+    #[test]
+    fn test_moved_lines_across_files() {
+        let moved_lines = get_moved_lines(
+            "\
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1,7 +1,2 @@
+ fn a() {}
+-fn b() {
+-    call_something(1, 2);
+-    let result = other_thing(x, y);
+-}
+ fn d() {}
+-fn c() {}
+diff --git a/b.rs b/b.rs
+--- a/b.rs
++++ b/b.rs
+@@ -1,1 +1,6 @@
+ mod m {
++    fn b() {
++        call_something(1, 3);
++        let result = other_thing(x, y);
++    }
++fn c() {}
+",
+        );
+        // The block of fn b() is moved and reindented, with its second line changed.
+        assert_eq!(moved_lines.get(5), Some(&MovedLine { counterpart: None }));
+        assert_eq!(
+            moved_lines.get(6),
+            Some(&MovedLine {
+                counterpart: Some("+    call_something(1, 3);".to_string())
+            })
+        );
+        assert_eq!(
+            moved_lines.get(17),
+            Some(&MovedLine {
+                counterpart: Some("-        call_something(1, 2);".to_string())
+            })
+        );
+        assert!(moved_lines.get(8).is_some());
+        assert!(moved_lines.get(19).is_some());
+        // fn c() {} is too small a block to be taken for moved code.
+        assert!(moved_lines.get(10).is_none());
+        assert!(moved_lines.get(20).is_none());
+        // Unchanged lines are never moved.
+        assert!(moved_lines.get(4).is_none());
+    }
+
+    #[test]
+    fn test_lines_edited_in_place_are_not_moved() {
+        let moved_lines = get_moved_lines(
+            "\
+@@ -1,2 +1,2 @@
+-fn something_long_enough(x: usize) {
++fn something_long_enough(x: usize) {
+ }
+",
+        );
+        assert!(moved_lines.get(1).is_none());
+        assert!(moved_lines.get(2).is_none());
+    }
+
+    #[test]
+    fn test_moved_lines_in_git_log_graph_output() {
+        let moved_lines = get_moved_lines(
+            "\
+* commit 94907c0f1
+| diff --git a/a.rs b/a.rs
+| --- a/a.rs
+| +++ b/a.rs
+| @@ -1,3 +1,1 @@
+|  fn a() {}
+| -    call_something(1, 2);
+| -    let result = other_thing(x, y);
+| diff --git a/b.rs b/b.rs
+| --- a/b.rs
+| +++ b/b.rs
+| @@ -1,1 +1,3 @@
+|  mod m {
+| +    call_something(1, 2);
+| +    let result = other_thing(x, y);
+",
+        );
+        assert_eq!(moved_lines.get(6), Some(&MovedLine { counterpart: None }));
+        assert_eq!(moved_lines.get(7), Some(&MovedLine { counterpart: None }));
+        assert_eq!(moved_lines.get(13), Some(&MovedLine { counterpart: None }));
+        assert_eq!(moved_lines.get(14), Some(&MovedLine { counterpart: None }));
+        assert!(moved_lines.get(5).is_none());
+    }
+
+    #[test]
+    fn test_moved_lines_in_combined_diff() {
+        let moved_lines = get_moved_lines(
+            "\
+@@@ -1,5 -1,5 +1,5 @@@
+  fn a() {}
+--fn b(x: usize) -> Option<usize> {
+--    call_something(x, 2)
+--}
+  fn d() {}
+++fn b(x: usize) -> Option<usize> {
+++    call_something(x, 3)
+++}
+",
+        );
+        assert_eq!(moved_lines.get(2), Some(&MovedLine { counterpart: None }));
+        assert_eq!(
+            moved_lines.get(3),
+            Some(&MovedLine {
+                counterpart: Some("+    call_something(x, 3)".to_string())
+            })
+        );
+        assert_eq!(moved_lines.get(6), Some(&MovedLine { counterpart: None }));
+        assert!(moved_lines.get(1).is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// The change command starting a hunk of a normal diff, e.g. "5,7c5,8", "3a4" or "10d9".
+    static ref CHANGE_COMMAND_REGEX: Regex =
+        Regex::new(r"^(\d+)(?:,(\d+))?([acd])(\d+)(?:,(\d+))?$").unwrap();
+}
+
+/// The line separating the removed lines from the added lines of a change ("c") hunk.
+const SEPARATOR: &str = "---";
+
+/// A hunk of a normal diff (`diff` without -u or -c). The hunk starts with a change command, giving
+/// the lines of the old file that are changed ("c") or deleted ("d"), or after which lines are
+/// added ("a"), followed by the corresponding lines of the new file. The lines of the old file are
+/// marked with "< " and the lines of the new file with "> ".
+#[derive(Debug, Default, PartialEq)]
+pub struct NormalHunk {
+    pub old_range: (usize, usize), // (start, count)
+    pub new_range: (usize, usize),
+    pub lines: Vec<String>, // The lines following the change command
+}
+
+impl NormalHunk {
+    /// Start a hunk at its change command line. Return None if the line is not a change command.
+    pub fn new(line: &str) -> Option<Self> {
+        let captures = CHANGE_COMMAND_REGEX.captures(line)?;
+        let range = |start: usize, end: usize| -> Option<(usize, usize)> {
+            let start_line: usize = captures[start].parse().ok()?;
+            let count = match captures.get(end) {
+                Some(end_line) => {
+                    (end_line.as_str().parse::<usize>().ok()? + 1).saturating_sub(start_line)
+                }
+                None => 1,
+            };
+            Some((start_line, count))
+        };
+        let (mut old_range, mut new_range) = (range(1, 2)?, range(4, 5)?);
+        // Added lines follow the given line of the old file, and deleted lines follow the given
+        // line of the new file, as in the empty range of a unified diff hunk header.
+        match &captures[3] {
+            "a" => old_range.1 = 0,
+            "d" => new_range.1 = 0,
+            _ => {}
+        }
+        Some(Self {
+            old_range,
+            new_range,
+            lines: Vec::new(),
+        })
+    }
+
+    /// Add a line of the hunk, following the change command. Return false if the line is not part
+    /// of the hunk.
+    pub fn push(&mut self, line: &str) -> bool {
+        let is_hunk_line = line.starts_with('<')
+            || line.starts_with('>')
+            || line.starts_with('\\')
+            || line == SEPARATOR;
+        if is_hunk_line {
+            self.lines.push(line.to_string());
+        }
+        is_hunk_line
+    }
+
+    /// Return the header of the hunk in unified diff form, e.g. "@@ -5,3 +5,4 @@".
+    pub fn get_unified_header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_range.0, self.old_range.1, self.new_range.0, self.new_range.1
+        )
+    }
+
+    /// Return the lines of the hunk in unified diff form, with None for the separator line.
+    pub fn get_unified_lines(&self) -> Vec<Option<String>> {
+        self.lines
+            .iter()
+            .map(|line| match line.chars().next() {
+                Some('<') => Some(format!("-{}", line.get(2..).unwrap_or(""))),
+                Some('>') => Some(format!("+{}", line.get(2..).unwrap_or(""))),
+                // E.g. "\ No newline at end of file"
+                _ if line != SEPARATOR => Some(line.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Return true if the line is the change command starting a hunk of a normal diff.
+pub fn is_change_command(line: &str) -> bool {
+    CHANGE_COMMAND_REGEX.is_match(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let ranges = |line| NormalHunk::new(line).map(|hunk| (hunk.old_range, hunk.new_range));
+        assert_eq!(ranges("5,7c5,8"), Some(((5, 3), (5, 4))));
+        assert_eq!(ranges("3a4"), Some(((3, 0), (4, 1))));
+        assert_eq!(ranges("0a1,2"), Some(((0, 0), (1, 2))));
+        assert_eq!(ranges("10,11d9"), Some(((10, 2), (9, 0))));
+        assert_eq!(ranges("5,7x5"), None);
+        assert!(!is_change_command("diff a.txt b.txt"));
+    }
+
+    #[test]
+    fn test_get_unified_lines() {
+        let mut hunk = NormalHunk::new("2,3c2").unwrap();
+        for line in &["< a", "<", "---", "> b", "\\ No newline at end of file"] {
+            assert!(hunk.push(line));
+        }
+        assert!(!hunk.push("5d3"));
+        assert_eq!(hunk.get_unified_header(), "@@ -2,2 +2,1 @@");
+        assert_eq!(
+            hunk.get_unified_lines(),
+            vec![
+                Some("-a".to_string()),
+                Some("-".to_string()),
+                None,
+                Some("+b".to_string()),
+                Some("\\ No newline at end of file".to_string()),
+            ]
+        );
+    }
+}
@@ -3,18 +3,28 @@ use std::str::FromStr;
 
 use ansi_term;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Color, Style, StyleModifier};
+use syntect::highlighting::{Color, FontStyle, Style, StyleModifier};
 use syntect::parsing::{SyntaxReference, SyntaxSet};
 
+use crate::ansi::{ANSI_CSI_ERASE_IN_LINE, ANSI_SGR_RESET};
 use crate::bat::assets::HighlightingAssets;
 use crate::bat::terminal::to_ansi_color;
+use crate::color_moved;
+use crate::combined_diff;
 use crate::config;
 use crate::edits;
+use crate::line_numbers;
+use crate::merge_conflict::{self, MergeConflict};
 use crate::paint::superimpose_style_sections::superimpose_style_sections;
+use crate::side_by_side;
 use crate::style;
 
-const ANSI_CSI_ERASE_IN_LINE: &str = "\x1b[K";
-const ANSI_SGR_RESET: &str = "\x1b[0m";
+/// Annotated minus lines, annotated plus lines, and the alignment of homologous lines.
+pub type DiffStyleSections<'a> = (
+    Vec<Vec<(StyleModifier, &'a str)>>,
+    Vec<Vec<(StyleModifier, &'a str)>>,
+    Vec<(Option<usize>, Option<usize>)>,
+);
 
 pub struct Painter<'a> {
     pub minus_lines: Vec<String>,
@@ -24,6 +34,19 @@ pub struct Painter<'a> {
     pub highlighter: HighlightLines<'a>,
     pub config: &'a config::Config<'a>,
     pub output_buffer: String,
+    pub minus_line_number: usize,
+    pub plus_line_number: usize,
+    pub line_number_width: usize, // The width of the line number columns of the current hunk
+    pub n_parents: usize,
+    pub minus_markers: Vec<String>,
+    pub plus_markers: Vec<String>,
+    pub merge_conflict: MergeConflict,
+    pub word_diff: bool, // True if the current hunk is a hunk of word diff output
+    pub moved: bool,     // True if the buffered lines are moved code (see color_moved)
+    // For buffered moved lines, the line at the other end of the move if it was changed by the
+    // move (see moved::MovedLine)
+    pub minus_moved_counterparts: Vec<Option<String>>,
+    pub plus_moved_counterparts: Vec<Option<String>>,
 }
 
 impl<'a> Painter<'a> {
@@ -39,6 +62,17 @@ impl<'a> Painter<'a> {
             minus_lines: Vec::new(),
             plus_lines: Vec::new(),
             output_buffer: String::new(),
+            minus_line_number: 0,
+            plus_line_number: 0,
+            line_number_width: line_numbers::MIN_LINE_NUMBER_WIDTH,
+            n_parents: 1,
+            minus_markers: Vec::new(),
+            plus_markers: Vec::new(),
+            merge_conflict: MergeConflict::default(),
+            word_diff: false,
+            moved: false,
+            minus_moved_counterparts: Vec::new(),
+            plus_moved_counterparts: Vec::new(),
             syntax: default_syntax,
             highlighter: dummy_highlighter,
             writer,
@@ -50,6 +84,16 @@ impl<'a> Painter<'a> {
         self.syntax = Painter::get_syntax(self.config.syntax_set, extension);
     }
 
+    /// Set the syntax according to the first line of a file, e.g. a shebang line. Use plain text
+    /// if no syntax is recognized.
+    pub fn set_syntax_by_first_line(&mut self, line: &str) {
+        self.syntax = self
+            .config
+            .syntax_set
+            .find_syntax_by_first_line(line)
+            .unwrap_or_else(|| Painter::get_syntax(self.config.syntax_set, None));
+    }
+
     fn get_syntax(syntax_set: &'a SyntaxSet, extension: Option<&str>) -> &'a SyntaxReference {
         syntax_set
             .find_syntax_by_extension(extension.unwrap_or("txt"))
@@ -63,6 +107,9 @@ impl<'a> Painter<'a> {
     }
 
     pub fn paint_buffered_lines(&mut self) {
+        if self.merge_conflict.is_buffered() {
+            self.paint_merge_conflict();
+        }
         let (minus_line_syntax_style_sections, plus_line_syntax_style_sections) =
             Self::get_syntax_style_sections(
                 &self.minus_lines,
@@ -70,33 +117,211 @@ impl<'a> Painter<'a> {
                 &mut self.highlighter,
                 self.config,
             );
-        let (minus_line_diff_style_sections, plus_line_diff_style_sections) =
-            Self::get_diff_style_sections(&self.minus_lines, &self.plus_lines, self.config);
+        let (minus_style_modifier, plus_style_modifier) = if self.moved {
+            (
+                self.config.minus_moved_style_modifier,
+                self.config.plus_moved_style_modifier,
+            )
+        } else {
+            (
+                self.config.minus_style_modifier,
+                self.config.plus_style_modifier,
+            )
+        };
+        let (minus_line_diff_style_sections, plus_line_diff_style_sections, line_alignment) =
+            if self.moved {
+                color_moved::get_diff_style_sections(
+                    &self.minus_lines,
+                    &self.plus_lines,
+                    &self.minus_moved_counterparts,
+                    &self.plus_moved_counterparts,
+                    self.config,
+                )
+            } else {
+                Self::get_diff_style_sections(
+                    &self.minus_lines,
+                    &self.plus_lines,
+                    &self.minus_markers,
+                    &self.plus_markers,
+                    self.n_parents,
+                    self.config,
+                )
+            };
         // TODO: lines and style sections contain identical line text
-        if !self.minus_lines.is_empty() {
-            Painter::paint_lines(
-                minus_line_syntax_style_sections,
-                minus_line_diff_style_sections,
+        if self.n_parents > 1 {
+            // A combined diff: each line is painted with its own marker columns. The old line
+            // number is the line number in the first parent, which is advanced line by line in
+            // input order: the buffered removed lines precede the buffered added lines.
+            let minus_lines = minus_line_syntax_style_sections
+                .into_iter()
+                .zip(minus_line_diff_style_sections)
+                .zip(&self.minus_markers)
+                .map(|(sections, markers)| (sections, markers, '-', minus_style_modifier));
+            let plus_lines = plus_line_syntax_style_sections
+                .into_iter()
+                .zip(plus_line_diff_style_sections)
+                .zip(&self.plus_markers)
+                .map(|(sections, markers)| (sections, markers, ' ', plus_style_modifier));
+            let mut minus_line_number = self.minus_line_number;
+            let mut plus_line_number = self.plus_line_number;
+            for ((syntax_sections, diff_sections), markers, first_parent_marker, style_modifier) in
+                minus_lines.chain(plus_lines)
+            {
+                let in_first_parent = markers.starts_with(first_parent_marker);
+                let in_result = first_parent_marker == ' ';
+                Painter::paint_lines_with_line_numbers(
+                    vec![syntax_sections],
+                    vec![diff_sections],
+                    (
+                        Some(minus_line_number).filter(|_| in_first_parent),
+                        Some(plus_line_number).filter(|_| in_result),
+                        self.line_number_width,
+                    ),
+                    &mut self.output_buffer,
+                    self.config,
+                    &combined_diff::paint_markers(markers, self.config),
+                    style_modifier,
+                );
+                if in_first_parent {
+                    minus_line_number += 1;
+                }
+                if in_result {
+                    plus_line_number += 1;
+                }
+            }
+            self.minus_line_number = minus_line_number;
+        } else if self.config.side_by_side {
+            side_by_side::paint_minus_and_plus_lines_side_by_side(
+                (
+                    minus_line_syntax_style_sections,
+                    plus_line_syntax_style_sections,
+                ),
+                (
+                    minus_line_diff_style_sections,
+                    plus_line_diff_style_sections,
+                ),
+                line_alignment,
+                (
+                    self.minus_line_number,
+                    self.plus_line_number,
+                    self.line_number_width,
+                ),
+                (minus_style_modifier, plus_style_modifier),
                 &mut self.output_buffer,
                 self.config,
-                self.config.minus_line_marker,
-                self.config.minus_style_modifier,
+            );
+        } else {
+            if !self.minus_lines.is_empty() {
+                Painter::paint_lines_with_line_numbers(
+                    minus_line_syntax_style_sections,
+                    minus_line_diff_style_sections,
+                    (Some(self.minus_line_number), None, self.line_number_width),
+                    &mut self.output_buffer,
+                    self.config,
+                    self.config.minus_line_marker,
+                    minus_style_modifier,
+                );
+            }
+            if !self.plus_lines.is_empty() {
+                Painter::paint_lines_with_line_numbers(
+                    plus_line_syntax_style_sections,
+                    plus_line_diff_style_sections,
+                    (None, Some(self.plus_line_number), self.line_number_width),
+                    &mut self.output_buffer,
+                    self.config,
+                    self.config.plus_line_marker,
+                    plus_style_modifier,
+                );
+            }
+        }
+        if self.n_parents == 1 {
+            self.minus_line_number += self.minus_lines.len();
+        }
+        self.plus_line_number += self.plus_lines.len();
+        self.minus_lines.clear();
+        self.plus_lines.clear();
+        self.minus_markers.clear();
+        self.plus_markers.clear();
+        self.minus_moved_counterparts.clear();
+        self.plus_moved_counterparts.clear();
+    }
+
+    /// Paint the lines of the current merge conflict region.
+    fn paint_merge_conflict(&mut self) {
+        let (minus_line_number, plus_line_number) = merge_conflict::paint_merge_conflict(
+            &self.merge_conflict,
+            &mut self.highlighter,
+            (self.minus_line_number, self.plus_line_number),
+            self.line_number_width,
+            self.n_parents,
+            &mut self.output_buffer,
+            self.config,
+        );
+        self.minus_line_number = minus_line_number;
+        self.plus_line_number = plus_line_number;
+        // The region may continue after the lines painted so far.
+        self.merge_conflict.lines.clear();
+    }
+
+    /// Stop reading the current merge conflict region: its buffered lines become buffered added
+    /// lines, and the rest of the region is read as added lines.
+    pub fn abandon_merge_conflict(&mut self) {
+        for line in self.merge_conflict.lines.drain(..) {
+            if self.n_parents > 1 {
+                self.plus_markers.push(line.markers);
+            }
+            self.plus_lines.push(line.line);
+        }
+        self.merge_conflict.clear();
+    }
+
+    /// Paint lines as paint_lines does, preceding each line with a line number gutter if line
+    /// numbers are enabled. line_numbers holds the line numbers of the first line in the old and
+    /// new files, and the width of the line number columns; a number that is None is left blank
+    /// in the gutter.
+    pub fn paint_lines_with_line_numbers(
+        syntax_style_sections: Vec<Vec<(Style, &str)>>,
+        diff_style_sections: Vec<Vec<(StyleModifier, &str)>>,
+        line_numbers: (Option<usize>, Option<usize>, usize),
+        output_buffer: &mut String,
+        config: &config::Config,
+        prefix: &str,
+        background_style_modifier: StyleModifier,
+    ) {
+        if !config.line_numbers {
+            Painter::paint_lines(
+                syntax_style_sections,
+                diff_style_sections,
+                output_buffer,
+                config,
+                prefix,
+                background_style_modifier,
                 None,
             );
+            return;
         }
-        if !self.plus_lines.is_empty() {
+        let (minus_line_number, plus_line_number, line_number_width) = line_numbers;
+        for (i, (syntax_sections, diff_sections)) in syntax_style_sections
+            .into_iter()
+            .zip(diff_style_sections)
+            .enumerate()
+        {
+            output_buffer.push_str(&line_numbers::format_line_numbers(
+                minus_line_number.map(|n| n + i),
+                plus_line_number.map(|n| n + i),
+                line_number_width,
+                config,
+            ));
             Painter::paint_lines(
-                plus_line_syntax_style_sections,
-                plus_line_diff_style_sections,
-                &mut self.output_buffer,
-                self.config,
-                self.config.plus_line_marker,
-                self.config.plus_style_modifier,
+                vec![syntax_sections],
+                vec![diff_sections],
+                output_buffer,
+                config,
+                prefix,
+                background_style_modifier,
                 None,
             );
         }
-        self.minus_lines.clear();
-        self.plus_lines.clear();
     }
 
     /// Superimpose background styles and foreground syntax
@@ -116,7 +341,11 @@ impl<'a> Painter<'a> {
             syntax_style_sections.iter().zip(diff_style_sections.iter())
         {
             let mut ansi_strings = Vec::new();
-            if prefix != "" {
+            if prefix.contains('\x1b') {
+                // The prefix is painted already (e.g. the marker columns of a combined diff). Its
+                // escape sequences would confuse the style differences computed by ANSIStrings.
+                output_buffer.push_str(&background_ansi_style.paint(prefix).to_string());
+            } else if prefix != "" {
                 ansi_strings.push(background_ansi_style.paint(prefix));
             }
             let mut dropped_prefix = prefix == ""; // TODO: Hack
@@ -162,7 +391,7 @@ impl<'a> Painter<'a> {
     }
 
     /// Perform syntax highlighting for minus and plus lines in buffer.
-    fn get_syntax_style_sections<'m, 'p>(
+    pub fn get_syntax_style_sections<'m, 'p>(
         minus_lines: &'m [String],
         plus_lines: &'p [String],
         highlighter: &mut HighlightLines,
@@ -202,15 +431,57 @@ impl<'a> Painter<'a> {
         }
     }
 
-    /// Set background styles to represent diff for minus and plus lines in buffer.
+    /// Set background styles to represent diff for minus and plus lines in buffer, and return
+    /// the alignment of homologous lines. In a combined diff (n_parents > 1), the edits of each
+    /// line are inferred against the other lines that differ from the same parent, as determined
+    /// by the marker columns of the lines.
     fn get_diff_style_sections<'b>(
         minus_lines: &'b [String],
         plus_lines: &'b [String],
+        minus_markers: &[String],
+        plus_markers: &[String],
+        n_parents: usize,
         config: &config::Config,
-    ) -> (
-        Vec<Vec<(StyleModifier, &'b str)>>,
-        Vec<Vec<(StyleModifier, &'b str)>>,
-    ) {
+    ) -> DiffStyleSections<'b> {
+        if n_parents == 1 {
+            return Painter::infer_edits(minus_lines, plus_lines, config);
+        }
+        let mut minus_line_sections = vec![Vec::new(); minus_lines.len()];
+        let mut plus_line_sections = vec![Vec::new(); plus_lines.len()];
+        let mut line_alignment = Vec::new();
+        for (minus_indices, plus_indices) in
+            combined_diff::group_lines_by_parent(minus_markers, plus_markers, n_parents)
+        {
+            let parent_minus_lines: Vec<String> = minus_indices
+                .iter()
+                .map(|i| minus_lines[*i].clone())
+                .collect();
+            let parent_plus_lines: Vec<String> = plus_indices
+                .iter()
+                .map(|i| plus_lines[*i].clone())
+                .collect();
+            let (parent_minus_line_sections, parent_plus_line_sections, parent_line_alignment) =
+                Painter::infer_edits(&parent_minus_lines, &parent_plus_lines, config);
+            for (i, sections) in minus_indices.iter().zip(parent_minus_line_sections) {
+                minus_line_sections[*i] = rebase_style_sections(sections, &minus_lines[*i]);
+            }
+            for (i, sections) in plus_indices.iter().zip(parent_plus_line_sections) {
+                plus_line_sections[*i] = rebase_style_sections(sections, &plus_lines[*i]);
+            }
+            line_alignment.extend(
+                parent_line_alignment
+                    .into_iter()
+                    .map(|(m, p)| (m.map(|m| minus_indices[m]), p.map(|p| plus_indices[p]))),
+            );
+        }
+        (minus_line_sections, plus_line_sections, line_alignment)
+    }
+
+    fn infer_edits<'b>(
+        minus_lines: &'b [String],
+        plus_lines: &'b [String],
+        config: &config::Config,
+    ) -> DiffStyleSections<'b> {
         edits::infer_edits(
             minus_lines,
             plus_lines,
@@ -224,6 +495,23 @@ impl<'a> Painter<'a> {
     }
 }
 
+/// Return the style sections referencing the memory of line instead of that of a copy of it. The
+/// concatenation of the sections must equal line.
+fn rebase_style_sections<'b>(
+    sections: Vec<(StyleModifier, &str)>,
+    line: &'b str,
+) -> Vec<(StyleModifier, &'b str)> {
+    let mut offset = 0;
+    sections
+        .into_iter()
+        .map(|(style, text)| {
+            let section = &line[offset..offset + text.len()];
+            offset += text.len();
+            (style, section)
+        })
+        .collect()
+}
+
 pub fn to_ansi_style(style: Style, true_color: bool) -> ansi_term::Style {
     let mut ansi_style = ansi_term::Style::new();
     if style.background != style::NO_COLOR {
@@ -232,6 +520,15 @@ pub fn to_ansi_style(style: Style, true_color: bool) -> ansi_term::Style {
     if style.foreground != style::NO_COLOR {
         ansi_style = ansi_style.fg(to_ansi_color(style.foreground, true_color));
     }
+    if style.font_style.contains(FontStyle::BOLD) {
+        ansi_style = ansi_style.bold();
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        ansi_style = ansi_style.italic();
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        ansi_style = ansi_style.underline();
+    }
     ansi_style
 }
 
@@ -244,6 +541,17 @@ pub fn paint_text(text: &str, style: Style, output_buffer: &mut String, true_col
     output_buffer.push_str(&ansi_style.paint(text).to_string());
 }
 
+/// Return text together with shell escape codes specifying the style given by a style modifier.
+pub fn paint_text_with_style_modifier(
+    text: &str,
+    style_modifier: StyleModifier,
+    config: &config::Config,
+) -> String {
+    to_ansi_style(config.no_style.apply(style_modifier), config.true_color)
+        .paint(text)
+        .to_string()
+}
+
 /// Return text together with shell escape codes specifying the foreground color.
 pub fn paint_text_foreground(text: &str, color: Color, true_color: bool) -> String {
     to_ansi_color(color, true_color).paint(text).to_string()
@@ -285,7 +593,7 @@ pub fn color_from_ansi_number(n: u8) -> Option<Color> {
     Color::from_str(&format!("#{:02x}000000", n)).ok()
 }
 
-mod superimpose_style_sections {
+pub mod superimpose_style_sections {
     use syntect::highlighting::{Style, StyleModifier};
 
     pub fn superimpose_style_sections(
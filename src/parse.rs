@@ -10,30 +10,213 @@ pub fn get_file_extension_from_marker_line(line: &str) -> Option<&str> {
         .and_then(|file| file.split('.').last())
 }
 
+/// Return the file extension of the new file of a "diff" command line, e.g.
+/// "diff -r a/src/delta.rs b/src/delta.rs".
+pub fn get_file_extension_from_diff_line(line: &str) -> Option<&str> {
+    line.split_whitespace()
+        .last()
+        .and_then(get_file_extension_from_file_meta_line_file_path)
+}
+
+/// Return true if the line is the "diff" line of a Mercurial diff, giving the revision compared
+/// against, e.g. "diff -r 8a5b3c1d2e4f src/delta.rs".
+pub fn is_hg_diff_line(line: &str) -> bool {
+    let mut words = line.split(' ');
+    words.next() == Some("diff")
+        && words.next() == Some("-r")
+        && words
+            .next()
+            .map(|revision| revision.len() >= 12 && revision.chars().all(|c| c.is_ascii_hexdigit()))
+            .unwrap_or(false)
+}
+
+/// Return the file path of a file metadata line. If git_diff_name is true, the "a/" or "b/" prefix
+/// of a git (or Mercurial) diff path is removed. A timestamp or revision following the path, as in
+/// "--- src/delta.rs\t(revision 1234)", is removed. A file that Subversion marks as
+/// "(nonexistent)" is given as "/dev/null", as it is by git. A path quoted by git is unquoted.
 pub fn get_file_path_from_file_meta_line(line: &str, git_diff_name: bool) -> String {
     match line {
         line if line.starts_with("rename from ") => {
             let offset = "rename from ".len();
-            &line[offset..]
+            unquote_path(&line[offset..])
         }
         line if line.starts_with("rename to ") => {
             let offset = "rename to ".len();
-            &line[offset..]
+            unquote_path(&line[offset..])
         }
-        line if line.starts_with("--- ") || line.starts_with("+++ ") => {
+        line if line.starts_with("copy from ") => {
+            let offset = "copy from ".len();
+            unquote_path(&line[offset..])
+        }
+        line if line.starts_with("copy to ") => {
+            let offset = "copy to ".len();
+            unquote_path(&line[offset..])
+        }
+        line if line.starts_with("--- ")
+            || line.starts_with("+++ ")
+            || line.starts_with("*** ") =>
+        {
             let offset = 4;
-            match &line[offset..] {
-                path if path == "/dev/null" => "/dev/null",
+            let mut columns = line[offset..].splitn(2, '\t');
+            let path = unquote_path(columns.next().unwrap_or(""));
+            match path {
+                _ if columns.next() == Some("(nonexistent)") => "/dev/null".to_string(),
+                path if path == "/dev/null" => path,
                 path if git_diff_name && (path.starts_with("a/") || path.starts_with("b/")) => {
-                    &path[2..]
+                    path[2..].to_string()
                 }
-                path if git_diff_name => &path,
-                path => path.split('\t').next().unwrap_or(""),
+                path => path,
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+/// Return the old and new paths of a "diff --git" line, e.g. "a/src/delta.rs" and
+/// "b/src/delta.rs", unquoted. Return None if the paths cannot be told apart: that is the case if
+/// they contain spaces and are not quoted, unless they are the same but for their prefixes.
+pub fn get_file_paths_from_diff_git_line(line: &str) -> Option<(String, String)> {
+    let paths = line.strip_prefix("diff --git ")?;
+    let (minus_path, plus_path) = if paths.starts_with('"') {
+        let end = get_quoted_path_len(paths)?;
+        (&paths[..end], paths[end..].strip_prefix(' ')?)
+    } else if paths.ends_with('"') {
+        // A quoted path never holds a space followed by an (unescaped) quote.
+        let start = paths.rfind(" \"")?;
+        (&paths[..start], &paths[start + 1..])
+    } else if paths.matches(' ').count() == 1 {
+        let mut paths = paths.splitn(2, ' ');
+        (paths.next()?, paths.next()?)
+    } else {
+        let middle = paths.len() / 2;
+        let (minus_path, plus_path) = (paths.get(..middle)?, paths.get(middle + 1..)?);
+        // The paths may have different one-letter prefixes, e.g. "a/" and "b/".
+        let is_same_path = minus_path == plus_path
+            || minus_path.get(1..2) == Some("/")
+                && plus_path.get(1..2) == Some("/")
+                && minus_path.get(2..) == plus_path.get(2..);
+        if paths.len() % 2 == 0 || !is_same_path {
+            return None;
+        }
+        (minus_path, plus_path)
+    };
+    Some((unquote_path(minus_path), unquote_path(plus_path)))
+}
+
+/// The prefixes of the old paths of git diffs: "a/" by default, and with `diff.mnemonicPrefix`,
+/// "c/" (a commit), "i/" (the index) or "o/" (an object). "1/" is used by `git diff --no-index`.
+const MINUS_PATH_PREFIXES: [&str; 5] = ["a/", "c/", "i/", "o/", "1/"];
+
+/// The prefixes of the new paths of git diffs: "b/" by default, and with `diff.mnemonicPrefix`,
+/// "c/", "i/", "o/" or "w/" (the work tree). "2/" is used by `git diff --no-index`.
+const PLUS_PATH_PREFIXES: [&str; 6] = ["b/", "c/", "i/", "o/", "w/", "2/"];
+
+/// Return the prefixes of the old and new paths of a git diff, according to its "diff --git"
+/// line: e.g. "a/" and "b/", or "i/" and "w/" with `diff.mnemonicPrefix`. The paths have
+/// prefixes if they start with different one-letter directories, and are otherwise the same or
+/// start with known prefixes. Otherwise, as with `diff.noprefix`, the prefixes are empty. If the
+/// paths of the line cannot be told apart, the prefixes are taken to be "a/" and "b/".
+pub fn get_file_path_prefixes_from_diff_git_line(line: &str) -> (String, String) {
+    let (minus_path, plus_path) = match get_file_paths_from_diff_git_line(line) {
+        Some(paths) => paths,
+        None => return ("a/".to_string(), "b/".to_string()),
+    };
+    let get_prefix = |path: &str| {
+        path.get(..2)
+            .filter(|prefix| prefix.ends_with('/') && !prefix.starts_with('/'))
+            .map(str::to_string)
+    };
+    match (get_prefix(&minus_path), get_prefix(&plus_path)) {
+        (Some(minus_prefix), Some(plus_prefix))
+            if minus_prefix != plus_prefix
+                && (minus_path[2..] == plus_path[2..]
+                    || MINUS_PATH_PREFIXES.contains(&minus_prefix.as_str())
+                        && PLUS_PATH_PREFIXES.contains(&plus_prefix.as_str())) =>
+        {
+            (minus_prefix, plus_prefix)
+        }
+        _ => (String::new(), String::new()),
+    }
+}
+
+/// Return the file path of a file metadata line of a git diff. The path of a "---" or "+++" line
+/// loses the prefix of the old or new path (see get_file_path_prefixes_from_diff_git_line).
+pub fn get_file_path_from_git_file_meta_line(line: &str, prefixes: &(String, String)) -> String {
+    let path = get_file_path_from_file_meta_line(line, false);
+    let prefix = match line.get(..4) {
+        Some("--- ") => &prefixes.0,
+        Some("+++ ") => &prefixes.1,
+        _ => return path,
+    };
+    match path.strip_prefix(prefix.as_str()) {
+        Some(stripped) if path != "/dev/null" => stripped.to_string(),
+        _ => path,
+    }
+}
+
+/// Return the length of the quoted path starting a string, including its quotes.
+fn get_quoted_path_len(s: &str) -> Option<usize> {
+    let mut bytes = s.bytes().enumerate().skip(1);
+    while let Some((i, byte)) = bytes.next() {
+        match byte {
+            b'\\' => {
+                bytes.next();
             }
+            b'"' => return Some(i + 1),
+            _ => {}
         }
-        _ => "",
     }
-    .to_string()
+    None
+}
+
+/// Undo git's quoting of a path. Git quotes a path holding special or non-ASCII characters,
+/// escaping them as in a C string, e.g. "a/caf\303\251.rs" for a/café.rs: the octal escapes are
+/// the bytes of the path's UTF-8 encoding. A path that is not quoted is returned as it is.
+pub fn unquote_path(path: &str) -> String {
+    let quoted = match path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.as_bytes(),
+        None => return path.to_string(),
+    };
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut i = 0;
+    while i < quoted.len() {
+        if quoted[i] != b'\\' || i + 1 == quoted.len() {
+            bytes.push(quoted[i]);
+            i += 1;
+            continue;
+        }
+        let escaped = quoted[i + 1];
+        i += 2;
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0B,
+            b'f' => 0x0C,
+            b'r' => b'\r',
+            b'0'..=b'7' => {
+                // Up to three octal digits.
+                let mut byte = u32::from(escaped - b'0');
+                for _ in 0..2 {
+                    match quoted.get(i) {
+                        Some(digit @ b'0'..=b'7') => {
+                            byte = byte * 8 + u32::from(digit - b'0');
+                            i += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                byte as u8
+            }
+            // \" and \\, and any other escaped character, stand for the character itself.
+            escaped => escaped,
+        });
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
 pub fn get_file_extension_from_file_meta_line_file_path(path: &str) -> Option<&str> {
@@ -61,17 +244,189 @@ pub fn get_file_change_description_from_file_paths(
     }
 }
 
+/// The extended header lines of a git diff, following the "diff --git" line: the modes of the
+/// file, the similarity of a renamed or copied file, and the (abbreviated) hashes of the file's
+/// contents given by the "index" line.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExtendedHeader {
+    // The prefixes of the old and new paths, e.g. "a/" and "b/"
+    prefixes: (String, String),
+    // The path of the file, if both sides of the "diff --git" line name the same file
+    diff_line_path: Option<String>,
+    old_mode: Option<String>,
+    new_mode: Option<String>,
+    is_new_file: bool,
+    is_deleted_file: bool,
+    // The paths of a renamed or copied file
+    from_file: Option<String>,
+    to_file: Option<String>,
+    is_copy: bool,
+    similarity: Option<String>,
+    index: Option<String>,
+}
+
+impl ExtendedHeader {
+    /// Return the extended header of the file of a "diff --git" line.
+    pub fn new(diff_line: &str) -> Self {
+        let prefixes = get_file_path_prefixes_from_diff_git_line(diff_line);
+        let strip_prefix = |path: &str, prefix: &str| path.strip_prefix(prefix).map(str::to_string);
+        let diff_line_path = get_file_paths_from_diff_git_line(diff_line)
+            .and_then(|(minus_path, plus_path)| {
+                let minus_path = strip_prefix(&minus_path, &prefixes.0)?;
+                Some((minus_path, strip_prefix(&plus_path, &prefixes.1)?))
+            })
+            .filter(|(minus_path, plus_path)| minus_path == plus_path)
+            .map(|(path, _)| path);
+        Self {
+            prefixes,
+            diff_line_path,
+            ..Self::default()
+        }
+    }
+
+    /// Return the file path of a file metadata line of the diff, with the prefixes given by the
+    /// "diff --git" line removed.
+    pub fn get_file_path_from_file_meta_line(&self, line: &str) -> String {
+        get_file_path_from_git_file_meta_line(line, &self.prefixes)
+    }
+
+    /// Add a line to the header. Return false if the line is not an extended header line.
+    pub fn push(&mut self, line: &str) -> bool {
+        let value = |prefix: &str| line[prefix.len()..].to_string();
+        if line.starts_with("old mode ") {
+            self.old_mode = Some(value("old mode "));
+        } else if line.starts_with("new mode ") {
+            self.new_mode = Some(value("new mode "));
+        } else if line.starts_with("deleted file mode ") {
+            self.old_mode = Some(value("deleted file mode "));
+            self.is_deleted_file = true;
+        } else if line.starts_with("new file mode ") {
+            self.new_mode = Some(value("new file mode "));
+            self.is_new_file = true;
+        } else if line.starts_with("rename from ") || line.starts_with("copy from ") {
+            self.from_file = Some(get_file_path_from_file_meta_line(line, false));
+            self.is_copy = line.starts_with("copy ");
+        } else if line.starts_with("rename to ") || line.starts_with("copy to ") {
+            self.to_file = Some(get_file_path_from_file_meta_line(line, false));
+        } else if line.starts_with("similarity index ") {
+            self.similarity = Some(value("similarity index "));
+        } else if line.starts_with("dissimilarity index ") {
+            // A file rewritten rather than modified; the dissimilarity is not shown.
+        } else if line.starts_with("index ") {
+            self.index = line.split(' ').nth(1).map(String::from);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Return the description of the file change for the file header, given the paths of the
+    /// "---" and "+++" lines, e.g. "renamed (92%): a.rs ⟶   b.rs (mode 644 ⟶   755, index
+    /// 3d31e23..c171e6a)".
+    pub fn get_file_change_description(&self, minus_file: &str, plus_file: &str) -> String {
+        let description = match (&self.from_file, &self.to_file) {
+            (Some(from_file), Some(to_file)) => format!(
+                "{}{}: {} ⟶   {}",
+                if self.is_copy { "copied" } else { "renamed" },
+                // A file with a similarity of 100% is unchanged: it has no hunks.
+                self.similarity
+                    .as_ref()
+                    .filter(|similarity| *similarity != "100%")
+                    .map(|similarity| format!(" ({})", similarity))
+                    .unwrap_or_default(),
+                from_file,
+                to_file
+            ),
+            _ => get_file_change_description_from_file_paths(minus_file, plus_file, false),
+        };
+        let mut annotations = Vec::new();
+        match (&self.old_mode, &self.new_mode) {
+            (Some(old_mode), Some(new_mode)) => annotations.push(format!(
+                "mode {} ⟶   {}",
+                format_mode(old_mode),
+                format_mode(new_mode)
+            )),
+            // The mode of an added or deleted file is shown unless it is that of a regular file.
+            (Some(mode), None) | (None, Some(mode)) if mode != "100644" => {
+                annotations.push(format!("mode {}", format_mode(mode)))
+            }
+            _ => {}
+        }
+        if let Some(index) = &self.index {
+            // The hash of a missing side (that of an added or deleted file) is all zeros.
+            let hashes: Vec<&str> = index
+                .split("..")
+                .filter(|hash| !hash.chars().all(|c| c == '0' || c == ','))
+                .collect();
+            annotations.push(format!("index {}", hashes.join("..")));
+        }
+        if annotations.is_empty() {
+            description
+        } else {
+            format!("{} ({})", description, annotations.join(", "))
+        }
+    }
+
+    /// Return the description of the file change for the file header of a diff with no "---" and
+    /// "+++" lines, such as that of a renamed file with unchanged contents, or of a change of mode.
+    /// Return None if there is no change to describe.
+    pub fn get_file_change_description_without_hunks(&self) -> Option<String> {
+        let is_renamed_or_copied = self.from_file.is_some() && self.to_file.is_some();
+        if !(is_renamed_or_copied
+            || self.is_new_file
+            || self.is_deleted_file
+            || self.old_mode != self.new_mode)
+        {
+            return None;
+        }
+        let path = self.diff_line_path.as_deref().unwrap_or("");
+        let minus_file = if self.is_new_file { "/dev/null" } else { path };
+        let plus_file = if self.is_deleted_file {
+            "/dev/null"
+        } else {
+            path
+        };
+        Some(self.get_file_change_description(minus_file, plus_file))
+    }
+}
+
+/// Return a file mode of a git diff as it is shown, e.g. "755" for "100755".
+fn format_mode(mode: &str) -> &str {
+    match mode {
+        "120000" => "symlink",
+        "160000" => "submodule",
+        mode => mode.strip_prefix("100").unwrap_or(mode),
+    }
+}
+
 /// Given input like
-/// "@@ -74,15 +74,14 @@ pub fn delta("
-/// Return " pub fn delta("
-pub fn parse_hunk_metadata(line: &str) -> (&str, &str) {
-    let mut iter = line.split("@@").skip(1);
-    let line_number = iter
+/// "@@ -74,15 +75,14 @@ pub fn delta("
+/// Return (" pub fn delta(", [(74, 15), (75, 14)]): the code fragment, and the (start line,
+/// line count) range of the hunk in each file, the last being the range in the new file. A
+/// combined diff hunk header, such as "@@@ -74,15 -74,16 +75,14 @@@", has one range for each
+/// parent. An omitted line count is 1.
+pub fn parse_hunk_metadata(line: &str) -> (&str, Vec<(usize, usize)>) {
+    let marker_length = line.find(|c| c != '@').unwrap_or(line.len());
+    let mut iter = line.split(&line[..marker_length]).skip(1);
+    let ranges = iter
         .next()
-        .and_then(|s| s.split('+').nth(1).and_then(|s| s.split(',').next()))
-        .unwrap_or("");
+        .map(|s| {
+            s.split_whitespace()
+                .filter(|range| range.starts_with('-') || range.starts_with('+'))
+                .filter_map(|range| {
+                    let mut numbers = range[1..].split(',');
+                    let start = numbers.next()?.parse::<usize>().ok()?;
+                    let count = match numbers.next() {
+                        Some(count) => count.parse::<usize>().ok()?,
+                        None => 1,
+                    };
+                    Some((start, count))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
     let code_fragment = iter.next().unwrap_or("");
-    (code_fragment, line_number)
+    (code_fragment, ranges)
 }
 
 /// Attempt to parse input as a file path and return extension as a &str.
@@ -133,20 +488,22 @@ mod tests {
         );
     }
 
-    // We should only strip the prefixes if they are "a/" or "b/". This will be correct except for
-    // the case of a user with `diff.noprefix = true` who has directories named "a" or "b", which
-    // is an irresolvable ambiguity. Ideally one would only strip the prefixes if we have confirmed
-    // that we are looking at something like
-    //
-    // --- a/src/parse.rs
-    // +++ b/src/parse.rs
-    //
-    // as opposed to something like
-    //
-    // --- a/src/parse.rs
-    // +++ sibling_of_a/src/parse.rs
-    //
-    // but we don't attempt that currently.
+    // Without a "diff --git" line, the "a/" and "b/" prefixes are stripped if present. A git diff
+    // has its prefixes worked out from its "diff --git" line instead, so that a user with
+    // `diff.noprefix = true` who has directories named "a" or "b", or a user of
+    // `diff.mnemonicPrefix`, gets the right paths (see test_get_file_path_prefixes).
+    #[test]
+    fn test_get_file_extension_from_diff_line() {
+        assert_eq!(
+            get_file_extension_from_diff_line("diff -r a/src/delta.rs b/src/delta.rs"),
+            Some("rs")
+        );
+        assert_eq!(
+            get_file_extension_from_diff_line("diff -r a/Makefile b/Makefile"),
+            Some("Makefile")
+        );
+    }
+
     #[test]
     fn test_get_file_path_from_git_file_meta_line() {
         assert_eq!(
@@ -169,6 +526,14 @@ mod tests {
             get_file_path_from_file_meta_line("+++ src/delta.rs", true),
             "src/delta.rs"
         );
+        assert_eq!(
+            get_file_path_from_file_meta_line("--- \"a/caf\\303\\251.rs\"", true),
+            "café.rs"
+        );
+        assert_eq!(
+            get_file_path_from_file_meta_line("+++ \"b/src/tab\\there.rs\"", true),
+            "src/tab\there.rs"
+        );
     }
 
     #[test]
@@ -193,6 +558,10 @@ mod tests {
             get_file_path_from_file_meta_line("+++ b/my src/my enough/my delta.rs", true),
             "my src/my enough/my delta.rs"
         );
+        assert_eq!(
+            get_file_path_from_file_meta_line("+++ \"b/my src/my \\\"quoted\\\" delta.rs\"", true),
+            "my src/my \"quoted\" delta.rs"
+        );
     }
 
     #[test]
@@ -201,6 +570,13 @@ mod tests {
             get_file_path_from_file_meta_line("rename from nospace/file2.el", true),
             "nospace/file2.el"
         );
+        assert_eq!(
+            get_file_path_from_file_meta_line(
+                "rename to \"nospace/\\346\\227\\245\\346\\234\\254.el\"",
+                true
+            ),
+            "nospace/日本.el"
+        );
     }
 
     #[test]
@@ -209,6 +585,10 @@ mod tests {
             get_file_path_from_file_meta_line("rename from with space/file1.el", true),
             "with space/file1.el"
         );
+        assert_eq!(
+            get_file_path_from_file_meta_line("rename from \"with space/\\\\file1.el\"", true),
+            "with space/\\file1.el"
+        );
     }
 
     #[test]
@@ -223,11 +603,302 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_file_path_from_svn_file_meta_line() {
+        assert_eq!(
+            get_file_path_from_file_meta_line("--- src/delta.rs\t(revision 1234)", false),
+            "src/delta.rs"
+        );
+        assert_eq!(
+            get_file_path_from_file_meta_line("+++ src/delta.rs\t(working copy)", false),
+            "src/delta.rs"
+        );
+        assert_eq!(
+            get_file_path_from_file_meta_line("--- src/delta.rs\t(nonexistent)", false),
+            "/dev/null"
+        );
+    }
+
+    #[test]
+    fn test_get_file_path_from_hg_file_meta_line() {
+        assert_eq!(
+            get_file_path_from_file_meta_line(
+                "--- a/src/delta.rs\tThu Jan 01 00:00:00 1970 +0000",
+                true
+            ),
+            "src/delta.rs"
+        );
+        assert_eq!(
+            get_file_path_from_file_meta_line(
+                "--- /dev/null\tThu Jan 01 00:00:00 1970 +0000",
+                true
+            ),
+            "/dev/null"
+        );
+    }
+
+    #[test]
+    fn test_is_hg_diff_line() {
+        assert!(is_hg_diff_line("diff -r 8a5b3c1d2e4f src/delta.rs"));
+        assert!(is_hg_diff_line(
+            "diff -r 8a5b3c1d2e4f -r 0b4d6a5f1c3e src/delta.rs"
+        ));
+        assert!(!is_hg_diff_line("diff -r a/src/delta.rs b/src/delta.rs"));
+        assert!(!is_hg_diff_line("diff --git a/src/delta.rs b/src/delta.rs"));
+    }
+
+    #[test]
+    fn test_get_file_path_from_git_file_meta_line_copy() {
+        assert_eq!(
+            get_file_path_from_file_meta_line("copy to src/my delta.rs", true),
+            "src/my delta.rs"
+        );
+    }
+
+    #[test]
+    fn test_get_file_extension_from_quoted_file_meta_line() {
+        let path = get_file_path_from_file_meta_line("+++ \"b/src/caf\\303\\251.rs\"", true);
+        assert_eq!(
+            get_file_extension_from_file_meta_line_file_path(&path),
+            Some("rs")
+        );
+    }
+
+    #[test]
+    fn test_unquote_path() {
+        assert_eq!(unquote_path("src/delta.rs"), "src/delta.rs");
+        assert_eq!(unquote_path("\"a/caf\\303\\251.rs\""), "a/café.rs");
+        assert_eq!(
+            unquote_path("\"a/\\a\\b\\t\\n\\v\\f\\r\""),
+            "a/\x07\x08\t\n\x0B\x0C\r"
+        );
+        assert_eq!(unquote_path("\"a/\\\"\\\\\""), "a/\"\\");
+        assert_eq!(unquote_path("\"a/\\1x\""), "a/\x01x");
+        // Not a quoted path.
+        assert_eq!(unquote_path("\""), "\"");
+    }
+
+    #[test]
+    fn test_get_file_paths_from_diff_git_line() {
+        let paths = |a: &str, b: &str| Some((a.to_string(), b.to_string()));
+        assert_eq!(
+            get_file_paths_from_diff_git_line("diff --git a/src/a.rs b/src/b.rs"),
+            paths("a/src/a.rs", "b/src/b.rs")
+        );
+        assert_eq!(
+            get_file_paths_from_diff_git_line("diff --git a/my src/a.rs b/my src/a.rs"),
+            paths("a/my src/a.rs", "b/my src/a.rs")
+        );
+        assert_eq!(
+            get_file_paths_from_diff_git_line("diff --git a/my src/a.rs b/my src/b.rs"),
+            None
+        );
+        assert_eq!(
+            get_file_paths_from_diff_git_line(
+                "diff --git \"a/my caf\\303\\251.rs\" \"b/my caf\\303\\251.rs\""
+            ),
+            paths("a/my café.rs", "b/my café.rs")
+        );
+        assert_eq!(
+            get_file_paths_from_diff_git_line("diff --git a/my cafe.rs \"b/my caf\\303\\251.rs\""),
+            paths("a/my cafe.rs", "b/my café.rs")
+        );
+        assert_eq!(
+            get_file_paths_from_diff_git_line("diff --git \"a/\\\" b/\" b/x"),
+            paths("a/\" b/", "b/x")
+        );
+    }
+
+    #[test]
+    fn test_get_file_path_prefixes() {
+        let prefixes = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git a/src/a.rs b/src/a.rs"),
+            prefixes("a/", "b/")
+        );
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git i/src/a.rs w/src/a.rs"),
+            prefixes("i/", "w/")
+        );
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git c/src/a.rs i/src/b.rs"),
+            prefixes("c/", "i/")
+        );
+        // diff.noprefix
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git a/x.rs a/x.rs"),
+            prefixes("", "")
+        );
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git src/a.rs src/b.rs"),
+            prefixes("", "")
+        );
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git x/a.rs y/b.rs"),
+            prefixes("", "")
+        );
+        // The paths cannot be told apart.
+        assert_eq!(
+            get_file_path_prefixes_from_diff_git_line("diff --git a/my a.rs b/my b.rs"),
+            prefixes("a/", "b/")
+        );
+    }
+
+    #[test]
+    fn test_get_file_path_from_git_file_meta_line_with_prefixes() {
+        let get_file_path = |diff_line: &str, line: &str| {
+            get_file_path_from_git_file_meta_line(
+                line,
+                &get_file_path_prefixes_from_diff_git_line(diff_line),
+            )
+        };
+        assert_eq!(
+            get_file_path("diff --git i/src/a.rs w/src/a.rs", "--- i/src/a.rs"),
+            "src/a.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git i/src/a.rs w/src/a.rs", "+++ w/src/a.rs"),
+            "src/a.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git a/x.rs a/x.rs", "+++ a/x.rs"),
+            "a/x.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git b/x.rs b/x.rs", "--- b/x.rs"),
+            "b/x.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git a/x b/y.rs b/x b/y.rs", "--- a/x b/y.rs"),
+            "x b/y.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git a/x b/y.rs b/x b/y.rs", "+++ b/x b/y.rs"),
+            "x b/y.rs"
+        );
+        assert_eq!(
+            get_file_path("diff --git a/x.rs b/x.rs", "--- /dev/null"),
+            "/dev/null"
+        );
+        // Rename lines have no prefixes.
+        assert_eq!(
+            get_file_path("diff --git a/a/x.rs b/a/y.rs", "rename from a/x.rs"),
+            "a/x.rs"
+        );
+    }
+
+    #[test]
+    fn test_extended_header_without_prefixes() {
+        let header = get_extended_header(&[
+            "diff --git a/run a/run",
+            "old mode 100644",
+            "new mode 100755",
+        ]);
+        assert_eq!(
+            header.get_file_change_description_without_hunks(),
+            Some("a/run (mode 644 ⟶   755)".to_string())
+        );
+        assert_eq!(
+            header.get_file_path_from_file_meta_line("--- a/run"),
+            "a/run"
+        );
+    }
+
+    fn get_extended_header(lines: &[&str]) -> ExtendedHeader {
+        let mut header = ExtendedHeader::new(lines[0]);
+        for line in &lines[1..] {
+            assert!(header.push(line));
+        }
+        header
+    }
+
+    #[test]
+    fn test_extended_header_mode_change() {
+        let header = get_extended_header(&[
+            "diff --git a/my script.sh b/my script.sh",
+            "old mode 100644",
+            "new mode 100755",
+        ]);
+        assert!(!ExtendedHeader::default().push("--- a/my script.sh"));
+        assert_eq!(
+            header.get_file_change_description_without_hunks(),
+            Some("my script.sh (mode 644 ⟶   755)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_extended_header_rename() {
+        let header = get_extended_header(&[
+            "diff --git a/src/a.rs b/src/b.rs",
+            "similarity index 92%",
+            "rename from src/a.rs",
+            "rename to src/b.rs",
+            "index 3d31e23..c171e6a 100644",
+        ]);
+        assert_eq!(
+            header.get_file_change_description("src/a.rs", "src/b.rs"),
+            "renamed (92%): src/a.rs ⟶   src/b.rs (index 3d31e23..c171e6a)"
+        );
+    }
+
+    #[test]
+    fn test_extended_header_new_file() {
+        let header = get_extended_header(&[
+            "diff --git a/run b/run",
+            "new file mode 100755",
+            "index 0000000..e69de29",
+        ]);
+        assert_eq!(
+            header.get_file_change_description_without_hunks(),
+            Some("added: run (mode 755, index e69de29)".to_string())
+        );
+        let header = get_extended_header(&[
+            "diff --git a/a.rs b/a.rs",
+            "new file mode 100644",
+            "index 0000000..e69de29",
+        ]);
+        assert_eq!(
+            header.get_file_change_description("/dev/null", "a.rs"),
+            "added: a.rs (index e69de29)"
+        );
+    }
+
+    #[test]
+    fn test_extended_header_without_change_to_describe() {
+        let header =
+            get_extended_header(&["diff --git a/a.rs b/a.rs", "index 3d31e23..c171e6a 100644"]);
+        assert_eq!(header.get_file_change_description_without_hunks(), None);
+    }
+
     #[test]
     fn test_parse_hunk_metadata() {
         assert_eq!(
             parse_hunk_metadata("@@ -74,15 +75,14 @@ pub fn delta(\n"),
-            (" pub fn delta(\n", "75")
+            (" pub fn delta(\n", vec![(74, 15), (75, 14)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_metadata_combined_diff() {
+        assert_eq!(
+            parse_hunk_metadata("@@@ -74,15 -74,16 +75,14 @@@ pub fn delta(\n"),
+            (" pub fn delta(\n", vec![(74, 15), (74, 16), (75, 14)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_metadata_added_file() {
+        assert_eq!(
+            parse_hunk_metadata("@@ -0,0 +1,3 @@"),
+            ("", vec![(0, 0), (1, 3)])
+        );
+    }
+
+    #[test]
+    fn test_parse_hunk_metadata_single_line_ranges() {
+        assert_eq!(
+            parse_hunk_metadata("@@ -1 +1 @@"),
+            ("", vec![(1, 1), (1, 1)])
         );
     }
 }
@@ -0,0 +1,249 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::combined_diff;
+use crate::config::Config;
+use crate::delta;
+use crate::edits;
+use crate::paint::{self, Painter};
+use crate::parse;
+
+lazy_static! {
+    /// A line pairing a commit of the old range with a commit of the new range, e.g.
+    /// "1:  cb3fb2d ! 1:  49cf354 Change x", or "-:  ------- > 2:  f2c1741 Add f" for a commit of
+    /// the new range only. The marker is "=" for an unchanged commit, "!" for a changed commit,
+    /// and "<" or ">" for a commit of the old or new range only.
+    static ref COMMIT_PAIR_LINE_REGEX: Regex = Regex::new(
+        r"^ *(-|\d+): +(-+|[0-9a-f]+) ([=!<>]) +(-|\d+): +(-+|[0-9a-f]+) (.*)$"
+    )
+    .unwrap();
+}
+
+/// The indentation of the diff of a pair of commits, which follows the commit pair line.
+const INDENT: &str = "    ";
+
+/// Return true if the line is a commit pair line of `git range-diff` output.
+pub fn is_commit_pair_line(line: &str) -> bool {
+    COMMIT_PAIR_LINE_REGEX.is_match(line)
+}
+
+/// Return the text of the header shown for a commit pair line: the commits of the old and new
+/// ranges ("-" for a missing commit), separated by the marker, and the subject.
+pub fn get_commit_pair_header(line: &str) -> Option<String> {
+    let captures = COMMIT_PAIR_LINE_REGEX.captures(line)?;
+    let commit = |number: &str, hash: &str| match number {
+        "-" => "-".to_string(),
+        _ => format!("{}: {}", number, hash),
+    };
+    Some(format!(
+        "{} {} {} {}",
+        commit(&captures[1], &captures[2]),
+        &captures[3],
+        commit(&captures[4], &captures[5]),
+        &captures[6]
+    ))
+}
+
+/// The removed and added lines of the diff of a pair of commits that have not yet been painted.
+/// The diff is a diff of diffs: each line has an outer marker column, comparing the patches of the
+/// two commits, followed by the line of a patch, which has its own (inner) marker column. A line is
+/// painted with the background of its outer marker; its inner marker is painted as the marker
+/// columns of a combined diff are, and the rest of the line is syntax highlighted.
+#[derive(Default)]
+pub struct RangeDiff {
+    minus_lines: Vec<String>,
+    minus_markers: Vec<String>,
+    plus_lines: Vec<String>,
+    plus_markers: Vec<String>,
+}
+
+impl RangeDiff {
+    /// Handle a line of the diff of a pair of commits. Return false if the line is not part of
+    /// such a diff.
+    pub fn push(&mut self, painter: &mut Painter, line: &str) -> bool {
+        let rest = match line.strip_prefix(INDENT) {
+            Some(rest) => rest,
+            None => return false,
+        };
+        let mut chars = rest.chars();
+        let outer_marker = match chars.next() {
+            Some(c) => c,
+            None => return false,
+        };
+        if outer_marker == '@' {
+            // The header of a hunk of the diff of diffs, e.g. "@@ a.rs: fn main() {", naming the
+            // file whose patches are compared.
+            self.paint_buffered_lines(painter);
+            if let Some(path) = rest.get(3..) {
+                set_syntax(painter, path.split(": ").next().unwrap_or(path));
+            }
+            painter.output_buffer.push_str(INDENT);
+            painter
+                .output_buffer
+                .push_str(&paint::paint_text_foreground(
+                    rest,
+                    painter.config.hunk_color,
+                    painter.config.true_color,
+                ));
+            painter.output_buffer.push('\n');
+            return true;
+        }
+        if outer_marker != ' ' && outer_marker != '-' && outer_marker != '+' {
+            return false;
+        }
+        // A line of the patches with no inner marker is e.g. a section header such as
+        // "## Commit message ##", or a line of the commit metadata.
+        let n_markers = match chars.next() {
+            Some(' ') | Some('-') | Some('+') => 2,
+            _ => 1,
+        };
+        if n_markers == 1 {
+            if let Some(section) = rest[1..]
+                .strip_prefix("## ")
+                .and_then(|s| s.strip_suffix(" ##"))
+            {
+                self.paint_buffered_lines(painter);
+                set_syntax(painter, section);
+            }
+        }
+        let markers = rest[..n_markers].to_string();
+        let prepared = delta::prepare(rest, n_markers, true, painter.config);
+        match outer_marker {
+            '-' => {
+                if !self.plus_lines.is_empty() {
+                    self.paint_buffered_lines(painter);
+                }
+                self.minus_markers.push(markers);
+                self.minus_lines.push(prepared);
+            }
+            '+' => {
+                self.plus_markers.push(markers);
+                self.plus_lines.push(prepared);
+            }
+            _ => {
+                self.paint_buffered_lines(painter);
+                let config = painter.config;
+                let syntax_style_sections = Painter::get_line_syntax_style_sections(
+                    &prepared,
+                    &mut painter.highlighter,
+                    config,
+                    true,
+                );
+                Painter::paint_lines_with_line_numbers(
+                    vec![syntax_style_sections],
+                    vec![vec![(config.zero_style_modifier, &prepared)]],
+                    (None, None, painter.line_number_width),
+                    &mut painter.output_buffer,
+                    config,
+                    &get_prefix(&markers, config),
+                    config.zero_style_modifier,
+                );
+            }
+        }
+        true
+    }
+
+    /// Paint the buffered removed and added lines, with the edits between them emphasized.
+    pub fn paint_buffered_lines(&mut self, painter: &mut Painter) {
+        let config = painter.config;
+        let (minus_line_syntax_style_sections, plus_line_syntax_style_sections) =
+            Painter::get_syntax_style_sections(
+                &self.minus_lines,
+                &self.plus_lines,
+                &mut painter.highlighter,
+                config,
+            );
+        let (minus_line_diff_style_sections, plus_line_diff_style_sections, _) = edits::infer_edits(
+            &self.minus_lines,
+            &self.plus_lines,
+            config.minus_style_modifier,
+            config.minus_emph_style_modifier,
+            config.plus_style_modifier,
+            config.plus_emph_style_modifier,
+            config.max_line_distance,
+            config.max_line_distance_for_naively_paired_lines,
+        );
+        for (syntax_style_sections, diff_style_sections, markers, background_style_modifier) in
+            minus_line_syntax_style_sections
+                .into_iter()
+                .zip(minus_line_diff_style_sections)
+                .zip(&self.minus_markers)
+                .map(|((syntax, diff), markers)| {
+                    (syntax, diff, markers, config.minus_style_modifier)
+                })
+                .chain(
+                    plus_line_syntax_style_sections
+                        .into_iter()
+                        .zip(plus_line_diff_style_sections)
+                        .zip(&self.plus_markers)
+                        .map(|((syntax, diff), markers)| {
+                            (syntax, diff, markers, config.plus_style_modifier)
+                        }),
+                )
+        {
+            Painter::paint_lines_with_line_numbers(
+                vec![syntax_style_sections],
+                vec![diff_style_sections],
+                (None, None, painter.line_number_width),
+                &mut painter.output_buffer,
+                config,
+                &get_prefix(markers, config),
+                background_style_modifier,
+            );
+        }
+        self.minus_lines.clear();
+        self.minus_markers.clear();
+        self.plus_lines.clear();
+        self.plus_markers.clear();
+    }
+}
+
+/// Return the indentation and the painted marker columns of a line of the diff of diffs.
+fn get_prefix(markers: &str, config: &Config) -> String {
+    format!(
+        "{}{}",
+        INDENT,
+        combined_diff::paint_markers(markers, config)
+    )
+}
+
+/// Set the syntax for the lines following a section header or hunk header of the diff of diffs,
+/// according to the file named by the header, e.g. "a.rs", "c.txt (new)" or "a.rs => b.rs". The
+/// commit metadata and message sections are plain text.
+fn set_syntax(painter: &mut Painter, header: &str) {
+    let path = match header.rfind(" (") {
+        Some(i) if header.ends_with(')') => &header[..i],
+        _ => header,
+    };
+    let path = path.rsplit(" => ").next().unwrap_or(path);
+    painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+        path,
+    ));
+    painter.set_highlighter();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_commit_pair_line() {
+        assert!(is_commit_pair_line("1:  cb3fb2d ! 1:  49cf354 Change x"));
+        assert!(is_commit_pair_line("-:  ------- > 2:  f2c1741 Add f"));
+        assert!(is_commit_pair_line(" 9:  67a3b12 = 10:  f2c1741 Add f"));
+        assert!(!is_commit_pair_line("1:  cb3fb2d ? 1:  49cf354 Change x"));
+        assert!(!is_commit_pair_line("-:  ------- Add f"));
+    }
+
+    #[test]
+    fn test_get_commit_pair_header() {
+        assert_eq!(
+            get_commit_pair_header("1:  cb3fb2d ! 1:  49cf354 Change x"),
+            Some("1: cb3fb2d ! 1: 49cf354 Change x".to_string())
+        );
+        assert_eq!(
+            get_commit_pair_header("3:  9dea7eb < -:  ------- Add c"),
+            Some("3: 9dea7eb < - Add c".to_string())
+        );
+    }
+}
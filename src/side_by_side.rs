@@ -0,0 +1,254 @@
+use syntect::highlighting::{Style, StyleModifier};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Config;
+use crate::line_numbers;
+use crate::paint::superimpose_style_sections::superimpose_style_sections;
+use crate::paint::to_ansi_style;
+
+/// Written at the right edge of a panel when a line is too long to fit.
+const TRUNCATION_SYMBOL: &str = "→";
+
+type SyntaxStyleSections<'a> = Vec<Vec<(Style, &'a str)>>;
+type DiffStyleSections<'a> = Vec<Vec<(StyleModifier, &'a str)>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanelSide {
+    Left,
+    Right,
+}
+
+/// Paint minus lines in the left column and plus lines in the right column. Lines that were
+/// inferred to be homologous are written on the same row; a line with no homolog is written
+/// opposite an empty cell. The style sections are (minus, plus) pairs; line_numbers holds the
+/// line numbers of the first minus line and the first plus line, and the width of the line number
+/// columns; background_style_modifiers holds the backgrounds of the minus and plus lines.
+pub fn paint_minus_and_plus_lines_side_by_side(
+    syntax_style_sections: (SyntaxStyleSections, SyntaxStyleSections),
+    diff_style_sections: (DiffStyleSections, DiffStyleSections),
+    line_alignment: Vec<(Option<usize>, Option<usize>)>,
+    line_numbers: (usize, usize, usize),
+    background_style_modifiers: (StyleModifier, StyleModifier),
+    output_buffer: &mut String,
+    config: &Config,
+) {
+    let (minus_style_modifier, plus_style_modifier) = background_style_modifiers;
+    let (minus_syntax_style_sections, plus_syntax_style_sections) = syntax_style_sections;
+    let (minus_diff_style_sections, plus_diff_style_sections) = diff_style_sections;
+    let (minus_line_number, plus_line_number, line_number_width) = line_numbers;
+    for (minus_index, plus_index) in line_alignment {
+        output_buffer.push_str(&match minus_index {
+            Some(i) => paint_panel_line(
+                &minus_syntax_style_sections[i],
+                &minus_diff_style_sections[i],
+                &get_line_number_gutter(
+                    minus_line_number + i,
+                    config.line_numbers_minus_style_modifier,
+                    line_number_width,
+                    config,
+                ),
+                config.minus_line_marker,
+                minus_style_modifier,
+                PanelSide::Left,
+                config,
+            ),
+            None => paint_empty_panel(PanelSide::Left, config),
+        });
+        output_buffer.push_str(&match plus_index {
+            Some(i) => paint_panel_line(
+                &plus_syntax_style_sections[i],
+                &plus_diff_style_sections[i],
+                &get_line_number_gutter(
+                    plus_line_number + i,
+                    config.line_numbers_plus_style_modifier,
+                    line_number_width,
+                    config,
+                ),
+                config.plus_line_marker,
+                plus_style_modifier,
+                PanelSide::Right,
+                config,
+            ),
+            None => paint_empty_panel(PanelSide::Right, config),
+        });
+        output_buffer.push('\n');
+    }
+}
+
+/// Paint an unchanged line in both columns. line_numbers holds its line numbers in the old and
+/// new files, and the width of the line number columns.
+pub fn paint_zero_line_side_by_side(
+    syntax_style_sections: &[(Style, &str)],
+    diff_style_sections: &[(StyleModifier, &str)],
+    prefix: &str,
+    line_numbers: (usize, usize, usize),
+    output_buffer: &mut String,
+    config: &Config,
+) {
+    let (minus_line_number, plus_line_number, line_number_width) = line_numbers;
+    for (side, line_number) in &[
+        (PanelSide::Left, minus_line_number),
+        (PanelSide::Right, plus_line_number),
+    ] {
+        output_buffer.push_str(&paint_panel_line(
+            syntax_style_sections,
+            diff_style_sections,
+            &get_line_number_gutter(
+                *line_number,
+                config.line_numbers_zero_style_modifier,
+                line_number_width,
+                config,
+            ),
+            prefix,
+            config.zero_style_modifier,
+            *side,
+            config,
+        ));
+    }
+    output_buffer.push('\n');
+}
+
+/// Paint an added line in the right column, opposite an empty cell, with its own background.
+/// line_numbers holds its line number in the new file, and the width of the line number columns.
+pub fn paint_plus_line_side_by_side(
+    syntax_style_sections: &[(Style, &str)],
+    diff_style_sections: &[(StyleModifier, &str)],
+    prefix: &str,
+    line_numbers: (usize, usize),
+    background_style_modifier: StyleModifier,
+    output_buffer: &mut String,
+    config: &Config,
+) {
+    let (plus_line_number, line_number_width) = line_numbers;
+    output_buffer.push_str(&paint_empty_panel(PanelSide::Left, config));
+    output_buffer.push_str(&paint_panel_line(
+        syntax_style_sections,
+        diff_style_sections,
+        &get_line_number_gutter(
+            plus_line_number,
+            config.line_numbers_plus_style_modifier,
+            line_number_width,
+            config,
+        ),
+        prefix,
+        background_style_modifier,
+        PanelSide::Right,
+        config,
+    ));
+    output_buffer.push('\n');
+}
+
+/// The left panel takes half of the terminal width; the right panel takes the rest.
+pub fn get_panel_width(side: PanelSide, config: &Config) -> usize {
+    match side {
+        PanelSide::Left => config.terminal_width / 2,
+        PanelSide::Right => config.terminal_width - config.terminal_width / 2,
+    }
+}
+
+fn get_line_number_gutter(
+    line_number: usize,
+    style_modifier: StyleModifier,
+    line_number_width: usize,
+    config: &Config,
+) -> String {
+    if config.line_numbers {
+        line_numbers::format_line_number_panel(
+            Some(line_number),
+            style_modifier,
+            line_number_width,
+            config,
+        )
+    } else {
+        "".to_string()
+    }
+}
+
+/// Return the painted text of one cell, truncated or padded to the width of its panel. The cell
+/// starts with the line number gutter, if any.
+fn paint_panel_line(
+    syntax_sections: &[(Style, &str)],
+    diff_sections: &[(StyleModifier, &str)],
+    gutter: &str,
+    prefix: &str,
+    background_style_modifier: StyleModifier,
+    side: PanelSide,
+    config: &Config,
+) -> String {
+    let panel_width = get_panel_width(side, config);
+    let background_style = config.no_style.apply(background_style_modifier);
+    let background_ansi_style = to_ansi_style(background_style, config.true_color);
+
+    let mut ansi_strings = Vec::new();
+    let mut width = UnicodeWidthStr::width(prefix);
+    width += console::measure_text_width(gutter);
+    if !prefix.is_empty() {
+        ansi_strings.push(background_ansi_style.paint(prefix.to_string()));
+    }
+    let mut dropped_prefix = prefix.is_empty();
+    for (style, mut text) in superimpose_style_sections(syntax_sections, diff_sections) {
+        if !dropped_prefix {
+            if !text.is_empty() {
+                text.remove(0);
+            }
+            dropped_prefix = true;
+        }
+        let text_width = UnicodeWidthStr::width(text.as_str());
+        if width + text_width > panel_width {
+            let truncated = truncate(&text, panel_width.saturating_sub(width + 1));
+            width += UnicodeWidthStr::width(truncated.as_str()) + 1;
+            let ansi_style = to_ansi_style(style, config.true_color);
+            ansi_strings.push(ansi_style.paint(truncated));
+            ansi_strings.push(ansi_style.paint(TRUNCATION_SYMBOL.to_string()));
+            break;
+        }
+        width += text_width;
+        ansi_strings.push(to_ansi_style(style, config.true_color).paint(text));
+    }
+    if width < panel_width {
+        let padding = " ".repeat(panel_width - width);
+        if config.background_color_extends_to_terminal_width {
+            ansi_strings.push(background_ansi_style.paint(padding));
+        } else if side == PanelSide::Left {
+            ansi_strings.push(ansi_term::Style::new().paint(padding));
+        }
+    }
+    format!("{}{}", gutter, ansi_term::ANSIStrings(&ansi_strings))
+}
+
+/// Return the text of a cell with no line. In the left panel it is filled with spaces so that
+/// the right panel stays aligned.
+fn paint_empty_panel(side: PanelSide, config: &Config) -> String {
+    match side {
+        PanelSide::Left => " ".repeat(get_panel_width(side, config)),
+        PanelSide::Right => "".to_string(),
+    }
+}
+
+/// Return the longest prefix of text whose display width does not exceed width.
+fn truncate(text: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for grapheme in text.graphemes(true) {
+        truncated_width += UnicodeWidthStr::width(grapheme);
+        if truncated_width > width {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abcdef", 3), "abc");
+        assert_eq!(truncate("abc", 5), "abc");
+        assert_eq!(truncate("áéíóú", 2), "áé");
+        assert_eq!(truncate("中文字", 3), "中");
+    }
+}
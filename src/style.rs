@@ -1,5 +1,10 @@
+use std::str::FromStr;
+
 use syntect::highlighting::{Color, FontStyle, Style, StyleModifier};
 
+use crate::cli;
+use crate::paint;
+
 pub const LIGHT_THEMES: [&str; 5] = [
     "GitHub",
     "Monokai Extended Light",
@@ -19,167 +24,276 @@ pub fn is_no_syntax_highlighting_theme_name(theme_name: &str) -> bool {
     theme_name.to_lowercase() == "none"
 }
 
-pub fn get_minus_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_MINUS_COLOR,
-        (true, false) => LIGHT_THEME_MINUS_COLOR_256,
-        (false, true) => DARK_THEME_MINUS_COLOR,
-        (false, false) => DARK_THEME_MINUS_COLOR_256,
-    }
+/// The default style strings of the elements of the output (see StyleSpec).
+pub struct DefaultStyles {
+    pub minus: String,
+    pub minus_emph: String,
+    pub plus: String,
+    pub plus_emph: String,
+    pub minus_moved: String,
+    pub plus_moved: String,
+    pub zero: String,
+    pub commit: String,
+    pub file: String,
+    pub hunk_header: String,
+    pub line_numbers_minus: String,
+    pub line_numbers_plus: String,
+    pub line_numbers_zero: String,
 }
 
-pub fn get_minus_emph_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_MINUS_EMPH_COLOR,
-        (true, false) => LIGHT_THEME_MINUS_EMPH_COLOR_256,
-        (false, true) => DARK_THEME_MINUS_EMPH_COLOR,
-        (false, false) => DARK_THEME_MINUS_EMPH_COLOR_256,
+/// Return the default style strings of the elements of the output. The background colors of the
+/// lines of a diff are chosen for a light or a dark terminal background, as RGB hex codes if the
+/// terminal supports 24-bit color and as ANSI color numbers otherwise. Removed lines are not
+/// syntax highlighted, unless highlight_removed.
+pub fn get_default_styles(
+    is_light_mode: bool,
+    is_true_color: bool,
+    highlight_removed: bool,
+) -> DefaultStyles {
+    // The background colors of removed, emphasized removed, added, emphasized added, moved removed
+    // and moved added lines.
+    let backgrounds = match (is_light_mode, is_true_color) {
+        (true, true) => [
+            "#ffe0e0", "#ffc0c0", "#d0ffd0", "#a0efa0", "#f5e0ff", "#d8f4ff",
+        ],
+        (true, false) => ["224", "217", "194", "157", "225", "195"],
+        (false, true) => [
+            "#3f0001", "#901011", "#002800", "#006000", "#3a003f", "#002f3f",
+        ],
+        (false, false) => ["52", "124", "22", "28", "53", "23"],
+    };
+    let minus_foreground = if highlight_removed {
+        "syntax"
+    } else {
+        "normal"
+    };
+    let minus_style = |background: &str| format!("{} {}", minus_foreground, background);
+    let plus_style = |background: &str| format!("syntax {}", background);
+    DefaultStyles {
+        minus: minus_style(backgrounds[0]),
+        minus_emph: minus_style(backgrounds[1]),
+        plus: plus_style(backgrounds[2]),
+        plus_emph: plus_style(backgrounds[3]),
+        minus_moved: minus_style(backgrounds[4]),
+        plus_moved: plus_style(backgrounds[5]),
+        zero: "syntax normal".to_string(),
+        commit: "plain yellow".to_string(),
+        file: "underline blue".to_string(),
+        hunk_header: "box blue".to_string(),
+        line_numbers_minus: "red".to_string(),
+        line_numbers_plus: "green".to_string(),
+        line_numbers_zero: "bright-black".to_string(),
     }
 }
 
-pub fn get_plus_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_PLUS_COLOR,
-        (true, false) => LIGHT_THEME_PLUS_COLOR_256,
-        (false, true) => DARK_THEME_PLUS_COLOR,
-        (false, false) => DARK_THEME_PLUS_COLOR_256,
-    }
-}
+/// A special color to specify that no color escape codes should be emitted.
+pub const NO_COLOR: Color = Color::BLACK;
 
-pub fn get_plus_emph_color_default(is_light_mode: bool, is_true_color: bool) -> Color {
-    match (is_light_mode, is_true_color) {
-        (true, true) => LIGHT_THEME_PLUS_EMPH_COLOR,
-        (true, false) => LIGHT_THEME_PLUS_EMPH_COLOR_256,
-        (false, true) => DARK_THEME_PLUS_EMPH_COLOR,
-        (false, false) => DARK_THEME_PLUS_EMPH_COLOR_256,
+pub fn get_no_style() -> Style {
+    Style {
+        foreground: NO_COLOR,
+        background: NO_COLOR,
+        font_style: FontStyle::empty(),
     }
 }
 
-const LIGHT_THEME_MINUS_COLOR: Color = Color {
-    r: 0xff,
-    g: 0xe0,
-    b: 0xe0,
-    a: 0xff,
-};
-
-const LIGHT_THEME_MINUS_COLOR_256: Color = Color {
-    r: 224,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
-
-const LIGHT_THEME_MINUS_EMPH_COLOR: Color = Color {
-    r: 0xff,
-    g: 0xc0,
-    b: 0xc0,
-    a: 0xff,
-};
-
-const LIGHT_THEME_MINUS_EMPH_COLOR_256: Color = Color {
-    r: 217,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
-
-const LIGHT_THEME_PLUS_COLOR: Color = Color {
-    r: 0xd0,
-    g: 0xff,
-    b: 0xd0,
-    a: 0xff,
-};
-
-const LIGHT_THEME_PLUS_COLOR_256: Color = Color {
-    r: 194,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
-
-const LIGHT_THEME_PLUS_EMPH_COLOR: Color = Color {
-    r: 0xa0,
-    g: 0xef,
-    b: 0xa0,
-    a: 0xff,
-};
-
-const LIGHT_THEME_PLUS_EMPH_COLOR_256: Color = Color {
-    r: 157,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
+pub const NO_BACKGROUND_COLOR_STYLE_MODIFIER: StyleModifier = StyleModifier {
+    foreground: None,
+    background: Some(NO_COLOR),
+    font_style: Some(FontStyle::empty()),
 };
 
-const DARK_THEME_MINUS_COLOR: Color = Color {
-    r: 0x3f,
-    g: 0x00,
-    b: 0x01,
-    a: 0xff,
-};
+/// A color of a style string: "normal" for no color (the terminal's default), "syntax" for the
+/// colors of syntax highlighting, or a color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StyleColor {
+    Normal,
+    Syntax,
+    Color(Color),
+}
 
-const DARK_THEME_MINUS_COLOR_256: Color = Color {
-    r: 52,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
+/// A style given by a style string, e.g. "bold italic #ffaa00 ul" or "box bold blue". The words
+/// of a style string are, in any order:
+///
+/// - up to two colors: the first is the foreground color, the second the background color. A
+///   color is written as an RGB hex code, an ANSI color name or an ANSI color number, or is
+///   "normal" or "syntax" (see StyleColor).
+/// - the attributes "bold", "italic" and "ul" (underlined).
+/// - for the commit, file and hunk header sections, the decoration of the section: "box",
+///   "underline", "plain" or "omit".
+///
+/// The parts of a style that are not given are taken from the default style of the element.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StyleSpec {
+    pub foreground: Option<StyleColor>,
+    pub background: Option<StyleColor>,
+    pub font_style: Option<FontStyle>,
+    pub decoration: Option<cli::SectionStyle>,
+}
 
-const DARK_THEME_MINUS_EMPH_COLOR: Color = Color {
-    r: 0x90,
-    g: 0x10,
-    b: 0x11,
-    a: 0xff,
-};
+impl StyleSpec {
+    /// Return the style modifier of the style, taking the parts of the style that are not given
+    /// from a default style modifier.
+    pub fn get_style_modifier(&self, default: StyleModifier) -> StyleModifier {
+        let get_color = |color: Option<StyleColor>, default: Option<Color>| match color {
+            Some(StyleColor::Normal) => Some(NO_COLOR),
+            Some(StyleColor::Syntax) => None,
+            Some(StyleColor::Color(color)) => Some(color),
+            None => default,
+        };
+        StyleModifier {
+            foreground: get_color(self.foreground, default.foreground),
+            background: get_color(self.background, default.background),
+            font_style: self.font_style.or(default.font_style),
+        }
+    }
 
-const DARK_THEME_MINUS_EMPH_COLOR_256: Color = Color {
-    r: 124,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
+    /// Return the style, taking the parts of the style that are not given from a default style.
+    pub fn or(self, default: StyleSpec) -> StyleSpec {
+        StyleSpec {
+            foreground: self.foreground.or(default.foreground),
+            background: self.background.or(default.background),
+            font_style: self.font_style.or(default.font_style),
+            decoration: self.decoration.or(default.decoration),
+        }
+    }
 
-const DARK_THEME_PLUS_COLOR: Color = Color {
-    r: 0x00,
-    g: 0x28,
-    b: 0x00,
-    a: 0xff,
-};
+    /// Return the foreground color of the style, if it is given as a color.
+    pub fn get_foreground_color(&self) -> Option<Color> {
+        match self.foreground {
+            Some(StyleColor::Color(color)) => Some(color),
+            _ => None,
+        }
+    }
+}
 
-const DARK_THEME_PLUS_COLOR_256: Color = Color {
-    r: 22,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
+/// Parse a style string (see StyleSpec).
+pub fn parse_style_string(s: &str) -> Result<StyleSpec, String> {
+    let mut spec = StyleSpec::default();
+    for word in s.split_whitespace() {
+        let attribute = match word.to_lowercase().as_str() {
+            "bold" => Some(FontStyle::BOLD),
+            "italic" => Some(FontStyle::ITALIC),
+            "ul" => Some(FontStyle::UNDERLINE),
+            _ => None,
+        };
+        if let Some(attribute) = attribute {
+            spec.font_style = Some(spec.font_style.unwrap_or_else(FontStyle::empty) | attribute);
+            continue;
+        }
+        if let Ok(decoration) = cli::SectionStyle::from_str(word) {
+            if spec.decoration.is_some() {
+                return Err(format!("more than one decoration in \"{}\"", s));
+            }
+            spec.decoration = Some(decoration);
+            continue;
+        }
+        let color = match word.to_lowercase().as_str() {
+            "normal" => StyleColor::Normal,
+            "syntax" => StyleColor::Syntax,
+            _ => StyleColor::Color(
+                parse_color(word).ok_or_else(|| format!("invalid color or attribute: {}", word))?,
+            ),
+        };
+        if spec.foreground.is_none() {
+            spec.foreground = Some(color);
+        } else if spec.background.is_none() {
+            if color == StyleColor::Syntax {
+                return Err("\"syntax\" is not a background color".to_string());
+            }
+            spec.background = Some(color);
+        } else {
+            return Err(format!("more than two colors in \"{}\"", s));
+        }
+    }
+    Ok(spec)
+}
 
-const DARK_THEME_PLUS_EMPH_COLOR: Color = Color {
-    r: 0x00,
-    g: 0x60,
-    b: 0x00,
-    a: 0xff,
-};
+/// Parse a color: an RGB hex code (e.g. "#0e7c0e"), an ANSI color name (e.g. "green") or an ANSI
+/// color number (e.g. "28").
+pub fn parse_color(s: &str) -> Option<Color> {
+    if s.starts_with('#') {
+        Color::from_str(s).ok()
+    } else {
+        s.parse::<u8>()
+            .ok()
+            .and_then(paint::color_from_ansi_number)
+            .or_else(|| paint::color_from_ansi_name(s))
+    }
+}
 
-const DARK_THEME_PLUS_EMPH_COLOR_256: Color = Color {
-    r: 28,
-    g: 0x00,
-    b: 0x00,
-    a: 0x00,
-};
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// A special color to specify that no color escape codes should be emitted.
-pub const NO_COLOR: Color = Color::BLACK;
+    #[test]
+    fn test_parse_style_string() {
+        let orange = Color {
+            r: 0xff,
+            g: 0xaa,
+            b: 0x00,
+            a: 0xff,
+        };
+        assert_eq!(
+            parse_style_string("bold italic #ffaa00 ul"),
+            Ok(StyleSpec {
+                foreground: Some(StyleColor::Color(orange)),
+                font_style: Some(FontStyle::BOLD | FontStyle::ITALIC | FontStyle::UNDERLINE),
+                ..StyleSpec::default()
+            })
+        );
+        assert_eq!(
+            parse_style_string("syntax #ffaa00"),
+            Ok(StyleSpec {
+                foreground: Some(StyleColor::Syntax),
+                background: Some(StyleColor::Color(orange)),
+                ..StyleSpec::default()
+            })
+        );
+        assert_eq!(
+            parse_style_string("box Normal red"),
+            Ok(StyleSpec {
+                foreground: Some(StyleColor::Normal),
+                background: paint::color_from_ansi_name("red").map(StyleColor::Color),
+                decoration: Some(cli::SectionStyle::Box),
+                ..StyleSpec::default()
+            })
+        );
+        assert_eq!(parse_style_string(""), Ok(StyleSpec::default()));
+        assert!(parse_style_string("bold reddish").is_err());
+        assert!(parse_style_string("red green blue").is_err());
+        assert!(parse_style_string("red syntax").is_err());
+        assert!(parse_style_string("box underline").is_err());
+    }
 
-pub fn get_no_style() -> Style {
-    Style {
-        foreground: NO_COLOR,
-        background: NO_COLOR,
-        font_style: FontStyle::empty(),
+    #[test]
+    fn test_get_style_modifier() {
+        let red = paint::color_from_ansi_name("red");
+        let default = StyleModifier {
+            foreground: None,
+            background: red,
+            font_style: Some(FontStyle::empty()),
+        };
+        let spec = parse_style_string("bold normal").unwrap();
+        assert_eq!(
+            spec.get_style_modifier(default),
+            StyleModifier {
+                foreground: Some(NO_COLOR),
+                background: red,
+                font_style: Some(FontStyle::BOLD),
+            }
+        );
+        // The parts of a style that are not given are taken from a default style.
+        let spec = parse_style_string("syntax")
+            .unwrap()
+            .or(parse_style_string("bold blue normal").unwrap());
+        assert_eq!(
+            spec.get_style_modifier(default),
+            StyleModifier {
+                foreground: None,
+                background: Some(NO_COLOR),
+                font_style: Some(FontStyle::BOLD),
+            }
+        );
     }
 }
-
-pub const NO_BACKGROUND_COLOR_STYLE_MODIFIER: StyleModifier = StyleModifier {
-    foreground: None,
-    background: Some(NO_COLOR),
-    font_style: None,
-};
@@ -0,0 +1,348 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use syntect::highlighting::StyleModifier;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ansi;
+use crate::config::Config;
+use crate::delta;
+use crate::paint::Painter;
+
+lazy_static! {
+    /// A removed or added section of a line of `git diff --word-diff` output, e.g. "[-x-]" or
+    /// "{+y+}".
+    static ref WORD_DIFF_MARKER_REGEX: Regex = Regex::new(r"\[-(.*?)-\]|\{\+(.*?)\+\}").unwrap();
+}
+
+/// The kind of a section of a word diff line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WordDiffKind {
+    Zero,
+    Minus,
+    Plus,
+}
+
+/// Return true if a line of a hunk is evidently a line of `git diff --word-diff` or
+/// `git diff --color-words` output. Such lines have no marker column, so a line starting with
+/// anything other than a marker is a word diff line. A line starting with a space is one only if
+/// it holds words colored as removed or added by git: an unchanged line of code may itself hold
+/// "[-...-]" or "{+...+}".
+fn is_word_diff_line(line: &str, raw_line: &str) -> bool {
+    match line.chars().next() {
+        // "\ No newline at end of file", or the "~" line ending each line of
+        // `--word-diff=porcelain` output, which has marker columns.
+        None | Some('+') | Some('-') | Some('\\') => false,
+        Some('~') if line == "~" => false,
+        Some(' ') => get_colored_sections(raw_line)
+            .iter()
+            .any(|(kind, _)| *kind != WordDiffKind::Zero),
+        Some(_) => true,
+    }
+}
+
+/// Return true if the lines of a hunk, following its header, are the lines of
+/// `git diff --word-diff` or `git diff --color-words` output, given the number of lines of each of
+/// the hunk's ranges. The lines are read until the lines of a unified diff hunk would end: a word
+/// diff hunk has a line with no marker column, or a line holding colored removed or added words,
+/// before then. Lines such as the indented unchanged lines starting a hunk are not enough to tell.
+pub fn is_word_diff_hunk<I>(lines: I, hunk_lines_remaining: &[usize]) -> bool
+where
+    I: Iterator<Item = (String, String)>,
+{
+    let mut hunk_lines_remaining = hunk_lines_remaining.to_vec();
+    for (line, raw_line) in lines {
+        if !delta::count_hunk_line(&line, 1, &mut hunk_lines_remaining) {
+            // The end of a truncated hunk is not a word diff line.
+            return is_word_diff_line(&line, &raw_line)
+                && !["commit ", "diff ", "@@"]
+                    .iter()
+                    .any(|prefix| line.starts_with(prefix));
+        }
+        if is_word_diff_line(&line, &raw_line) {
+            return true;
+        }
+        if hunk_lines_remaining.iter().all(|n| *n == 0) {
+            return false;
+        }
+    }
+    false
+}
+
+/// Split a word diff line into its unchanged, removed and added sections. The sections are marked
+/// by "[-...-]" and "{+...+}", or by red and green colors if git's word diff is colored. If
+/// keep_markers is true, the "[-...-]" and "{+...+}" markers are kept in the text of the sections.
+pub fn parse_word_diff_line(
+    line: &str,
+    raw_line: &str,
+    keep_markers: bool,
+) -> Vec<(WordDiffKind, String)> {
+    if !WORD_DIFF_MARKER_REGEX.is_match(line) {
+        return get_colored_sections(raw_line);
+    }
+    let mut sections = Vec::new();
+    let mut section_start = 0;
+    for captures in WORD_DIFF_MARKER_REGEX.captures_iter(line) {
+        let marked = captures.get(0).unwrap();
+        if marked.start() > section_start {
+            sections.push((
+                WordDiffKind::Zero,
+                line[section_start..marked.start()].to_string(),
+            ));
+        }
+        let (kind, text) = match captures.get(1) {
+            Some(text) => (WordDiffKind::Minus, text),
+            None => (WordDiffKind::Plus, captures.get(2).unwrap()),
+        };
+        let text = if keep_markers {
+            marked.as_str()
+        } else {
+            text.as_str()
+        };
+        sections.push((kind, text.to_string()));
+        section_start = marked.end();
+    }
+    if line.len() > section_start {
+        sections.push((WordDiffKind::Zero, line[section_start..].to_string()));
+    }
+    sections
+}
+
+/// Split a line of `git diff --color-words` output into its sections of visible text. Sections in
+/// red are removed, and sections in green are added.
+fn get_colored_sections(raw_line: &str) -> Vec<(WordDiffKind, String)> {
+    let mut sections: Vec<(WordDiffKind, String)> = Vec::new();
+    let mut kind = WordDiffKind::Zero;
+    let mut i = 0;
+    while i < raw_line.len() {
+        if let Some(length) = ansi::get_escape_sequence_length(&raw_line[i..]) {
+            let sequence = &raw_line[i..i + length];
+            if sequence.starts_with("\x1b[") && sequence.ends_with('m') {
+                kind = get_sgr_kind(&sequence[2..length - 1], kind);
+            }
+            i += length;
+            continue;
+        }
+        let c = raw_line[i..].chars().next().unwrap();
+        match sections.last_mut() {
+            Some((last_kind, text)) if *last_kind == kind => text.push(c),
+            _ => sections.push((kind, c.to_string())),
+        }
+        i += c.len_utf8();
+    }
+    sections
+}
+
+/// Return the kind of the text following an SGR sequence with the given parameters, given the
+/// kind of the text preceding it.
+fn get_sgr_kind(parameters: &str, kind: WordDiffKind) -> WordDiffKind {
+    let mut parameters = parameters.split(';');
+    let mut kind = kind;
+    while let Some(parameter) = parameters.next() {
+        match parameter {
+            "" | "0" | "39" => kind = WordDiffKind::Zero,
+            "31" | "91" => kind = WordDiffKind::Minus,
+            "32" | "92" => kind = WordDiffKind::Plus,
+            // An extended color: skip its "5;n" or "2;r;g;b" parameters.
+            "38" | "48" => match parameters.next() {
+                Some("5") => {
+                    parameters.next();
+                }
+                Some("2") => {
+                    parameters.nth(2);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    kind
+}
+
+/// Paint a word diff line. Removed and added words are painted with the emphasis styles over the
+/// syntax highlighted text of the line. A line holding only removed (or only added) words is
+/// painted as a removed (or added) line, and its line number is that of the old (or new) file
+/// only.
+pub fn paint_word_diff_line(painter: &mut Painter, line: &str, raw_line: &str, config: &Config) {
+    let sections = parse_word_diff_line(line, raw_line, config.color_only);
+    let has_kind = |kind| sections.iter().any(|(k, _)| *k == kind);
+    let (has_minus, has_plus) = (has_kind(WordDiffKind::Minus), has_kind(WordDiffKind::Plus));
+    let has_zero = has_kind(WordDiffKind::Zero) || sections.is_empty();
+    let line_style_modifier = match (has_zero, has_minus, has_plus) {
+        (false, true, false) => Some(config.minus_style_modifier),
+        (false, false, true) => Some(config.plus_style_modifier),
+        _ => None,
+    };
+    let background_style_modifier = line_style_modifier.unwrap_or(config.zero_style_modifier);
+    let get_style_modifier = |kind| -> StyleModifier {
+        line_style_modifier.unwrap_or(match kind {
+            WordDiffKind::Zero => config.zero_style_modifier,
+            WordDiffKind::Minus => config.minus_emph_style_modifier,
+            WordDiffKind::Plus => config.plus_emph_style_modifier,
+        })
+    };
+
+    // Under --color-only the line is written as it is, without the space replacing the marker
+    // column of other diff lines.
+    let (mut text, prefix) = if config.color_only {
+        (String::new(), "")
+    } else {
+        (" ".to_string(), " ")
+    };
+    let mut section_ranges = Vec::new();
+    let mut section_start = 0;
+    for (kind, section) in &sections {
+        text.push_str(&delta::expand_tabs(
+            section.graphemes(true),
+            config.tab_width,
+        ));
+        section_ranges.push((*kind, section_start, text.len()));
+        section_start = text.len();
+    }
+    text.push('\n');
+    let mut diff_style_sections: Vec<_> = section_ranges
+        .iter()
+        .map(|(kind, start, end)| (get_style_modifier(*kind), &text[*start..*end]))
+        .collect();
+    diff_style_sections.push((background_style_modifier, &text[section_start..]));
+
+    let syntax_style_sections =
+        Painter::get_line_syntax_style_sections(&text, &mut painter.highlighter, config, true);
+    let (in_old_file, in_new_file) = (has_zero || has_minus, has_zero || has_plus);
+    Painter::paint_lines_with_line_numbers(
+        vec![syntax_style_sections],
+        vec![diff_style_sections],
+        (
+            Some(painter.minus_line_number).filter(|_| in_old_file),
+            Some(painter.plus_line_number).filter(|_| in_new_file),
+            painter.line_number_width,
+        ),
+        &mut painter.output_buffer,
+        config,
+        prefix,
+        background_style_modifier,
+    );
+    if in_old_file {
+        painter.minus_line_number += 1;
+    }
+    if in_new_file {
+        painter.plus_line_number += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use WordDiffKind::*;
+
+    fn sections(sections: &[(WordDiffKind, &str)]) -> Vec<(WordDiffKind, String)> {
+        sections
+            .iter()
+            .map(|(kind, text)| (*kind, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_word_diff_line() {
+        let line = "    let [-x-]{+y+} = 1;";
+        assert_eq!(
+            parse_word_diff_line(line, line, false),
+            sections(&[
+                (Zero, "    let "),
+                (Minus, "x"),
+                (Plus, "y"),
+                (Zero, " = 1;")
+            ])
+        );
+        assert_eq!(
+            parse_word_diff_line(line, line, true),
+            sections(&[
+                (Zero, "    let "),
+                (Minus, "[-x-]"),
+                (Plus, "{+y+}"),
+                (Zero, " = 1;")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_colored_word_diff_line() {
+        let raw_line = "    let \x1b[31mx\x1b[m\x1b[32my\x1b[m = 1;\x1b[m";
+        assert_eq!(
+            parse_word_diff_line("    let xy = 1;", raw_line, false),
+            sections(&[
+                (Zero, "    let "),
+                (Minus, "x"),
+                (Plus, "y"),
+                (Zero, " = 1;")
+            ])
+        );
+        assert_eq!(
+            get_colored_sections("\x1b[1;31mx\x1b[38;5;32my"),
+            sections(&[(Minus, "xy")])
+        );
+    }
+
+    #[test]
+    fn test_is_word_diff_line() {
+        assert!(is_word_diff_line("fn main() {", "fn main() {"));
+        assert!(is_word_diff_line("{+a+}", "{+a+}"));
+        assert!(is_word_diff_line(" [-a-]", " \x1b[31m[-a-]\x1b[m"));
+        assert!(!is_word_diff_line(" x[-1-]", " x[-1-]"));
+        assert!(is_word_diff_line(" a", " \x1b[32ma\x1b[m"));
+        assert!(!is_word_diff_line(" a", " a\x1b[m"));
+        assert!(!is_word_diff_line("+a", "\x1b[32m+a\x1b[m"));
+        assert!(!is_word_diff_line("~", "~"));
+        assert!(!is_word_diff_line("", ""));
+    }
+
+    #[test]
+    fn test_is_word_diff_hunk() {
+        let is_word_diff_hunk = |lines: &[&str], hunk_lines_remaining: &[usize]| {
+            is_word_diff_hunk(
+                lines
+                    .iter()
+                    .map(|line| (line.to_string(), line.to_string())),
+                hunk_lines_remaining,
+            )
+        };
+        // Indented unchanged lines, followed by a line holding removed and added words colored
+        // by git.
+        assert!(super::is_word_diff_hunk(
+            vec![
+                ("    let a = 1;", "    let a = 1;"),
+                ("    let b = 2;", "    let b = 2;"),
+                (
+                    "    let [-x-]{+y+} = 3;",
+                    "    let \x1b[31m[-x-]\x1b[m\x1b[32m{+y+}\x1b[m = 3;"
+                ),
+            ]
+            .into_iter()
+            .map(|(line, raw_line)| (line.to_string(), raw_line.to_string())),
+            &[3, 3]
+        ));
+        // Unchanged lines of code holding "[-...-]" and "{+...+}" that are not colored.
+        assert!(!is_word_diff_hunk(
+            &[
+                " let y = x[-1-];",
+                " let z = {+ a +};",
+                "-let a = 1;",
+                "+let a = 2;"
+            ],
+            &[3, 3]
+        ));
+        assert!(is_word_diff_hunk(
+            &["    let a = 1;", "}", "diff --git a/b.rs b/b.rs"],
+            &[3, 3]
+        ));
+        assert!(!is_word_diff_hunk(
+            &[" let a = 1;", "-let x = 3;", "+let y = 3;"],
+            &[2, 2]
+        ));
+        // A truncated hunk.
+        assert!(!is_word_diff_hunk(
+            &[" let a = 1;", "diff --git a/b.rs b/b.rs"],
+            &[2, 2]
+        ));
+    }
+}